@@ -26,5 +26,5 @@ pub fn default_config_dir() -> io::Result<PathBuf> {
 }
 
 pub fn history_file(lang_name: &str) -> io::Result<PathBuf> {
-    Ok(default_config_dir()?.join(&format!(".{}_repl_history", lang_name)))
+    Ok(default_config_dir()?.join(format!(".{}_repl_history", lang_name)))
 }
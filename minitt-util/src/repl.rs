@@ -41,7 +41,7 @@ impl Completer for MiniHelper {
             .all_cmd
             .iter()
             .filter(|s| s.starts_with(subs))
-            .map(|s| s.clone())
+            .cloned()
             .map(|str| Pair {
                 display: str.clone(),
                 replacement: str,
@@ -59,7 +59,6 @@ impl Hinter for MiniHelper {
         self.all_cmd
             .iter()
             .filter(|cmd| cmd.starts_with(line))
-            .cloned()
             .map(|cmd| cmd[pos..].to_string())
             .next()
     }
@@ -104,7 +103,7 @@ pub fn create_editor(all_cmd: &[&str]) -> Editor<MiniHelper> {
 pub fn repl_plain<TCS>(
     mut tcs: TCS,
     prompt: &str,
-    welcome_message: impl FnOnce(ReplEnvType) -> (),
+    welcome_message: impl FnOnce(ReplEnvType),
     work: impl Fn(TCS, ReplEnvType, &str) -> Option<TCS>,
 ) {
     welcome_message(ReplEnvType::Plain);
@@ -127,7 +126,7 @@ pub fn repl_rich<TCS>(
     prompt: &str,
     create_editor: impl FnOnce() -> Editor<MiniHelper>,
     history: Option<PathBuf>,
-    welcome_message: impl FnOnce(ReplEnvType) -> (),
+    welcome_message: impl FnOnce(ReplEnvType),
     work: impl Fn(TCS, ReplEnvType, &str) -> Option<TCS>,
 ) {
     let mut r = create_editor();
@@ -172,7 +171,7 @@ pub fn repl<TCS>(
     repl_kind: ReplEnvType,
     create_editor: impl FnOnce() -> Editor<MiniHelper>,
     history: impl FnOnce() -> Option<PathBuf>,
-    welcome_message: impl FnOnce(ReplEnvType) -> (),
+    welcome_message: impl FnOnce(ReplEnvType),
     work: impl Fn(TCS, ReplEnvType, &str) -> Option<TCS>,
 ) {
     use ReplEnvType::*;
@@ -21,34 +21,44 @@ sub ntr {return colored $_[0], 'green';}
 sub red {return colored $_[0], 'red';}
 sub redy {return colored $_[0], 'bold red';}
 
+# Compare the output of `$cmd` with the golden value in `$out`.
+sub golden {
+    my ($case, $cmd, $out) = @_;
+    `touch $out`;
+    my $diff = `$cmd 2>&1 | diff --strip-trailing-cr - $out`;
+    if (length $diff) {
+        push @failure, $case;
+        say red(" Failed $case:");
+        map {say red("  $_")} split /\n/, $diff;
+        return if $isCI != 0;
+        print colored('  Update the golden value (y/N)? ', 'cyan');
+        (readline =~ s/[\n\r]//rg) eq 'y' ? `$cmd > $out 2>&1`
+            : say colored(<<"HINT", 'bold yellow');
+  Leaving it alone.
+  To update the golden value, run `test.pl` in `samples` directly.
+  Command: $cmd
+HINT
+    } else {
+        say ntr(" Passed $case");
+        $success++;
+    }
+}
+
 foreach my $fixture (map {substr $_, 0, -1}
         split /[ \t\n]+/, `ls -t -d ./*/`) {
     say colored("Fixture $fixture:", 'yellow');
     my $fixtureFlags = -e "$fixture.flags" ? `cat $fixture.flags` : '';
-    foreach my $case (split /[ \t\n]+/, `ls -t -G $fixture/*.minitt`) {
-        my $out = $case =~ s/\.minitt/\.out/rg;
-        my $flagFile = $case =~ s/\.minitt/\.flags/rg;
+    foreach my $case (split /[ \t\n]+/, `ls -t -G $fixture/*.minitt $fixture/*.lminitt 2>/dev/null`) {
+        my $out = $case =~ s/\.l?minitt$/\.out/rg;
+        my $flagFile = $case =~ s/\.l?minitt$/\.flags/rg;
         my $caseFlags = -e $flagFile ? `cat $flagFile` : '';
-        `touch $out`;
         my $flags = "$fixtureFlags $caseFlags" =~ s/[\n|\r]//rg;
-        my $cmd = "$minittc $flags $case";
-        my $diff = `$cmd 2>&1 | diff --strip-trailing-cr - $out`;
-        if (length $diff) {
-            push @failure, $case;
-            say red(" Failed $case:");
-            map {say red("  $_")} split /\n/, $diff;
-            next if $isCI != 0;
-            print colored('  Update the golden value (y/N)? ', 'cyan');
-            (readline =~ s/[\n\r]//rg) eq 'y' ? `$cmd > $out 2>&1`
-                : say colored(<<"HINT", 'bold yellow');
-  Leaving it alone.
-  To update the golden value, run `test.pl` in `samples` directly.
-  Command: $cmd
-HINT
-        } else {
-            say ntr(" Passed $case");
-            $success++;
-        }
+        golden $case, "$minittc $flags $case", $out;
+    }
+    # Projects are built, with the output in `build.out` next to the manifest
+    foreach my $manifest (split /[ \t\n]+/, `ls $fixture/*/minitt.toml 2>/dev/null`) {
+        my $out = $manifest =~ s/minitt\.toml$/build.out/rg;
+        golden $manifest, "$minittc build --manifest-path $manifest", $out;
     }
 }
 
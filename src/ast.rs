@@ -1,7 +1,12 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::rc::Rc;
 
 use either::Either;
+#[cfg(feature = "serial")]
+use serde::{Deserialize, Serialize};
+
+use crate::case::SourceClauses;
 
 pub type Level = u32;
 
@@ -10,6 +15,7 @@ pub type Level = u32;
 ///
 /// $M,\ N,\ A,\ B ::=$
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
 pub enum Expression {
     /// $0$
     Unit,
@@ -38,22 +44,33 @@ pub enum Expression {
     /// `\Sigma a: b. c` or `A * B`
     Sigma(Typed, Box<Self>),
     /// $\lambda p. M$,
-    /// `\lambda a. c`, the optional value is the type of the argument.<br/>
-    /// This cannot be specified during parsing because it's used for generated intermediate values
-    /// during type-checking.
-    Lambda(Pattern, Option<AnonymousValue>, Box<Self>),
+    /// `\lambda a. c` or `\lambda (a : A). c`, the optional expression is the type of the
+    /// parameter.<br/>
+    /// Lambdas with the parameter type specified can be inferred.
+    Lambda(Pattern, Option<Box<Self>>, Box<Self>),
     /// $M.1$,
     /// `bla.1`
     First(Box<Self>),
     /// $M.2$,
     /// `bla.2`
     Second(Box<Self>),
+    /// This is an extension to Mini-TT, `bla.x`, the field `x` of a record.<br/>
+    /// Records are nested `Sigma`s binding the field names, ending with `1`, the type-checker
+    /// finds the position of the field and elaborates it to `.2`s followed by a `.1`.
+    Field(Box<Self>, String),
     /// $M \ N$,
     /// `f a`
     Application(Box<Self>, Box<Self>),
     /// $M, N$,
     /// `a, b`
     Pair(Box<Self>, Box<Self>),
+    /// This is an extension to Mini-TT, `{ x = a; y = b }`, a record literal.<br/>
+    /// The type-checker matches the fields with the ones of the record type by name, and
+    /// elaborates it to the pairs of the fields in the order of the type, `a, b, 0`.
+    Record(Vec<(String, Self)>),
+    /// This is an extension to Mini-TT, $(M : A)$,
+    /// `(a : b)`, the expression with its type specified.
+    Ascription(Box<Self>, Box<Self>),
     /// $\textsf{c}\ M$, `Cons a`
     Constructor(String, Box<Self>),
     /// `const a = b`, this is an extension: a declaration whose type-signature is inferred.
@@ -62,6 +79,213 @@ pub enum Expression {
     /// $D; M$,
     /// `let bla` or `rec bla`
     Declaration(Box<Declaration>, Box<Self>),
+    /// This is an extension to Mini-TT, `infixl 6 + = plus`, a fixity declaration.
+    Fixity(Box<Fixity>, Box<Self>),
+    /// This is an extension to Mini-TT, `{-# BUILTIN NATURAL nat Zero Suc #-}`, a pragma
+    /// declaring the natural number type of the decimal literals.
+    Natural(Box<Natural>, Box<Self>),
+    /// This is an extension to Mini-TT, `import data.list`, bringing the definitions of another
+    /// file into scope.<br/>
+    /// Modules are loaded by the caller of the type-checker, which only sees the rest.
+    Import(Box<Import>, Box<Self>),
+    /// This is an extension to Mini-TT, `module Nat { let add ...; }`, a block of declarations
+    /// whose names are qualified by the module name in the rest, `Nat.add`.
+    Module(Box<Module>, Box<Self>),
+    /// This is an extension to Mini-TT, `open Nat`, bringing the names of a module into scope
+    /// unqualified in the rest.
+    Open(Box<Open>, Box<Self>),
+    /// This is an extension to Mini-TT, `export (a, b, C)` at the beginning of a file, the names
+    /// visible to the importing modules.
+    Export(Box<Export>, Box<Self>),
+    /// This is an extension to Mini-TT, `String`, the built-in type of strings.
+    StringType,
+    /// This is an extension to Mini-TT, `Char`, the built-in type of characters.
+    CharType,
+    /// This is an extension to Mini-TT, `"bla"` or `'c'`.
+    Literal(Literal),
+    /// This is an extension to Mini-TT, `primStringAppend`, a built-in function.
+    Primitive(Primitive),
+    /// This is an extension to Mini-TT, the source code location of an expression.<br/>
+    /// It does not affect the semantics of the wrapped expression.
+    Located(Span, Box<Self>),
+    /// This is an extension to Mini-TT, the case tree of a `split` or a `match` with the clauses
+    /// it's compiled from. The type-checker finds the unreachable clauses with them, and they're
+    /// printed instead of the case tree.<br/>
+    /// It does not affect the semantics of the wrapped expression.
+    Clauses(Box<SourceClauses>, Box<Self>),
+}
+
+impl Expression {
+    /// Remove the outermost `Located` wrappers.
+    pub fn unlocated(&self) -> &Self {
+        match self {
+            Expression::Located(_, expression) => expression.unlocated(),
+            expression => expression,
+        }
+    }
+
+    /// Mutable version of `unlocated`.
+    pub fn unlocated_mut(&mut self) -> &mut Self {
+        match self {
+            Expression::Located(_, expression) => expression.unlocated_mut(),
+            expression => expression,
+        }
+    }
+
+    /// The constructor call `C a b c`, parsed as `((C a) b) c`, with the arguments collected as
+    /// the fields of the constructor, `C (a, (b, c))`. `None` if it's not such an application.<br/>
+    /// The type-checker elaborates the calls to the fields, so they're evaluated as usual.
+    pub fn constructor_call(&self) -> Option<Self> {
+        // The head is found first, the arguments are only cloned for a constructor
+        let mut function = self;
+        while let Expression::Application(applied, _) = function {
+            function = applied.unlocated();
+        }
+        let (name, first) = match (self, function) {
+            (Expression::Application(..), Expression::Constructor(name, first)) => (name, first),
+            _ => return None,
+        };
+        let mut fields = None;
+        let mut function = self;
+        // The arguments are collected from the last one
+        while let Expression::Application(applied, argument) = function {
+            let argument = (**argument).clone();
+            fields = Some(match fields {
+                None => argument,
+                Some(rest) => Expression::Pair(Box::new(argument), Box::new(rest)),
+            });
+            function = applied.unlocated();
+        }
+        let fields = Expression::Pair(first.clone(), Box::new(fields?));
+        Some(Expression::Constructor(name.clone(), Box::new(fields)))
+    }
+
+    /// The names defined by the declarations at the beginning of the expression, in order, and
+    /// whether they're private. Names of the nested modules are qualified by the module names.
+    pub fn definitions(&self) -> Vec<(String, bool)> {
+        let mut definitions = Vec::new();
+        let mut expression = self;
+        loop {
+            expression = match expression {
+                Expression::Declaration(declaration, rest) => {
+                    let private = declaration.is_private;
+                    let names = declaration.pattern.names().into_iter();
+                    definitions.extend(names.map(|name| (name.to_owned(), private)));
+                    rest
+                }
+                Expression::Constant(pattern, _, rest) => {
+                    let names = pattern.names().into_iter();
+                    definitions.extend(names.map(|name| (name.to_owned(), false)));
+                    rest
+                }
+                Expression::Module(module, rest) => {
+                    let names = module.names().into_iter();
+                    definitions.extend(names.map(|name| (module.qualify(&name), false)));
+                    rest
+                }
+                Expression::Fixity(_, rest)
+                | Expression::Natural(_, rest)
+                | Expression::Import(_, rest)
+                | Expression::Open(_, rest)
+                | Expression::Export(_, rest) => rest,
+                Expression::Located(_, expression) => expression,
+                _ => return definitions,
+            }
+        }
+    }
+
+    /// The `BUILTIN NATURAL` pragmas among the declarations at the beginning of the expression,
+    /// in order.
+    pub fn naturals(&self) -> Vec<Natural> {
+        let mut naturals = Vec::new();
+        let mut expression = self;
+        loop {
+            expression = match expression {
+                Expression::Natural(natural, rest) => {
+                    naturals.push((**natural).clone());
+                    rest
+                }
+                Expression::Declaration(_, rest)
+                | Expression::Constant(_, _, rest)
+                | Expression::Module(_, rest)
+                | Expression::Fixity(_, rest)
+                | Expression::Import(_, rest)
+                | Expression::Open(_, rest)
+                | Expression::Export(_, rest) => rest,
+                Expression::Located(_, expression) => expression,
+                _ => return naturals,
+            }
+        }
+    }
+
+    /// The modules imported at the beginning of the expression, in order.
+    pub fn imports(&self) -> Vec<&Import> {
+        let mut imports = Vec::new();
+        let mut expression = self;
+        loop {
+            expression = match expression {
+                Expression::Import(import, rest) => {
+                    imports.push(&**import);
+                    rest
+                }
+                Expression::Export(_, rest) => rest,
+                _ => return imports,
+            }
+        }
+    }
+
+    /// Move version of `unlocated`.
+    pub fn into_unlocated(self) -> Self {
+        match self {
+            Expression::Located(_, expression) => expression.into_unlocated(),
+            expression => expression,
+        }
+    }
+}
+
+/// Source code location of a syntax tree node.<br/>
+/// Byte offsets are used for slicing the source, line and column (both start from 1) are used for
+/// error reporting.
+///
+/// Like `AnonymousValue`, it does not do `Eq` comparison.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Span {
+    /// Start byte offset (inclusive).
+    pub start: usize,
+    /// End byte offset (exclusive).
+    pub end: usize,
+    /// Line number of the start position.
+    pub line: usize,
+    /// Column number of the start position.
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// The span starting from `self` and ending at `other`.
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            end: other.end,
+            ..self
+        }
+    }
+}
+
+impl Eq for Span {}
+
+impl PartialEq<Span> for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
 /// Just a wrapper for a value but does not do `Eq` comparison.
@@ -111,6 +335,7 @@ pub fn branch_to_righted(branch: Branch, context: Telescope) -> CaseTree {
 /// $p:A$, Pattern with type explicitly specified.
 /// This is just a helper struct.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
 pub struct Typed {
     pub pattern: Pattern,
     pub expression: Box<Expression>,
@@ -170,6 +395,14 @@ pub enum Value {
     /// $[k]$.
     /// Neutral form.
     Neutral(Neutral),
+    /// Canonical form: the built-in string type.
+    StringType,
+    /// Canonical form: the built-in character type.
+    CharType,
+    /// Canonical form: a string or character literal.
+    Literal(Literal),
+    /// Canonical form: a built-in function applied to fewer arguments than its arity.
+    Primitive(Primitive, Vec<Self>),
 }
 
 /// Generic definition for two kinds of neutral terms.
@@ -182,6 +415,10 @@ pub enum GenericNeutral<Value: Clone> {
     /// $\textsf{x}_n$.
     /// Neutral form: stuck on a free variable.
     Generated(u32),
+    /// This is an extension to Mini-TT, the postulate bound to the name of a definition that
+    /// failed to type-check, stuck like a free variable.<br/>
+    /// The index tells apart the postulates of definitions of the same name.
+    Postulate(u32, String),
     /// $k\ v$.
     /// Neutral form: stuck on applying on a free variable.
     Application(Box<Self>, Box<Value>),
@@ -197,6 +434,8 @@ pub enum GenericNeutral<Value: Clone> {
         GenericBranch<GenericCase<Either<Value, Expression>, Value>>,
         Box<Self>,
     ),
+    /// Neutral form: stuck on applying a built-in function on a free variable.
+    Primitive(Primitive, Vec<Value>),
 }
 
 /// $k ::= k(v)$.
@@ -206,11 +445,12 @@ pub type Neutral = GenericNeutral<Value>;
 /// `Patt` in Mini-TT.
 ///
 /// $p ::=$
-#[derive(Debug, Clone, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
 pub enum Pattern {
     /// $p,p$,
     /// Pair pattern. This sounds like trivial and useless, but we can achieve mutual recursion by
-    /// using this pattern.
+    /// using this pattern, `mutual` blocks are desugared into declarations of pair patterns.
     Pair(Box<Self>, Box<Self>),
     /// \_,
     /// Unit pattern, used for introducing anonymous definitions.
@@ -218,6 +458,66 @@ pub enum Pattern {
     /// $x$,
     /// Variable name pattern, the most typical pattern.
     Var(String),
+    /// This is an extension to Mini-TT, the source code location of a pattern.<br/>
+    /// Like `Expression::Located`, it does not affect the semantics of the wrapped pattern, and
+    /// it's ignored by the `Eq` comparison.<br/>
+    /// The location is boxed to keep patterns, which are everywhere in values, small.
+    Located(Box<Span>, Box<Self>),
+}
+
+impl Pattern {
+    /// `pattern` located at `span`.
+    pub fn located(span: Span, pattern: Self) -> Self {
+        Pattern::Located(Box::new(span), Box::new(pattern))
+    }
+
+    /// Remove the `Located` wrappers of the pattern itself, not the ones of its components.
+    pub fn unlocated(&self) -> &Self {
+        match self {
+            Pattern::Located(_, pattern) => pattern.unlocated(),
+            pattern => pattern,
+        }
+    }
+
+    /// The source code location of the pattern, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Pattern::Located(span, _) => Some(**span),
+            _ => None,
+        }
+    }
+}
+
+impl Eq for Pattern {}
+
+impl PartialEq<Pattern> for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd<Pattern> for Pattern {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pattern {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use crate::ast::Pattern::*;
+        match (self.unlocated(), other.unlocated()) {
+            (Pair(first, second), Pair(other_first, other_second)) => first
+                .cmp(other_first)
+                .then_with(|| second.cmp(other_second)),
+            (Pair(_, _), _) => Ordering::Less,
+            (_, Pair(_, _)) => Ordering::Greater,
+            (Unit, Unit) => Ordering::Equal,
+            (Unit, _) => Ordering::Less,
+            (_, Unit) => Ordering::Greater,
+            (Var(name), Var(other_name)) => name.cmp(other_name),
+            _ => unreachable!(),
+        }
+    }
 }
 
 /// `Decl` in Mini-TT.
@@ -227,6 +527,7 @@ pub enum Pattern {
 /// coding convenience I've made it a struct with a `bool` member
 /// (`is_recursive`) to indicate whether it's recursive.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
 pub struct Declaration {
     /// $p$ in syntax.
     pub pattern: Pattern,
@@ -238,6 +539,11 @@ pub struct Declaration {
     pub body: Expression,
     /// Whether the $\textsf{rec}$ is present.
     pub is_recursive: bool,
+    /// This is an extension, `private let`, the declaration is not visible to the importing
+    /// modules, or outside of the `module` block.
+    pub is_private: bool,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
 }
 
 impl Declaration {
@@ -255,6 +561,8 @@ impl Declaration {
             signature,
             body,
             is_recursive,
+            is_private: false,
+            span: None,
         }
     }
 
@@ -277,6 +585,302 @@ impl Declaration {
     ) -> Self {
         Self::new(pattern, prefix_parameters, signature, body, true)
     }
+
+    /// The body abstracted over the prefix parameters, `\lambda (b: c). f b` for
+    /// `let a (b: c): d = f b;`.
+    pub fn lifted_body(&self) -> Expression {
+        (self.prefix_parameters.iter().rev()).fold(self.body.clone(), |body, parameter| {
+            let parameter_type = Some(parameter.expression.clone());
+            Expression::Lambda(parameter.pattern.clone(), parameter_type, Box::new(body))
+        })
+    }
+}
+
+/// Associativity of an infix operator.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub enum Associativity {
+    /// `infixl`, `a + b + c` is `(a + b) + c`.
+    Left,
+    /// `infixr`, `a :: b :: c` is `a :: (b :: c)`.
+    Right,
+}
+
+/// This is an extension to Mini-TT, `infixl 6 + = plus`.<br/>
+/// The operator `+` is bound to `plus`, and `a + b` is parsed as `(+) a b`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Fixity {
+    pub operator: String,
+    pub associativity: Associativity,
+    /// From 0 to 9, operators of higher precedence bind tighter.
+    pub precedence: u8,
+    /// The name bound to the operator.
+    pub name: String,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Fixity {
+    /// Constructor
+    pub fn new(
+        operator: String,
+        associativity: Associativity,
+        precedence: u8,
+        name: String,
+    ) -> Self {
+        Self {
+            operator,
+            associativity,
+            precedence,
+            name,
+            span: None,
+        }
+    }
+
+    /// The binding of the operator, `const + = plus; rest`.
+    pub fn into_constant(self, rest: Expression) -> Expression {
+        let name = Expression::Var(self.name);
+        let name = match self.span {
+            Some(span) => Expression::Located(span, Box::new(name)),
+            None => name,
+        };
+        Expression::Constant(Pattern::Var(self.operator), Box::new(name), Box::new(rest))
+    }
+}
+
+/// This is an extension to Mini-TT, `{-# BUILTIN NATURAL nat Zero Suc #-}`.<br/>
+/// Decimal literals are constructor chains of `nat`, `2` is `Suc (Suc Zero)`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Natural {
+    /// The natural number type.
+    pub name: String,
+    /// The constructor of zero, whose payload is the unit.
+    pub zero: String,
+    /// The constructor of successors, whose payload is the natural number type.
+    pub suc: String,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Natural {
+    /// The largest decimal literal, the constructor chains of larger numbers are nested too
+    /// deeply to be evaluated and type-checked recursively.
+    pub const MAX_LITERAL: usize = 64;
+
+    /// Constructor
+    pub fn new(name: String, zero: String, suc: String) -> Self {
+        Self {
+            name,
+            zero,
+            suc,
+            span: None,
+        }
+    }
+
+    /// The constructor chain of the number `n`, which is at most `MAX_LITERAL` in parsed code.
+    pub fn literal(&self, n: usize) -> Expression {
+        let zero = Expression::Constructor(self.zero.clone(), Box::new(Expression::Unit));
+        (0..n).fold(zero, |number, _| {
+            Expression::Constructor(self.suc.clone(), Box::new(number))
+        })
+    }
+}
+
+/// This is an extension to Mini-TT, `import data.list`.<br/>
+/// The module `data.list` is the file `data/list.minitt`, relative to the importing file or a
+/// directory in the search path.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Import {
+    /// The components of the module name, `["data", "list"]`.
+    pub path: Vec<String>,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Import {
+    /// Constructor
+    pub fn new(path: Vec<String>) -> Self {
+        Self { path, span: None }
+    }
+
+    /// The module name, `data.list`.
+    pub fn name(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+/// This is an extension to Mini-TT, `module Nat { ... }`.<br/>
+/// Qualified names are ordinary names with dots, `Nat.add` is bound after the block, while
+/// `add` is only visible in the block.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Module {
+    pub name: String,
+    /// The declarations in the block.
+    pub body: Expression,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Module {
+    /// Constructor
+    pub fn new(name: String, body: Expression) -> Self {
+        Self {
+            name,
+            body,
+            span: None,
+        }
+    }
+
+    /// The qualified name of `name` defined in the block, `Nat.add` for `add`.
+    pub fn qualify(&self, name: &str) -> String {
+        format!("{}.{}", self.name, name)
+    }
+
+    /// The names defined in the block and visible outside of it, unqualified, each once.
+    /// Names of the nested modules are qualified by the nested module names.
+    pub fn names(&self) -> Vec<String> {
+        let definitions = self.body.definitions();
+        let shadowed = |index: usize, name: &String| {
+            (definitions[index + 1..].iter()).any(|(shadowing, _)| shadowing == name)
+        };
+        (definitions.iter().enumerate())
+            .filter(|(index, (name, private))| !private && !shadowed(*index, name))
+            .map(|(_, (name, _))| name.clone())
+            .collect()
+    }
+
+    /// The private names defined in the block, unqualified.
+    pub fn private_names(&self) -> Vec<String> {
+        let public = self.names();
+        (self.body.definitions().into_iter())
+            .filter(|(name, private)| *private && !public.contains(name))
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+/// This is an extension to Mini-TT, `open Nat`.<br/>
+/// The names qualified by `Nat.` are bound without the qualifier.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Open {
+    /// The module name, may be qualified, like `Nat.Even`.
+    pub name: String,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Open {
+    /// Constructor
+    pub fn new(name: String) -> Self {
+        Self { name, span: None }
+    }
+
+    /// The unqualified name of `name` in the module, if it's in the module.
+    pub fn unqualify<'a>(&self, name: &'a str) -> Option<&'a str> {
+        name.strip_prefix(self.name.as_str())?.strip_prefix('.')
+    }
+}
+
+/// This is an extension to Mini-TT, `export (a, b, C)`.<br/>
+/// A module name exports the names defined in the module as well, `C.x` for `C`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Export {
+    pub names: Vec<String>,
+    /// Source code location, present if it's parsed from source code.
+    pub span: Option<Span>,
+}
+
+impl Export {
+    /// Constructor
+    pub fn new(names: Vec<String>) -> Self {
+        Self { names, span: None }
+    }
+
+    /// Whether the name is in the list, or defined in a module in the list.
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|exported| {
+            (name.strip_prefix(exported.as_str()))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    }
+}
+
+/// This is an extension to Mini-TT, a string or character literal.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub enum Literal {
+    /// `"bla"`, of type `String`.
+    String(String),
+    /// `'c'`, of type `Char`.
+    Char(char),
+}
+
+/// This is an extension to Mini-TT, the built-in functions on strings and characters.<br/>
+/// Equalities return `True 0` or `False 0`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub enum Primitive {
+    /// `primStringAppend : String -> String -> String`.
+    StringAppend,
+    /// `primStringLength : String -> nat`, where `nat` is declared by a
+    /// `BUILTIN NATURAL` pragma.
+    StringLength(Box<Natural>),
+    /// `primStringEquality : String -> String -> Sum { False | True }`.
+    StringEquality,
+    /// `primCharEquality : Char -> Char -> Sum { False | True }`.
+    CharEquality,
+}
+
+impl Primitive {
+    /// The name used in source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Primitive::StringAppend => "primStringAppend",
+            Primitive::StringLength(_) => "primStringLength",
+            Primitive::StringEquality => "primStringEquality",
+            Primitive::CharEquality => "primCharEquality",
+        }
+    }
+
+    /// Number of arguments needed before it computes.
+    pub fn arity(&self) -> usize {
+        match self {
+            Primitive::StringLength(_) => 1,
+            _ => 2,
+        }
+    }
+
+    /// The type of the built-in function.
+    pub fn signature(&self) -> Expression {
+        use crate::ast::Expression::*;
+        let function = |input: Expression, output: Expression| {
+            Pi(Typed::new(Pattern::Unit, input), Box::new(output))
+        };
+        let boolean = || {
+            let mut branches = BTreeMap::new();
+            branches.insert("False".to_string(), Box::new(One));
+            branches.insert("True".to_string(), Box::new(One));
+            Sum(branches)
+        };
+        match self {
+            Self::StringAppend => function(StringType, function(StringType, StringType)),
+            Self::StringLength(natural) => function(StringType, Var(natural.name.clone())),
+            Self::StringEquality => function(StringType, function(StringType, boolean())),
+            Self::CharEquality => function(CharType, function(CharType, boolean())),
+        }
+    }
+}
+
+/// Whether `name` is an operator symbol like `+` or `::`, instead of an identifier or the
+/// variable of a generated value like `<0>`.
+pub fn is_operator(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| "+-*/<>=&|~^%@?$!:.".contains(c))
 }
 
 /// Generic definition for two kinds of telescopes.<br/>
@@ -0,0 +1,577 @@
+use crate::ast::{Branch, Expression, Pattern, Span};
+#[cfg(feature = "serial")]
+use serde::{Deserialize, Serialize};
+
+/// The key of the clause taken by a `split` when no clause matches the constructor.
+pub const DEFAULT_CLAUSE: &str = "_";
+
+/// Patterns in `split` clauses, which, unlike `Pattern`, may match constructors.<br/>
+/// `Pair` is only used when at least one of the components is refutable,
+/// use `CasePattern::pair` to build one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub enum CasePattern {
+    /// Irrefutable pattern, matches anything.
+    Pattern(Pattern),
+    Pair(Box<Self>, Box<Self>),
+    /// The constructor name, the pattern of the fields and the source code location.
+    Constructor(String, Box<Self>, Option<Span>),
+    /// Absurd pattern, matches values of an empty sum type, of which there's none.
+    Absurd(Option<Span>),
+}
+
+impl CasePattern {
+    /// Pair two patterns, the result is irrefutable if both of them are.
+    pub fn pair(first: Self, second: Self) -> Self {
+        match (first, second) {
+            (CasePattern::Pattern(first), CasePattern::Pattern(second)) => {
+                CasePattern::Pattern(Pattern::Pair(Box::new(first), Box::new(second)))
+            }
+            (first, second) => CasePattern::Pair(Box::new(first), Box::new(second)),
+        }
+    }
+
+    /// `C p`, where `p` is the pattern of the constructor's fields.
+    pub fn constructor(name: String, fields: Self) -> Self {
+        CasePattern::Constructor(name, Box::new(fields), None)
+    }
+
+    /// Attach the source code location to the pattern. Wildcards are left as they are.
+    pub fn at(self, span: Span) -> Self {
+        match self {
+            CasePattern::Pattern(Pattern::Unit) => CasePattern::Pattern(Pattern::Unit),
+            CasePattern::Pattern(pattern) => CasePattern::Pattern(Pattern::located(span, pattern)),
+            CasePattern::Constructor(name, fields, _) => {
+                CasePattern::Constructor(name, fields, Some(span))
+            }
+            CasePattern::Absurd(_) => CasePattern::Absurd(Some(span)),
+            pair => pair,
+        }
+    }
+
+    /// The source code location of the pattern, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            CasePattern::Pattern(pattern) => pattern.span(),
+            CasePattern::Pair(first, second) => Some(first.span()?.merge(second.span()?)),
+            CasePattern::Constructor(_, _, span) | CasePattern::Absurd(span) => *span,
+        }
+    }
+
+    /// Whether there's an absurd pattern in it.
+    pub fn is_absurd(&self) -> bool {
+        match self {
+            CasePattern::Pattern(_) => false,
+            CasePattern::Pair(first, second) => first.is_absurd() || second.is_absurd(),
+            CasePattern::Constructor(_, fields, _) => fields.is_absurd(),
+            CasePattern::Absurd(_) => true,
+        }
+    }
+}
+
+/// The type of the values matched by the clauses, as far as the reachability of the clauses
+/// is concerned. The clauses matching the values of unknown types (`None`) are assumed to be
+/// reachable unless a clause before them matches the same values.
+pub trait Matched: Clone {
+    /// The constructors of a sum type with the types of their fields, `None` if it's not a sum
+    /// type. The constructors with empty payloads are left out, there's no value of them.
+    fn constructors(&self) -> Option<Vec<(String, Option<Self>)>>;
+
+    /// The types of the components of a pair type.
+    fn components(&self) -> (Option<Self>, Option<Self>);
+}
+
+/// The indices of the clauses, whose patterns are `patterns`, that never match a value of
+/// the type `matched`, since the values they match are matched by the clauses before them.
+/// <br/>
+/// The clauses are walked in order, a clause is unreachable if it's not useful to the ones
+/// before it (see "Warnings for pattern matching" by Luc Maranget).
+/// Clauses with absurd patterns are never reported, they match no value by design.
+pub fn unreachable_clauses<T: Matched>(patterns: &[CasePattern], matched: Option<T>) -> Vec<usize> {
+    let mut rows: Vec<Vec<CasePattern>> = Default::default();
+    let mut unreachable: Vec<usize> = Default::default();
+    let types = [matched];
+    for (index, pattern) in patterns.iter().enumerate() {
+        let row = vec![pattern.clone()];
+        if !pattern.is_absurd() && !useful(&rows, &row, &types) {
+            unreachable.push(index);
+        }
+        rows.push(row);
+    }
+    unreachable
+}
+
+/// The index of the first clause, whose patterns are `patterns`, that matches a constructor
+/// where the clauses before it match a pair, or a pair where they match a constructor,
+/// with the part of the pattern that does so. No value can be matched by both.
+pub fn mismatched_clause(patterns: &[CasePattern]) -> Option<(usize, CasePattern)> {
+    let mut rows: Vec<Vec<CasePattern>> = Default::default();
+    for (index, pattern) in patterns.iter().enumerate() {
+        rows.push(vec![pattern.clone()]);
+        if let Some(mismatched) = mismatched(&rows) {
+            return Some((index, mismatched));
+        }
+    }
+    None
+}
+
+/// The first pattern of the last row, if it matches a constructor where the rows before it match
+/// a pair, or the other way around. The rows before it are assumed to be consistent.
+fn mismatched(rows: &[Vec<CasePattern>]) -> Option<CasePattern> {
+    let last = rows.last().filter(|row| !row.is_empty())?;
+    let is_sum = |pattern: &CasePattern| {
+        matches!(
+            pattern,
+            CasePattern::Constructor(..) | CasePattern::Absurd(_)
+        )
+    };
+    let pairs = rows
+        .iter()
+        .any(|row| matches!(row[0], CasePattern::Pair(..)));
+    let sums = rows.iter().any(|row| is_sum(&row[0]));
+    if pairs && sums {
+        return Some(last[0].clone());
+    }
+    if pairs {
+        return mismatched(&specialize_pair(rows));
+    }
+    if let CasePattern::Constructor(name, _, _) = &last[0] {
+        return mismatched(&specialize(rows, name));
+    }
+    // The last row matches anything, it's matched with the rows before it for each constructor
+    let mut names: Vec<&String> = Default::default();
+    for row in rows.iter() {
+        match &row[0] {
+            CasePattern::Constructor(name, _, _) if !names.contains(&name) => names.push(name),
+            _ => {}
+        }
+    }
+    let rest: Vec<_> = (rows.iter())
+        .filter(|row| matches!(row[0], CasePattern::Pattern(_)))
+        .map(|row| row[1..].to_vec())
+        .collect();
+    (names.into_iter())
+        .find_map(|name| mismatched(&specialize(rows, name)))
+        .or_else(|| mismatched(&rest))
+}
+
+/// Whether there's a value matched by `patterns`, whose types are `types`, but not by any of the
+/// `rows`. `U` in the paper.
+fn useful<T: Matched>(
+    rows: &[Vec<CasePattern>],
+    patterns: &[CasePattern],
+    types: &[Option<T>],
+) -> bool {
+    let (pattern, patterns) = match patterns.split_first() {
+        Some(split) => split,
+        None => return rows.is_empty(),
+    };
+    let (matched, types) = types.split_first().unwrap();
+    let wildcard = || CasePattern::Pattern(Pattern::Unit);
+    let with = |heads: Vec<CasePattern>| [heads, patterns.to_vec()].concat();
+    let typed = |heads: Vec<Option<T>>| [heads, types.to_vec()].concat();
+    match pattern {
+        // Matches nothing
+        CasePattern::Absurd(_) => false,
+        CasePattern::Constructor(name, fields, _) => {
+            let fields_type = constructor_fields(matched, name);
+            let rows = specialize(rows, name);
+            useful(
+                &rows,
+                &with(vec![*fields.clone()]),
+                &typed(vec![fields_type]),
+            )
+        }
+        CasePattern::Pair(first, second) => {
+            let (first_type, second_type) = components(matched);
+            let heads = vec![*first.clone(), *second.clone()];
+            useful(
+                &specialize_pair(rows),
+                &with(heads),
+                &typed(vec![first_type, second_type]),
+            )
+        }
+        CasePattern::Pattern(_)
+            if rows
+                .iter()
+                .any(|row| matches!(row[0], CasePattern::Pair(..))) =>
+        {
+            let (first_type, second_type) = components(matched);
+            let heads = vec![wildcard(), wildcard()];
+            useful(
+                &specialize_pair(rows),
+                &with(heads),
+                &typed(vec![first_type, second_type]),
+            )
+        }
+        CasePattern::Pattern(_) => {
+            let matched_names: Vec<&String> = (rows.iter())
+                .filter_map(|row| match &row[0] {
+                    CasePattern::Constructor(name, _, _) => Some(name),
+                    _ => None,
+                })
+                .collect();
+            let constructors = matched.as_ref().and_then(Matched::constructors);
+            match constructors {
+                // Every constructor is matched by some clause, the wildcard is useful if it's
+                // useful to one of them
+                Some(constructors)
+                    if (constructors.iter()).all(|(name, _)| matched_names.contains(&name)) =>
+                {
+                    (constructors.into_iter()).any(|(name, fields_type)| {
+                        let rows = specialize(rows, &name);
+                        useful(&rows, &with(vec![wildcard()]), &typed(vec![fields_type]))
+                    })
+                }
+                _ => {
+                    let rows: Vec<_> = (rows.iter())
+                        .filter(|row| matches!(row[0], CasePattern::Pattern(_)))
+                        .map(|row| row[1..].to_vec())
+                        .collect();
+                    useful(&rows, patterns, types)
+                }
+            }
+        }
+    }
+}
+
+/// The rows matching the constructor `name`, with the pattern of its fields in place of the
+/// first pattern.
+fn specialize(rows: &[Vec<CasePattern>], name: &str) -> Vec<Vec<CasePattern>> {
+    (rows.iter())
+        .filter_map(|row| {
+            let fields = match &row[0] {
+                CasePattern::Constructor(matched, fields, _) if matched == name => *fields.clone(),
+                CasePattern::Pattern(_) => CasePattern::Pattern(Pattern::Unit),
+                _ => return None,
+            };
+            Some([vec![fields], row[1..].to_vec()].concat())
+        })
+        .collect()
+}
+
+/// The rows matching a pair, with the patterns of the components in place of the first pattern.
+fn specialize_pair(rows: &[Vec<CasePattern>]) -> Vec<Vec<CasePattern>> {
+    (rows.iter())
+        .filter_map(|row| {
+            let components = match &row[0] {
+                CasePattern::Pair(first, second) => vec![*first.clone(), *second.clone()],
+                CasePattern::Pattern(_) => vec![CasePattern::Pattern(Pattern::Unit); 2],
+                _ => return None,
+            };
+            Some([components, row[1..].to_vec()].concat())
+        })
+        .collect()
+}
+
+/// The type of the fields of the constructor `name` of `matched`, if known.
+fn constructor_fields<T: Matched>(matched: &Option<T>, name: &str) -> Option<T> {
+    let constructors = matched.as_ref()?.constructors()?;
+    (constructors.into_iter())
+        .find(|(constructor, _)| constructor == name)
+        .and_then(|(_, fields)| fields)
+}
+
+/// The types of the components of `matched`, if known.
+fn components<T: Matched>(matched: &Option<T>) -> (Option<T>, Option<T>) {
+    match matched {
+        Some(matched) => matched.components(),
+        None => (None, None),
+    }
+}
+
+/// `expression`, located at `span` if it's known.
+fn located(span: Option<Span>, expression: Expression) -> Expression {
+    match span {
+        Some(span) => Expression::Located(span, Box::new(expression)),
+        None => expression,
+    }
+}
+
+/// A clause of a `split`: `C p => e`, or `_ => e` which matches anything.<br/>
+/// The pattern is either a constructor pattern or `_`, since there's no variable to bind
+/// the value being split to.<br/>
+/// A clause has no body if and only if its pattern has an absurd pattern in it.
+pub type Clause = (CasePattern, Option<Expression>);
+
+/// The clauses of a `split`, or of a `match` with its scrutinee, as they're written in the
+/// source code, with their locations, see `Expression::Clauses`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct SourceClauses {
+    /// The scrutinee of a `match`, `None` for a `split`.
+    pub scrutinee: Option<Expression>,
+    pub clauses: Vec<(Clause, Option<Span>)>,
+}
+
+impl SourceClauses {
+    /// The patterns of the clauses, in order.
+    pub fn patterns(&self) -> Vec<CasePattern> {
+        (self.clauses.iter())
+            .map(|((pattern, _), _)| pattern.clone())
+            .collect()
+    }
+}
+
+/// A row of the clause matrix: the patterns to match against each occurrence,
+/// the variables already bound to an occurrence, and the body.
+#[derive(Clone)]
+struct Row {
+    patterns: Vec<CasePattern>,
+    bindings: Vec<(Pattern, String)>,
+    body: Option<Expression>,
+}
+
+impl Row {
+    /// Bind the irrefutable `column`-th pattern to `occurrence`, leaving a wildcard there.
+    fn bind(mut self, column: usize, occurrence: &str) -> Self {
+        let wildcard = CasePattern::Pattern(Pattern::Unit);
+        match std::mem::replace(&mut self.patterns[column], wildcard) {
+            CasePattern::Pattern(Pattern::Unit) => {}
+            CasePattern::Pattern(pattern) => self.bindings.push((pattern, occurrence.to_owned())),
+            _ => unreachable!(),
+        }
+        self
+    }
+
+    /// Replace the `column`-th pattern with `patterns`.
+    fn replace(mut self, column: usize, patterns: Vec<CasePattern>) -> Self {
+        self.patterns.splice(column..=column, patterns);
+        self
+    }
+
+    fn refutable_column(&self) -> Option<usize> {
+        self.patterns
+            .iter()
+            .position(|pattern| !matches!(pattern, CasePattern::Pattern(_)))
+    }
+}
+
+/// Compile the clauses of a `split` into a case tree.<br/>
+/// Clauses are tried from the first to the last. When a clause matches nested constructors,
+/// the fields of the outer constructor are bound to fresh variables (`#0`, `#1`, ...)
+/// and matched by nested `split`s, clauses that do not match a nested constructor are
+/// collected into the `DEFAULT_CLAUSE` of the nested `split`.
+/// The clause `_ => e` becomes the `DEFAULT_CLAUSE` of the `split` itself.
+/// An absurd pattern on `x` becomes `split {} x`.
+///
+/// Clauses matching a constructor where the clauses before them match a pair, or the other way
+/// around, are ill-typed and left out, the type-checker reports them (see `mismatched_clause`).
+///
+/// The type of a nested `split` may depend on the field it splits, which is a variable bound
+/// by the outer clause, so `Suc Zero` is checked against the type for `Suc Zero`.
+/// The components of a pair pattern are projections instead, splitting them is non-dependent.
+pub fn compile_split(clauses: Vec<Clause>) -> Branch {
+    let rows = clauses
+        .into_iter()
+        .map(|(pattern, body)| Row {
+            patterns: vec![pattern],
+            bindings: Default::default(),
+            body,
+        })
+        .collect();
+    Compiler::default().branches(0, &[String::new()], rows)
+}
+
+/// Compile `match e { clauses }`, where the scrutinee `e` is usually a tuple, into nested `split`s.
+/// <br/>
+/// Matching on several values `match x, y { p, q => e }` is matching on the tuple `(x, y)`,
+/// so the clauses are checked for coverage across the whole tuple.
+/// Only when the scrutinee is a single variable can the type depend on the value matched.
+pub fn compile_match(scrutinee: Expression, clauses: Vec<Clause>) -> Expression {
+    let mut compiler = Compiler::default();
+    let (occurrence, binding) = match scrutinee.unlocated() {
+        Expression::Var(name) => (name.clone(), None),
+        _ => (compiler.fresh(), Some(scrutinee)),
+    };
+    let rows: Vec<Row> = clauses
+        .into_iter()
+        .map(|(pattern, body)| Row {
+            patterns: vec![pattern],
+            bindings: Default::default(),
+            body,
+        })
+        .collect();
+    let compiled = if rows.is_empty() {
+        // Nothing can be matched, the scrutinee must be of an empty sum type.
+        Expression::Application(
+            Box::new(Expression::Split(Default::default())),
+            Box::new(Expression::Var(occurrence.clone())),
+        )
+    } else {
+        compiler.compile(vec![occurrence.clone()], rows)
+    };
+    match binding {
+        Some(scrutinee) => Expression::Constant(
+            Pattern::Var(occurrence),
+            Box::new(scrutinee),
+            Box::new(compiled),
+        ),
+        None => compiled,
+    }
+}
+
+#[derive(Default)]
+struct Compiler {
+    fresh: usize,
+}
+
+impl Compiler {
+    fn fresh(&mut self) -> String {
+        self.fresh += 1;
+        format!("#{}", self.fresh - 1)
+    }
+
+    /// Match `rows` against `occurrences`.
+    fn compile(&mut self, occurrences: Vec<String>, mut rows: Vec<Row>) -> Expression {
+        let column = match rows[0].refutable_column() {
+            Some(column) => column,
+            None => return Self::leaf(occurrences, rows.remove(0)),
+        };
+        let occurrence = occurrences[column].clone();
+        if !matches!(rows[0].patterns[column], CasePattern::Pair(_, _)) {
+            // Errors of the nested `split` are located to the first pattern it's compiled from.
+            let span = rows[0].patterns[column].span();
+            let branch = match rows[0].patterns[column] {
+                // Nothing to match, the clauses after it are unreachable.
+                CasePattern::Absurd(_) => Default::default(),
+                _ => self.branches(column, &occurrences, rows),
+            };
+            let split = Expression::Application(
+                Box::new(Expression::Split(branch)),
+                Box::new(Expression::Var(occurrence)),
+            );
+            return located(span, split);
+        }
+        let (first, second) = (self.fresh(), self.fresh());
+        let mut occurrences = occurrences;
+        occurrences.splice(column..=column, vec![first.clone(), second.clone()]);
+        let rows = rows
+            .into_iter()
+            .filter_map(|row| {
+                let components = match &row.patterns[column] {
+                    CasePattern::Pair(first, second) => vec![*first.clone(), *second.clone()],
+                    CasePattern::Pattern(pattern) => match pattern.unlocated() {
+                        Pattern::Pair(first, second) => vec![
+                            CasePattern::Pattern(*first.clone()),
+                            CasePattern::Pattern(*second.clone()),
+                        ],
+                        _ => {
+                            let wildcard = CasePattern::Pattern(Pattern::Unit);
+                            let row = row.bind(column, &occurrence);
+                            return Some(row.replace(column, vec![wildcard.clone(), wildcard]));
+                        }
+                    },
+                    CasePattern::Constructor(..) | CasePattern::Absurd(_) => return None,
+                };
+                Some(row.replace(column, components))
+            })
+            .collect();
+        Expression::Constant(
+            Pattern::Pair(
+                Box::new(Pattern::Var(first)),
+                Box::new(Pattern::Var(second)),
+            ),
+            Box::new(Expression::Var(occurrence)),
+            Box::new(self.compile(occurrences, rows)),
+        )
+    }
+
+    /// Bind the variables of a row whose patterns are all irrefutable.
+    fn leaf(occurrences: Vec<String>, mut row: Row) -> Expression {
+        let body = row
+            .body
+            .expect("A clause without absurd patterns must have a body.");
+        for (pattern, occurrence) in row.patterns.into_iter().zip(occurrences) {
+            if let CasePattern::Pattern(pattern) = pattern {
+                row.bindings.push((pattern, occurrence));
+            }
+        }
+        row.bindings
+            .into_iter()
+            .rev()
+            .fold(body, |body, (pattern, occurrence)| {
+                match pattern.unlocated() {
+                    Pattern::Unit => body,
+                    Pattern::Var(name) if *name == occurrence => body,
+                    _ => Expression::Constant(
+                        pattern,
+                        Box::new(Expression::Var(occurrence)),
+                        Box::new(body),
+                    ),
+                }
+            })
+    }
+
+    /// The clauses of a `split` on the `column`-th occurrence.
+    fn branches(&mut self, column: usize, occurrences: &[String], rows: Vec<Row>) -> Branch {
+        // The constructors, with the location of the first clause matching each of them
+        let mut names: Vec<(&String, Option<Span>)> = Default::default();
+        for row in rows.iter() {
+            if let CasePattern::Constructor(name, _, span) = &row.patterns[column] {
+                if names.iter().all(|(matched, _)| *matched != name) {
+                    names.push((name, *span));
+                }
+            }
+        }
+        let occurrence = &occurrences[column];
+        let mut branch: Branch = Default::default();
+        for (name, span) in names.into_iter() {
+            let mut rows: Vec<Row> = rows
+                .iter()
+                .filter_map(|row| match &row.patterns[column] {
+                    CasePattern::Constructor(matched, fields, _) if matched == name => {
+                        Some(row.clone().replace(column, vec![*fields.clone()]))
+                    }
+                    CasePattern::Pattern(_) => Some(row.clone().bind(column, occurrence)),
+                    _ => None,
+                })
+                .collect();
+            let mut occurrences = occurrences.to_vec();
+            let wildcard = CasePattern::Pattern(Pattern::Unit);
+            let clause = if rows.iter().all(|row| row.patterns[column] == wildcard) {
+                // The fields are never used.
+                for row in rows.iter_mut() {
+                    row.patterns.remove(column);
+                }
+                occurrences.remove(column);
+                let body = self.compile(occurrences, rows);
+                Expression::Lambda(Pattern::Unit, None, Box::new(body))
+            } else if rows[0].refutable_column().is_none() {
+                // The first clause matches anything, the fields can be bound directly.
+                let mut row = rows.remove(0);
+                let pattern = match row.patterns.remove(column) {
+                    CasePattern::Pattern(pattern) => pattern,
+                    _ => unreachable!(),
+                };
+                occurrences.remove(column);
+                Expression::Lambda(pattern, None, Box::new(Self::leaf(occurrences, row)))
+            } else {
+                let fields = self.fresh();
+                occurrences[column] = fields.clone();
+                let body = self.compile(occurrences, rows);
+                Expression::Lambda(Pattern::Var(fields), None, Box::new(body))
+            };
+            branch.insert(name.clone(), Box::new(located(span, clause)));
+        }
+        let defaults: Vec<Row> = rows
+            .into_iter()
+            .filter(|row| matches!(row.patterns[column], CasePattern::Pattern(_)))
+            .map(|row| {
+                let mut row = row.bind(column, occurrence);
+                row.patterns.remove(column);
+                row
+            })
+            .collect();
+        if !defaults.is_empty() {
+            let mut occurrences = occurrences.to_vec();
+            occurrences.remove(column);
+            let body = self.compile(occurrences, defaults);
+            branch.insert(
+                DEFAULT_CLAUSE.to_owned(),
+                Box::new(Expression::Lambda(Pattern::Unit, None, Box::new(body))),
+            );
+        }
+        branch
+    }
+}
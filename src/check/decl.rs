@@ -1,4 +1,7 @@
-use crate::ast::{up_dec_rc, up_var_rc, AnonymousValue, Declaration, Expression, Pattern, Typed};
+use crate::ast::{
+    up_dec_rc, up_var_rc, Closure, Declaration, Export, Expression, Module, Natural, Pattern,
+    Typed, Value,
+};
 use crate::check::expr::{check, check_type};
 use crate::check::read_back::generate_value;
 use crate::check::tcm::{update_gamma_borrow, update_gamma_lazy, Gamma, TCE, TCM, TCS};
@@ -33,25 +36,25 @@ pub type LiftState<'a> = (Expression, Expression, TCS<'a>);
 pub fn check_lift_parameters<'a>(
     index: u32,
     tcs: TCS<'a>,
-    mut parameters: Vec<Typed>,
+    parameters: &mut [Typed],
     check_body: impl FnOnce(TCS<'a>) -> TCM<LiftState<'a>>,
 ) -> TCM<LiftState<'a>> {
-    if parameters.is_empty() {
-        return check_body(tcs);
-    }
-    let parameter = parameters.remove(0);
+    let (parameter, parameters) = match parameters.split_first_mut() {
+        Some(split) => split,
+        None => return check_body(tcs),
+    };
+    let (_, tcs) = check_type(index, tcs, &mut parameter.expression)?;
     // Forgive me, I failed find a better name.
     let clone = parameter.clone();
-    let (pattern, expression) = parameter.destruct();
-    let (_, tcs) = check_type(index, tcs, expression.clone())?;
+    let (pattern, expression) = parameter.clone().destruct();
     let generated = generate_value(index);
     let type_val = expression.clone().eval(tcs.context());
-    let tcs = tcs.update(pattern.clone(), type_val.clone(), generated)?;
+    let tcs = tcs.update(pattern.clone(), type_val, generated)?;
     let (signature, body, tcs) = check_lift_parameters(index + 1, tcs, parameters, check_body)?;
 
     Ok((
         Expression::Pi(clone, Box::new(signature)),
-        Expression::Lambda(pattern, AnonymousValue::some(type_val), Box::new(body)),
+        Expression::Lambda(pattern, Some(Box::new(expression)), Box::new(body)),
         tcs,
     ))
 }
@@ -68,9 +71,13 @@ pub fn check_lift_parameters<'a>(
 /// $$
 /// Extracted from `checkD` in Mini-TT.<br/>
 /// This part deals with recursive declarations, but without prefixed parameters.
-pub fn check_recursive_declaration(index: u32, tcs: TCS, declaration: Declaration) -> TCM<Gamma> {
+pub fn check_recursive_declaration<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    declaration: &mut Declaration,
+) -> TCM<Gamma<'a>> {
     let pattern = declaration.pattern.clone();
-    check_type(index, tcs_borrow!(tcs), declaration.signature.clone())
+    check_type(index, tcs_borrow!(tcs), &mut declaration.signature)
         .map_err(|err| try_locate!(err, pattern))?;
     let signature = declaration.signature.clone().eval(tcs.context());
     let generated = generate_value(index);
@@ -78,9 +85,15 @@ pub fn check_recursive_declaration(index: u32, tcs: TCS, declaration: Declaratio
     let fake_tcs = fake_tcs
         .update(pattern.clone(), signature.clone(), generated)
         .map_err(|err| try_locate!(err, pattern))?;
-    let body = declaration.body.clone();
-    check(index + 1, fake_tcs, body.clone(), signature.clone())
-        .map_err(|err| try_locate!(err, pattern))?;
+    check_components(
+        index + 1,
+        fake_tcs,
+        &pattern,
+        &mut declaration.body,
+        signature.clone(),
+    )
+    .map_err(|err| try_locate!(err, pattern))?;
+    let (body, declaration) = (declaration.body.clone(), declaration.clone());
     let context = tcs.context;
     update_gamma_lazy(tcs.gamma, &pattern, signature, || {
         body.eval(up_dec_rc(context, declaration))
@@ -88,6 +101,31 @@ pub fn check_recursive_declaration(index: u32, tcs: TCS, declaration: Declaratio
     .map_err(|err| try_locate!(err, pattern))
 }
 
+/// This is an extension, it's not present in Mini-TT.<br/>
+/// Check the body of a declaration against its signature. When both the pattern and the body
+/// are pairs, as in the declarations desugared from `mutual` blocks, each component is checked
+/// on its own, so the errors are located to the definition that failed.
+fn check_components<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    pattern: &Pattern,
+    body: &mut Expression,
+    signature: Value,
+) -> TCM<TCS<'a>> {
+    if let (
+        Pattern::Pair(first_pattern, second_pattern),
+        Expression::Pair(first, second),
+        Value::Sigma(first_type, second_type),
+    ) = (pattern.unlocated(), body.unlocated_mut(), &signature)
+    {
+        let first_type = *first_type.clone();
+        let tcs = check_components(index, tcs, first_pattern, first, first_type)?;
+        let second_type = (second_type.clone()).instantiate(first.clone().eval(tcs.context()));
+        return check_components(index, tcs, second_pattern, second, second_type);
+    }
+    check(index, tcs, body, signature).map_err(|err| try_locate!(err, pattern))
+}
+
 /// $$
 /// \frac{\rho,\Gamma\vdash_l A
 ///       \quad \rho,\Gamma\vdash_l M\Leftarrow t
@@ -99,43 +137,104 @@ pub fn check_recursive_declaration(index: u32, tcs: TCS, declaration: Declaratio
 /// $$
 /// Extracted from `checkD` in Mini-TT.<br/>
 /// This part deals with non-recursive declarations, but without prefixed parameters.
-pub fn check_simple_declaration(
+pub fn check_simple_declaration<'a>(
     index: u32,
-    mut tcs: TCS,
+    mut tcs: TCS<'a>,
     pattern: Pattern,
-    signature: Expression,
-    body: Expression,
-) -> TCM<Gamma> {
+    signature: &mut Expression,
+    body: &mut Expression,
+) -> TCM<Gamma<'a>> {
     let (_, new_tcs) =
-        check_type(index, tcs, signature.clone()).map_err(|err| try_locate!(err, pattern))?;
+        check_type(index, tcs, signature).map_err(|err| try_locate!(err, pattern))?;
     tcs = new_tcs;
     // workaround: fix error when calculate level here ↓
-    let signature = signature.eval(tcs.context());
-    tcs = check(index, tcs, body.clone(), signature.clone())
+    let signature = signature.clone().eval(tcs.context());
+    // The local declarations in the body, like the helpers in `where` clauses, are not kept
+    check(index, tcs_borrow!(tcs), body, signature.clone())
         .map_err(|err| try_locate!(err, pattern))?;
-    let TCS { gamma, context } = tcs;
+    let body = body.clone();
+    let TCS { gamma, context, .. } = tcs;
     update_gamma_lazy(gamma, &pattern, signature, || body.eval(context))
         .map_err(|err| try_locate!(err, pattern))
 }
 
+/// Check the declarations in a `module` block and bind the names defined there, qualified by
+/// the module name. The unqualified names are not kept.
+pub fn check_module<'a>(index: u32, tcs: TCS<'a>, module: &mut Module) -> TCM<TCS<'a>> {
+    let definitions = {
+        let inner = check(index, tcs_borrow!(tcs), &mut module.body, Value::One)?;
+        (module.names().into_iter())
+            .map(|name| {
+                let signature = inner.gamma.get(&name).cloned();
+                let signature = signature.ok_or_else(|| TCE::UnresolvedName(name.clone()))?;
+                let value = inner.context.resolve(&name).map_err(TCE::Textual)?;
+                Ok((module.qualify(&name), signature, value))
+            })
+            .collect::<TCM<Vec<_>>>()?
+    };
+    let private = module.private_names().into_iter();
+    let tcs = tcs.hide(private.map(|name| module.qualify(&name)), &module.name);
+    (definitions.into_iter()).try_fold(tcs, |tcs, (name, signature, value)| {
+        tcs.update(Pattern::Var(name), signature, value)
+    })
+}
+
+/// The names in an `export` list must be defined, or be the names of modules.
+pub fn check_exports(tcs: &TCS, export: &Export) -> TCM<()> {
+    let defined = |name: &&String| {
+        let module = format!("{}.", name);
+        (tcs.gamma.keys()).any(|defined| defined == *name || defined.starts_with(&module))
+    };
+    match export.names.iter().find(|name| !defined(name)) {
+        None => Ok(()),
+        Some(name) => {
+            let err = TCE::UnresolvedName(name.clone());
+            Err(match export.span {
+                Some(span) => err.with_span(span),
+                None => err,
+            })
+        }
+    }
+}
+
 /// Originally `checkD` in Mini-TT, but now it's not because this implementation supports
 /// prefixed parameters :)<br/>
 /// Check if a declaration is well-typed and update the context.
-pub fn check_declaration(index: u32, tcs: TCS, declaration: Declaration) -> TCM<TCS> {
+pub fn check_declaration<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    declaration: &mut Declaration,
+) -> TCM<TCS<'a>> {
+    match declaration.span {
+        Some(span) => {
+            check_declaration_unspanned(index, tcs, declaration).map_err(|err| err.with_span(span))
+        }
+        None => check_declaration_unspanned(index, tcs, declaration),
+    }
+}
+
+/// The actual implementation of `check_declaration`, errors are not located to the declaration.
+fn check_declaration_unspanned<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    declaration: &mut Declaration,
+) -> TCM<TCS<'a>> {
     if declaration.prefix_parameters.is_empty() {
         let context = tcs.context();
-        return if !declaration.is_recursive {
+        let (warnings, hidden) = (tcs.warnings.clone(), tcs.hidden.clone());
+        let gamma = if !declaration.is_recursive {
             check_simple_declaration(
                 index,
                 tcs,
                 declaration.pattern.clone(),
-                declaration.signature.clone(),
-                declaration.body.clone(),
+                &mut declaration.signature,
+                &mut declaration.body,
             )
         } else {
-            check_recursive_declaration(index, tcs, declaration.clone())
-        }
-        .map(|gamma| TCS::new(gamma, up_dec_rc(context, declaration)));
+            check_recursive_declaration(index, tcs, declaration)
+        }?;
+        let context = up_dec_rc(context, declaration.clone());
+        return Ok(TCS::with_shared(gamma, context, warnings, hidden));
     }
     let (pattern, signature, body) = match declaration {
         Declaration {
@@ -144,47 +243,129 @@ pub fn check_declaration(index: u32, tcs: TCS, declaration: Declaration) -> TCM<
             signature,
             body,
             is_recursive: false,
+            ..
         } => check_lift_parameters(index, tcs_borrow!(tcs), prefix_parameters, |tcs| {
-            let (_, tcs) = check_type(index, tcs, signature.clone())
-                .map_err(|err| try_locate!(err, pattern))?;
+            let (_, tcs) =
+                check_type(index, tcs, signature).map_err(|err| try_locate!(err, pattern))?;
             let context = tcs.context();
-            let tcs = check(index, tcs, body.clone(), signature.clone().eval(context))
+            let tcs = check(index, tcs, body, signature.clone().eval(context))
                 .map_err(|err| try_locate!(err, pattern))?;
-            Ok((signature, body, tcs))
+            Ok((signature.clone(), body.clone(), tcs))
         })
-        .map(|(signature, body, _)| (pattern, signature, body))?,
-        declaration => {
-            let pattern = declaration.pattern.clone();
-            check_lift_parameters(
-                index,
-                tcs_borrow!(tcs),
-                declaration.prefix_parameters.clone(),
-                |tcs| {
-                    let (_, tcs) = check_type(index, tcs, declaration.signature.clone())
-                        .map_err(|err| try_locate!(err, pattern))?;
-                    let pattern = pattern.clone();
-                    let generated = generate_value(index);
-                    let signature = declaration.signature.clone().eval(tcs.context());
-                    let fake_tcs: TCS = tcs_borrow!(tcs);
-                    let fake_tcs = fake_tcs
-                        .update(pattern.clone(), signature.clone(), generated)
-                        .map_err(|err| try_locate!(err, pattern))?;
-                    check(index + 1, fake_tcs, declaration.body.clone(), signature)
-                        .map_err(|err| try_locate!(err, pattern))?;
-                    Ok((
-                        declaration.signature.clone(),
-                        declaration.body.clone(),
-                        TCS::new(tcs.gamma, up_dec_rc(tcs.context, declaration)),
-                    ))
-                },
-            )
-            .map(|(signature, body, _)| (pattern, signature, body))?
-        }
+        .map(|(signature, body, _)| (pattern.clone(), signature, body))?,
+        Declaration {
+            pattern,
+            prefix_parameters,
+            signature,
+            body,
+            is_recursive: true,
+            ..
+        } => check_lift_parameters(index, tcs_borrow!(tcs), prefix_parameters, |tcs| {
+            let (_, tcs) =
+                check_type(index, tcs, signature).map_err(|err| try_locate!(err, pattern))?;
+            let generated = generate_value(index);
+            let signature_val = signature.clone().eval(tcs.context());
+            let fake_tcs: TCS = tcs_borrow!(tcs);
+            let fake_tcs = fake_tcs
+                .update(pattern.clone(), signature_val.clone(), generated)
+                .map_err(|err| try_locate!(err, pattern))?;
+            check(index + 1, fake_tcs, body, signature_val)
+                .map_err(|err| try_locate!(err, pattern))?;
+            Ok((signature.clone(), body.clone(), tcs))
+        })
+        .map(|(signature, body, _)| (pattern.clone(), signature, body))?,
     };
 
-    let TCS { gamma, context } = tcs;
+    let TCS {
+        gamma,
+        context,
+        warnings,
+        hidden,
+    } = tcs;
     let body = body.eval(context.clone());
     update_gamma_borrow(gamma, &pattern, signature.eval(context.clone()), &body)
-        .map(|gamma| TCS::new(gamma, up_var_rc(context, pattern.clone(), body)))
+        .map(|gamma| {
+            let context = up_var_rc(context, pattern.clone(), body);
+            TCS::with_shared(gamma, context, warnings, hidden)
+        })
         .map_err(|err| try_locate!(err, pattern))
 }
+
+/// This is an extension, it's not present in Mini-TT.<br/>
+/// Bind the pattern of a declaration which failed to type-check as an opaque postulate of the
+/// declared signature (prefix parameters included), so the rest of the program can still be
+/// checked. The `postulate` binds the names of the pattern to opaque values of these names.
+///
+/// If the signature itself is ill-typed, the pattern is still bound in the context (so evaluation
+/// does not get stuck), but not in Gamma.
+pub fn postulate_declaration(postulate: Value, tcs: TCS, declaration: Declaration) -> TCS {
+    let Declaration {
+        pattern,
+        prefix_parameters,
+        signature,
+        ..
+    } = declaration;
+    let mut signature = prefix_parameters
+        .into_iter()
+        .rev()
+        .fold(signature, |signature, parameter| {
+            Expression::Pi(parameter, Box::new(signature))
+        });
+    let context = tcs.context();
+    let signature = match check_type(0, tcs_borrow!(tcs), &mut signature) {
+        Ok(_) => signature.eval(context.clone()),
+        Err(_) => {
+            let context = up_var_rc(context, pattern, postulate);
+            return TCS::with_shared(tcs.gamma, context, tcs.warnings, tcs.hidden);
+        }
+    };
+    let TCS {
+        gamma,
+        context,
+        warnings,
+        hidden,
+    } = tcs;
+    let gamma =
+        update_gamma_borrow(gamma.clone(), &pattern, signature, &postulate).unwrap_or(gamma);
+    TCS::with_shared(
+        gamma,
+        up_var_rc(context, pattern, postulate),
+        warnings,
+        hidden,
+    )
+}
+
+/// This is an extension, it's not present in Mini-TT.<br/>
+/// Check if the natural number type `N` of a `BUILTIN NATURAL` pragma is a type with the
+/// constructors $\textsf{zero}:N$ and $\textsf{suc}:N\rightarrow N$.
+pub fn check_natural(index: u32, tcs: TCS, natural: &Natural) -> TCM<()> {
+    let checked = check_natural_type(index, tcs, natural);
+    match natural.span {
+        Some(span) => checked.map_err(|err| err.with_span(span)),
+        None => checked,
+    }
+}
+
+/// Check if the name of the natural number type still refers to one, the pragma only names the
+/// type and it can be shadowed after the pragma. The error is not located at the pragma.
+pub fn check_natural_type(index: u32, tcs: TCS, natural: &Natural) -> TCM<()> {
+    let check_constructors = || {
+        let mut nat = Expression::Var(natural.name.clone());
+        let (_, tcs) = check_type(index, tcs, &mut nat)?;
+        let nat = nat.eval(tcs.context());
+        let tcs = check(index, tcs, &mut natural.literal(0), nat.clone())?;
+        let predecessor = "n".to_string();
+        let mut suc = Expression::Lambda(
+            Pattern::Var(predecessor.clone()),
+            None,
+            Box::new(Expression::Constructor(
+                natural.suc.clone(),
+                Box::new(Expression::Var(predecessor)),
+            )),
+        );
+        let suc_type = Value::Pi(Box::new(nat.clone()), Closure::Value(Box::new(nat)));
+        check(index, tcs, &mut suc, suc_type).map(|_| ())
+    };
+    check_constructors()
+        .map_err(|err| TCE::InvalidNatural(Box::new(err), Box::new(natural.clone())))
+}
@@ -3,11 +3,19 @@ use std::collections::BTreeMap;
 
 use either::Either;
 
-use super::decl::check_declaration;
-use super::read_back::generate_value;
+use super::decl::{
+    check_declaration, check_exports, check_module, check_natural, check_natural_type,
+};
+use super::read_back::{generate_value, generated_name, ReadBack};
 use super::subtype::check_subtype;
-use super::tcm::{update_gamma, TCE, TCM, TCS};
-use crate::ast::{Branch, Closure, Expression, GenericCase, Level, Typed, Value};
+use super::tcm::{update_gamma, TCE, TCM, TCS, TCW};
+use crate::ast::{
+    nil_rc, up_var_rc, Branch, Closure, Expression, GenericCase, Level, Neutral, Pattern, Typed,
+    Value,
+};
+use crate::case::{
+    mismatched_clause, unreachable_clauses, CasePattern, Matched, SourceClauses, DEFAULT_CLAUSE,
+};
 
 /// $$
 /// \frac{\Gamma(x)\rightarrow t}
@@ -29,49 +37,86 @@ use crate::ast::{Branch, Closure, Expression, GenericCase, Level, Typed, Value};
 /// \frac{\rho,\Gamma\vdash\_l M\Rightarrow \Sigma\ t\ g}
 ///      {\rho,\Gamma\vdash\_l M.2 \Rightarrow \textsf{inst}\ g((⟦M⟧\rho).1)}
 /// $$
+/// $$
+/// \frac{\rho,\Gamma\vdash\_l A
+///       \quad \rho,\Gamma\vdash\_l M\Leftarrow ⟦A⟧\rho}
+///      {\rho,\Gamma\vdash\_l (M : A)\Rightarrow ⟦A⟧\rho}
+/// $$
 /// `checkI` in Mini-TT.<br/>
 /// Type inference rule. More inferences are added here (maybe it's useful?).
-pub fn check_infer(index: u32, mut tcs: TCS, expression: Expression) -> TCM<Value> {
+pub fn check_infer(index: u32, mut tcs: TCS, expression: &mut Expression) -> TCM<Value> {
     use crate::ast::Expression::*;
-    // change for below usage of `expression`
-    match expression.clone() {
+    if let Some(call) = expression.constructor_call() {
+        *expression = call;
+    }
+    match expression {
         Unit => Ok(Value::One),
-        Type(level) => Ok(Value::Type(level + 1)),
-        Void | One => Ok(Value::Type(0)),
-        Var(name) => tcs
-            .gamma
-            .get(&name)
-            .cloned()
-            .ok_or_else(|| TCE::UnresolvedName(name)),
+        Type(level) => Ok(Value::Type(*level + 1)),
+        Void | One | StringType | CharType => Ok(Value::Type(0)),
+        Literal(crate::ast::Literal::String(_)) => Ok(Value::StringType),
+        Literal(crate::ast::Literal::Char(_)) => Ok(Value::CharType),
+        Primitive(primitive) => {
+            // The result type is looked up by name, which may be shadowed since the pragma
+            if let crate::ast::Primitive::StringLength(natural) = primitive {
+                check_natural_type(index, tcs_borrow!(tcs), natural)?;
+            }
+            Ok(primitive.signature().eval(tcs.context()))
+        }
+        Var(name) => match tcs.gamma.get(name) {
+            Some(signature) => Ok(signature.clone()),
+            None => Err(match tcs.hidden.get(name) {
+                Some(module) => TCE::HiddenName(name.clone(), module.clone()),
+                None => TCE::UnresolvedName(name.clone()),
+            }),
+        },
         Constructor(name, expression) => {
             let mut map = BTreeMap::new();
             let context = tcs.context.clone();
-            let inferred = check_infer(index, tcs, *expression)?;
+            let inferred = check_infer(index, tcs, expression)?;
             let case = GenericCase::new(Either::Left(inferred), context);
-            map.insert(name, Box::new(case));
+            map.insert(name.clone(), Box::new(case));
             Ok(Value::Sum(map))
         }
         Pair(left, right) => {
-            let left = check_infer(index, tcs_borrow!(tcs), *left)?;
-            let right = check_infer(index, tcs_borrow!(tcs), *right)?;
+            let left = check_infer(index, tcs_borrow!(tcs), left)?;
+            let right = check_infer(index, tcs_borrow!(tcs), right)?;
             let right = Closure::Value(Box::new(right));
             Ok(Value::Sigma(Box::new(left), right))
         }
-        First(pair) => match check_infer(index, tcs, *pair)? {
+        First(pair) => match check_infer(index, tcs, pair)? {
             Value::Sigma(first, _) => Ok(*first),
             e => Err(TCE::WantSigmaBut(e)),
         },
         Second(pair) => {
-            let TCS { gamma, context } = tcs;
-            match check_infer(index, TCS::new(gamma, context.clone()), *pair.clone())? {
-                Value::Sigma(_, second) => Ok(second.instantiate(pair.eval(context).first())),
+            let context = tcs.context();
+            match check_infer(index, tcs, pair)? {
+                Value::Sigma(_, second) => {
+                    Ok(second.instantiate(pair.clone().eval(context).first()))
+                }
                 e => Err(TCE::WantSigmaBut(e)),
             }
         }
+        Field(record, name) => {
+            let context = tcs.context();
+            let record_type = check_infer(index, tcs, record)?;
+            let mut projection = *record.clone();
+            let mut record = record.clone().eval(context);
+            let mut field_type = record_type.clone();
+            while let Value::Sigma(first, second) = field_type {
+                if field_name(&second) == Some(name) {
+                    *expression = First(Box::new(projection));
+                    return Ok(*first);
+                }
+                field_type = second.instantiate(record.clone().first());
+                record = record.second();
+                projection = Second(Box::new(projection));
+            }
+            Err(TCE::NoSuchField(name.clone(), Box::new(record_type)))
+        }
         Sum(branches) => {
             let mut max_level = 0;
-            for (_, branch) in branches.into_iter() {
-                let (level, new) = check_type(index, tcs, *branch)?;
+            for branch in branches.values_mut() {
+                let (level, new) = check_type(index, tcs, branch)?;
                 tcs = new;
                 if level > max_level {
                     max_level = level;
@@ -81,50 +126,73 @@ pub fn check_infer(index: u32, mut tcs: TCS, expression: Expression) -> TCM<Valu
         }
         Merge(left, right) => {
             if left.clone().eval_to_sum(tcs.context()).is_none() {
-                return Err(TCE::WantSumBut(Either::Right(*left)));
+                return Err(TCE::WantSumBut(Either::Right(*left.clone())));
             }
             if right.clone().eval_to_sum(tcs.context()).is_none() {
-                return Err(TCE::WantSumBut(Either::Right(*right)));
+                return Err(TCE::WantSumBut(Either::Right(*right.clone())));
             }
-            let left_level = match check_infer(index, tcs_borrow!(tcs), *left)? {
+            let left_level = match check_infer(index, tcs_borrow!(tcs), left)? {
                 Value::Type(left_level) => left_level,
                 e => return Err(TCE::WantSumBut(Either::Left(e))),
             };
-            let right_level = match check_infer(index, tcs_borrow!(tcs), *right)? {
+            let right_level = match check_infer(index, tcs_borrow!(tcs), right)? {
                 Value::Type(right_level) => right_level,
                 e => return Err(TCE::WantSumBut(Either::Left(e))),
             };
             Ok(Value::Type(max(left_level, right_level)))
         }
         Pi(input, output) | Sigma(input, output) => {
-            let (left_level, new) = check_type(index, tcs, *input.expression.clone())?;
+            let (left_level, new) = check_type(index, tcs, &mut input.expression)?;
             tcs = new;
-            let input_type = input.expression.eval(tcs.context());
+            let input_type = input.expression.clone().eval(tcs.context());
             let generated = generate_for(index, &input_type);
             let gamma = update_gamma(tcs.gamma, &input.pattern, input_type, generated)?;
-            let (right_level, _) = check_type(index + 1, TCS::new(gamma, tcs.context), *output)?;
+            let tcs = TCS::with_shared(gamma, tcs.context, tcs.warnings, tcs.hidden);
+            let (right_level, _) = check_type(index + 1, tcs, output)?;
             // Does this need to depend on the level of the return type?
             Ok(Value::Type(max(left_level, right_level)))
         }
-        Application(function, argument) => match *function {
-            Lambda(pattern, Some(parameter_type), return_value) => {
-                let parameter_type = *parameter_type.internal;
-                tcs = check(index, tcs, *argument, parameter_type.clone())?;
-                let generated = generate_for(index + 1, &parameter_type);
-                let tcs = tcs.update(pattern, parameter_type, generated)?;
-                check_infer(index + 1, tcs, *return_value)
+        Application(function, argument) => match check_infer(index, tcs_borrow!(tcs), function)? {
+            Value::Pi(input, output) => {
+                let context = tcs.context();
+                check(index, tcs, argument, *input)?;
+                Ok(output.instantiate(argument.clone().eval(context)))
             }
-            f => match check_infer(index, tcs_borrow!(tcs), f)? {
-                Value::Pi(input, output) => {
-                    let context = tcs.context();
-                    check(index, tcs, *argument.clone(), *input)?;
-                    Ok(output.instantiate(argument.eval(context)))
-                }
-                e => Err(TCE::WantPiBut(e, *argument)),
-            },
+            e => Err(TCE::WantPiBut(Box::new(e), *argument.clone())),
         },
-        Declaration(_, _) | Constant(_, _, _) => Err(tce_unreachable!()),
-        e => Err(TCE::CannotInfer(e)),
+        Lambda(pattern, Some(parameter_type), body) => {
+            let (_, new) = check_type(index, tcs, parameter_type)?;
+            tcs = new;
+            let parameter_type = parameter_type.clone().eval(tcs.context());
+            let generated = generate_for(index, &parameter_type);
+            let tcs = tcs.update(pattern.clone(), parameter_type.clone(), generated)?;
+            let body_type = check_infer(index + 1, tcs, body)?;
+            // The body type depends on the parameter through the generated value
+            let body_type = abstract_generated(index + 1, index, parameter_type.clone(), body_type);
+            Ok(Value::Pi(Box::new(parameter_type), body_type))
+        }
+        Ascription(expression, expression_type) => {
+            let (_, new) = check_type(index, tcs, expression_type)?;
+            tcs = new;
+            let expression_type = expression_type.clone().eval(tcs.context());
+            check(index, tcs, expression, expression_type.clone())?;
+            Ok(expression_type)
+        }
+        Located(span, expression) => {
+            check_infer(index, tcs, expression).map_err(|err| err.with_span(*span))
+        }
+        Clauses(clauses, expression) => {
+            check_clauses(clauses)?;
+            check_infer(index, tcs, expression)
+        }
+        Declaration(_, _)
+        | Constant(_, _, _)
+        | Fixity(_, _)
+        | Natural(_, _)
+        | Import(_, _)
+        | Module(_, _)
+        | Open(_, _) => Err(tce_unreachable!()),
+        e => Err(TCE::CannotInfer(e.clone())),
     }
 }
 
@@ -150,14 +218,21 @@ pub fn check_infer(index: u32, mut tcs: TCS, expression: Expression) -> TCM<Valu
 /// $$
 /// `checkT` in Mini-TT.<br/>
 /// Check if an expression is a well-typed type expression.
-pub fn check_type(index: u32, tcs: TCS, expression: Expression) -> TCM<(Level, TCS)> {
+pub fn check_type<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    expression: &mut Expression,
+) -> TCM<(Level, TCS<'a>)> {
     use crate::ast::Expression::*;
     match expression {
         Sum(constructors) => check_sum_type(index, tcs, constructors),
-        Pi(first, second) | Sigma(first, second) => check_telescoped(index, tcs, first, *second),
-        Merge(left, right) => check_merge_type(index, tcs, *left, *right),
-        Type(level) => Ok((level + 1, tcs)),
-        Void | One => Ok((0, tcs)),
+        Pi(first, second) | Sigma(first, second) => check_telescoped(index, tcs, first, second),
+        Merge(left, right) => check_merge_type(index, tcs, left, right),
+        Type(level) => Ok((*level + 1, tcs)),
+        Void | One | StringType | CharType => Ok((0, tcs)),
+        Located(span, expression) => {
+            check_type(index, tcs, expression).map_err(|err| err.with_span(*span))
+        }
         expression => {
             let inferred = check_infer(index, tcs_borrow!(tcs), expression)?;
             match inferred.level_safe() {
@@ -169,23 +244,23 @@ pub fn check_type(index: u32, tcs: TCS, expression: Expression) -> TCM<(Level, T
 }
 
 /// To reuse code that checks if a merge expression is well-typed between `check_type` and `check`
-pub fn check_merge_type(
+pub fn check_merge_type<'a>(
     index: u32,
-    mut tcs: TCS,
-    left: Expression,
-    right: Expression,
-) -> TCM<(Level, TCS)> {
-    let (left_level, new_tcs) = check_type(index, tcs, left.clone())?;
+    mut tcs: TCS<'a>,
+    left: &mut Expression,
+    right: &mut Expression,
+) -> TCM<(Level, TCS<'a>)> {
+    let (left_level, new_tcs) = check_type(index, tcs, left)?;
     tcs = new_tcs;
-    let (right_level, new_tcs) = check_type(index, tcs, right.clone())?;
+    let (right_level, new_tcs) = check_type(index, tcs, right)?;
     tcs = new_tcs;
     let left_branches = match left.clone().eval_to_sum(tcs.context()) {
         Some(branches) => branches,
-        None => return Err(TCE::WantSumBut(Either::Right(left))),
+        None => return Err(TCE::WantSumBut(Either::Right(left.clone()))),
     };
     let right_branches = match right.clone().eval_to_sum(tcs.context()) {
         Some(branches) => branches,
-        None => return Err(TCE::WantSumBut(Either::Right(right))),
+        None => return Err(TCE::WantSumBut(Either::Right(right.clone()))),
     };
     for left_branch in left_branches.into_iter() {
         if right_branches.contains(&left_branch) {
@@ -213,6 +288,14 @@ pub fn check_merge_type(
 ///       \Leftarrow \Pi(\textsf{Sum}\lang c\_1:A\_1 | \dots | c\_n:A\_n,v \rang)g}
 /// $$
 /// $$
+/// \frac{\rho,\Gamma\vdash\_l N\Rightarrow t\'
+///       \quad \rho,\Gamma\vdash\_l \textsf{fun}\ S\Leftarrow \Pi\ t\' (\\\_.t)}
+///      {\rho,\Gamma\vdash\_l (\textsf{fun}\ S)\ N\Leftarrow t}
+/// \quad
+/// \frac{\rho,\Gamma\vdash\_l N\Rightarrow \textsf{Sum}\lang\rang}
+///      {\rho,\Gamma\vdash\_l (\textsf{fun}())\ N\Leftarrow t}
+/// $$
+/// $$
 /// \frac{\rho,\Gamma\vdash\_l D\Rightarrow \Gamma\_1
 ///       \quad (\rho,\Gamma),\Gamma\_1\vdash\_l M\Leftarrow t}
 ///      {\rho,\Gamma\vdash\_l D; M\Leftarrow t}
@@ -228,90 +311,227 @@ pub fn check_merge_type(
 /// $$
 /// `check` in Mini-TT.<br/>
 /// However, telescope and gamma are preserved for REPL use.
-pub fn check(index: u32, mut tcs: TCS, expression: Expression, value: Value) -> TCM<TCS> {
+pub fn check<'a>(
+    index: u32,
+    mut tcs: TCS<'a>,
+    expression: &mut Expression,
+    value: Value,
+) -> TCM<TCS<'a>> {
     use crate::ast::Expression as E;
     use crate::ast::Value as V;
-    match (expression, value) {
+    if let Some(call) = expression.constructor_call() {
+        *expression = call;
+    }
+    match (&mut *expression, value) {
+        (E::Located(span, expression), value) => {
+            let span = *span;
+            let warnings = tcs.warnings.borrow().len();
+            let tcs = check(index, tcs, expression, value).map_err(|err| err.with_span(span))?;
+            tcs.locate_warnings(warnings, span);
+            Ok(tcs)
+        }
+        (E::Clauses(clauses, expression), value) => {
+            check_clauses(clauses)?;
+            let matched = matched_type(index, &tcs, expression, &value);
+            let tcs = check(index, tcs, expression, value)?;
+            for clause in unreachable_clauses(&clauses.patterns(), matched) {
+                let ((pattern, _), span) = &clauses.clauses[clause];
+                let warning = TCW::UnreachableClause(pattern.clone());
+                tcs.warn(match span {
+                    Some(span) => TCW::Spanned(Box::new(warning), *span),
+                    None => warning,
+                });
+            }
+            Ok(tcs)
+        }
         (E::Unit, V::One) | (E::One, V::Type(0)) => Ok(tcs),
         (E::Type(low), V::Type(high)) => {
-            if low < high {
+            if *low < high {
                 Ok(tcs)
             } else {
-                Err(TCE::TypeMismatch(V::Type(low + 1), V::Type(high)))
+                Err(TCE::mismatch(V::Type(*low + 1), V::Type(high)))
             }
         }
         // There's nothing left to check.
         (E::Void, _) => Ok(tcs),
-        (E::Lambda(pattern, _, body), V::Pi(signature, closure)) => {
+        (E::Lambda(pattern, parameter_type, body), V::Pi(signature, closure)) => {
+            if let Some(parameter_type) = parameter_type {
+                let (_, new) = check_type(index, tcs, parameter_type)?;
+                tcs = new;
+                let parameter_type = parameter_type.clone().eval(tcs.context());
+                tcs = check_subtype(index, tcs, *signature.clone(), parameter_type, true)?;
+            }
             let fake_tcs: TCS = tcs_borrow!(tcs);
-            let generated = generate_for(index, &*signature);
-            let fake_tcs = fake_tcs.update(pattern, *signature, generated.clone())?;
-            check(index + 1, fake_tcs, *body, closure.instantiate(generated))?;
+            let generated = generate_for(index, &signature);
+            let fake_tcs = fake_tcs.update(pattern.clone(), *signature, generated.clone())?;
+            check(index + 1, fake_tcs, body, closure.instantiate(generated))?;
             Ok(tcs)
         }
         (E::Pair(first, second), V::Sigma(first_type, second_type)) => {
-            tcs = check(index, tcs, *first.clone(), *first_type)?;
+            tcs = check(index, tcs, first, *first_type)?;
             let context = tcs.context();
-            check(
-                index,
-                tcs,
-                *second,
-                second_type.instantiate(first.eval(context)),
-            )
+            let second_type = second_type.instantiate(first.clone().eval(context));
+            check(index, tcs, second, second_type)
+        }
+        (E::Record(fields), record_type @ V::Sigma(_, _)) => {
+            let (tcs, pairs) = check_record(index, tcs, fields.clone(), record_type)?;
+            *expression = pairs;
+            Ok(tcs)
         }
         (E::Constructor(name, body), V::Sum(constructors)) => {
             let constructor = constructors
-                .get(&name)
-                .ok_or_else(|| TCE::InvalidConstructor(name))?
+                .get(name)
+                .ok_or_else(|| TCE::InvalidConstructor(name.clone()))?
                 .clone()
                 .reduce_to_value();
-            check(index, tcs, *body, constructor)
+            check(index, tcs, body, constructor)
         }
         (E::Sum(constructors), V::Type(level)) => {
             check_level(level, check_sum_type(index, tcs, constructors)?)
         }
         (E::Merge(left, right), V::Type(level)) => {
-            check_level(level, check_merge_type(index, tcs, *left, *right)?)
+            check_level(level, check_merge_type(index, tcs, left, right)?)
         }
         (E::Sigma(first, second), V::Type(level)) | (E::Pi(first, second), V::Type(level)) => {
-            check_level(level, check_telescoped(index, tcs, first, *second)?)
+            check_level(level, check_telescoped(index, tcs, first, second)?)
         }
         (E::Declaration(declaration, rest), rest_type) => {
-            let tcs = check_declaration(index, tcs, *declaration)?;
-            check(index, tcs, *rest, rest_type)
+            let tcs = check_declaration(index, tcs, declaration)?;
+            check(index, tcs, rest, rest_type)
+        }
+        (E::Natural(natural, rest), rest_type) => {
+            check_natural(index, tcs_borrow!(tcs), natural)?;
+            check(index, tcs, rest, rest_type)
+        }
+        (E::Import(import, _), _) => {
+            let err = TCE::UnresolvedImport(import.name());
+            Err(match import.span {
+                Some(span) => err.with_span(span),
+                None => err,
+            })
+        }
+        (E::Module(module, rest), rest_type) => {
+            let span = module.span;
+            let tcs = check_module(index, tcs, module).map_err(|err| match span {
+                Some(span) => err.with_span(span),
+                None => err,
+            })?;
+            check(index, tcs, rest, rest_type)
+        }
+        (E::Open(open, rest), rest_type) => {
+            let tcs = tcs.open(open).map_err(|err| match open.span {
+                Some(span) => err.with_span(span),
+                None => err,
+            })?;
+            check(index, tcs, rest, rest_type)
+        }
+        (E::Export(export, rest), rest_type) => {
+            let tcs = check(index, tcs, rest, rest_type)?;
+            check_exports(&tcs, export)?;
+            Ok(tcs)
+        }
+        (E::Fixity(fixity, rest), rest_type) => {
+            *expression = fixity.clone().into_constant(*rest.clone());
+            check(index, tcs, expression, rest_type)
         }
         (E::Constant(pattern, body, rest), rest_type) => {
-            let signature = check_infer(index, tcs_borrow!(tcs), *body.clone())?;
-            let body_val = body.eval(tcs.context());
-            let tcs = tcs.update(pattern, signature, body_val)?;
-            check(index, tcs, *rest, rest_type)
+            let signature = check_infer(index, tcs_borrow!(tcs), body)?;
+            let body_val = body.clone().eval(tcs.context());
+            let tcs = tcs.update(pattern.clone(), signature, body_val)?;
+            check(index, tcs, rest, rest_type).map_err(|err| match err.missing_case() {
+                TCE::MissingCaseIn(mut cases) => match fill_pattern(pattern, &mut cases) {
+                    Some(case) => match &**body {
+                        E::Var(name) => {
+                            cases.insert(name.clone(), case);
+                            TCE::MissingCaseIn(cases)
+                        }
+                        // The scrutinee of a `match`.
+                        _ if cases.is_empty() => TCE::MissingCase(case),
+                        _ => {
+                            cases.insert(pattern.to_string(), case);
+                            TCE::MissingCaseIn(cases)
+                        }
+                    },
+                    None => TCE::MissingCaseIn(cases),
+                },
+                err => err,
+            })
+        }
+        // Elimination of a variable, used by nested patterns. The type may depend on the value
+        // being split when it's a generated one (the field of a constructor or a parameter),
+        // otherwise the elimination is non-dependent.
+        (E::Application(function, argument), value) if matches!(**function, E::Split(_)) => {
+            let argument_type = check_infer(index, tcs_borrow!(tcs), argument)?;
+            if **function == E::Split(Default::default()) && !is_empty_sum(&argument_type) {
+                return Err(TCE::WantEmptyBut(argument_type));
+            }
+            let motive = match argument.clone().eval(tcs.context()) {
+                V::Neutral(Neutral::Generated(generated)) => {
+                    abstract_generated(index, generated, argument_type.clone(), value)
+                }
+                _ => Closure::Value(Box::new(value)),
+            };
+            let signature = V::Pi(Box::new(argument_type), motive);
+            let checked = check(index, tcs, function, signature);
+            checked.map_err(|err| match (err.missing_case(), &**argument) {
+                (TCE::MissingCase(case), E::Var(name)) => {
+                    TCE::MissingCaseIn(vec![(name.clone(), case)].into_iter().collect())
+                }
+                (TCE::MissingCaseIn(mut cases), E::Var(name)) => match cases.remove("") {
+                    Some(case) => {
+                        cases.insert(name.clone(), case);
+                        TCE::MissingCaseIn(cases)
+                    }
+                    None => TCE::MissingCaseIn(cases),
+                },
+                (err, _) => err,
+            })
         }
         // I really wish to have box pattern here :(
-        (E::Split(mut branches), V::Pi(sum, closure)) => match *sum {
+        (E::Split(branches), V::Pi(sum, closure)) => match *sum {
             V::Sum(sum_branches) => {
+                let constructors: Vec<_> = sum_branches.keys().cloned().collect();
                 for (name, branch) in sum_branches.into_iter() {
-                    let pattern_match = match branches.remove(&name) {
-                        Some(pattern_match) => *pattern_match,
-                        None => return Err(TCE::MissingCase(name)),
-                    };
                     let branch_value = branch.reduce_to_value();
+                    let clause = if branches.contains_key(&name) {
+                        name.clone()
+                    } else if is_empty_sum(&branch_value) {
+                        // There's no value of an empty sum type, nor this constructor.
+                        continue;
+                    } else if branches.contains_key(DEFAULT_CLAUSE) {
+                        DEFAULT_CLAUSE.to_owned()
+                    } else {
+                        return Err(TCE::MissingCase(wildcard_fields(index, name, branch_value)));
+                    };
+                    let pattern_match = branches.get_mut(&clause).unwrap();
                     let signature = V::Pi(
                         Box::new(branch_value),
                         Closure::Choice(Box::new(closure.clone()), name.clone()),
                     );
-                    tcs = check(index, tcs, pattern_match, signature)?;
+                    let checked = check(index, tcs, pattern_match, signature);
+                    tcs = match checked.map_err(TCE::missing_case) {
+                        Ok(tcs) => tcs,
+                        Err(TCE::MissingCaseIn(cases)) => {
+                            return Err(missing_case_in(name, pattern_match, cases))
+                        }
+                        Err(err) => return Err(err),
+                    };
                 }
-                if branches.is_empty() {
+                let unexpected =
+                    |clause: &&String| *clause != DEFAULT_CLAUSE && !constructors.contains(*clause);
+                let clauses: Vec<_> = (branches.keys().filter(unexpected))
+                    .map(|clause| clause.as_str())
+                    .collect();
+                if clauses.is_empty() {
                     Ok(tcs)
                 } else {
-                    let clauses: Vec<_> = branches.keys().map(|br| br.as_str()).collect();
                     Err(TCE::UnexpectedCases(clauses.join(" | ")))
                 }
             }
             not_sum_so_fall_through => check_fallback(
                 index,
                 tcs,
-                E::Split(branches),
+                expression,
                 V::Pi(Box::new(not_sum_so_fall_through), closure),
             ),
         },
@@ -319,6 +539,40 @@ pub fn check(index: u32, mut tcs: TCS, expression: Expression, value: Value) ->
     }
 }
 
+/// This is an extension, it's not present in Mini-TT.<br/>
+/// Check the fields of a record literal against the ones of `record_type` by name, in the order
+/// of the type, and elaborate them to the pairs in that order.
+fn check_record<'a>(
+    index: u32,
+    mut tcs: TCS<'a>,
+    mut fields: Vec<(String, Expression)>,
+    record_type: Value,
+) -> TCM<(TCS<'a>, Expression)> {
+    let mut elaborated = Vec::with_capacity(fields.len());
+    let mut rest_type = record_type.clone();
+    while let Value::Sigma(first, second) = rest_type.clone() {
+        let name = match field_name(&second) {
+            Some(name) => name,
+            None => break,
+        };
+        let position = fields.iter().position(|(field, _)| field == name);
+        let missing = || TCE::MissingField(name.to_owned(), Box::new(record_type.clone()));
+        let (_, mut field) = fields.remove(position.ok_or_else(missing)?);
+        tcs = check(index, tcs, &mut field, *first)?;
+        rest_type = second.instantiate(field.clone().eval(tcs.context()));
+        elaborated.push(field);
+    }
+    if let Some((name, _)) = fields.into_iter().next() {
+        return Err(TCE::NoSuchField(name, Box::new(record_type)));
+    }
+    let mut unit = Expression::Unit;
+    let tcs = check(index, tcs, &mut unit, rest_type)?;
+    let pairs = (elaborated.into_iter().rev()).fold(unit, |rest, field| {
+        Expression::Pair(Box::new(field), Box::new(rest))
+    });
+    Ok((tcs, pairs))
+}
+
 /// $$
 /// \frac{i < j}{\Gamma\vdash \textsf{U}\_i <: \textsf{U}\_j}
 /// $$
@@ -338,7 +592,12 @@ pub fn check_level(level: u32, (actual_level, tcs): (u32, TCS)) -> TCM<TCS> {
 /// $$
 /// Fallback rule of instance check.<br/>
 /// First infer the expression type, then do subtyping comparison.
-pub fn check_fallback(index: u32, tcs: TCS, body: Expression, signature: Value) -> TCM<TCS> {
+pub fn check_fallback<'a>(
+    index: u32,
+    tcs: TCS<'a>,
+    body: &mut Expression,
+    signature: Value,
+) -> TCM<TCS<'a>> {
     let inferred = check_infer(index, tcs_borrow!(tcs), body)?;
     check_subtype(index, tcs, inferred, signature, true)
 }
@@ -351,10 +610,14 @@ pub fn check_fallback(index: u32, tcs: TCS, body: Expression, signature: Value)
 ///       \textsf{Sum}(c\_1\ A\_1|\dots|c\_n\ A\_n)\Leftarrow \textsf{U}}
 /// $$
 /// To reuse code that checks if a sum type is well-typed between `check_type` and `check`
-pub fn check_sum_type(index: u32, mut tcs: TCS, constructors: Branch) -> TCM<(Level, TCS)> {
+pub fn check_sum_type<'a>(
+    index: u32,
+    mut tcs: TCS<'a>,
+    constructors: &mut Branch,
+) -> TCM<(Level, TCS<'a>)> {
     let mut max_level = 0;
-    for constructor in constructors.values().cloned() {
-        let (level, new) = check_type(index, tcs, *constructor)?;
+    for constructor in constructors.values_mut() {
+        let (level, new) = check_type(index, tcs, constructor)?;
         tcs = new;
         if level > max_level {
             max_level = level;
@@ -370,21 +633,184 @@ pub fn check_sum_type(index: u32, mut tcs: TCS, constructors: Branch) -> TCM<(Le
 ///      {\rho,\Gamma\vdash\_l (\Pi /\Sigma) \ p:A.B}
 /// $$
 /// To reuse code that checks if a sigma or a pi type is well-typed between `check_type` and `check`
-pub fn check_telescoped(
+pub fn check_telescoped<'a>(
     index: u32,
-    mut tcs: TCS,
-    first: Typed,
-    second: Expression,
-) -> TCM<(Level, TCS)> {
-    let (_, new) = check_type(index, tcs, *first.expression.clone())?;
+    mut tcs: TCS<'a>,
+    first: &mut Typed,
+    second: &mut Expression,
+) -> TCM<(Level, TCS<'a>)> {
+    let (_, new) = check_type(index, tcs, &mut first.expression)?;
     tcs = new;
-    let ty = first.expression.eval(tcs.context());
+    let ty = first.expression.clone().eval(tcs.context());
     let generated = generate_for(index, &ty);
-    let internal_tcs = tcs_borrow!(tcs).update(first.pattern, ty, generated)?;
+    let internal_tcs = tcs_borrow!(tcs).update(first.pattern.clone(), ty, generated)?;
     let (level, _) = check_type(index + 1, internal_tcs, second)?;
     Ok((level, tcs))
 }
 
+/// The missing case of a nested `split` on the fields of constructor `name`,
+/// where the fields are bound by the `clause`.
+fn missing_case_in(
+    name: String,
+    clause: &Expression,
+    mut cases: BTreeMap<String, CasePattern>,
+) -> TCE {
+    let fields = match clause.unlocated() {
+        Expression::Lambda(pattern, _, _) => fill_pattern(pattern, &mut cases),
+        _ => None,
+    };
+    let fields = fields.unwrap_or(CasePattern::Pattern(Pattern::Unit));
+    let case = CasePattern::constructor(name, fields);
+    if cases.is_empty() {
+        TCE::MissingCase(case)
+    } else {
+        cases.insert(String::new(), case);
+        TCE::MissingCaseIn(cases)
+    }
+}
+
+/// `pattern` with the variables replaced by their missing `cases` and the others by `_`,
+/// if any of the variables is in `cases`.
+fn fill_pattern(
+    pattern: &Pattern,
+    cases: &mut BTreeMap<String, CasePattern>,
+) -> Option<CasePattern> {
+    fn fill(pattern: &Pattern, cases: &mut BTreeMap<String, CasePattern>) -> CasePattern {
+        match pattern {
+            Pattern::Var(name) => {
+                (cases.remove(name)).unwrap_or(CasePattern::Pattern(Pattern::Unit))
+            }
+            Pattern::Unit => CasePattern::Pattern(Pattern::Unit),
+            Pattern::Pair(first, second) => {
+                let first = fill(first, cases);
+                CasePattern::pair(first, fill(second, cases))
+            }
+            Pattern::Located(_, pattern) => fill(pattern, cases),
+        }
+    }
+    if cases.keys().any(|name| pattern.contains(name)) {
+        Some(fill(pattern, cases))
+    } else {
+        None
+    }
+}
+
+/// The constructor `name` with a wildcard for each of its `fields`, `Cons _ _`.<br/>
+/// The fields of a constructor are a `Sigma` of their types, the last one isn't a `Sigma`.
+fn wildcard_fields(index: u32, name: String, mut fields: Value) -> CasePattern {
+    let wildcard = || CasePattern::Pattern(Pattern::Unit);
+    let mut pattern = wildcard();
+    while let Value::Sigma(_, rest) = fields {
+        pattern = CasePattern::pair(wildcard(), pattern);
+        fields = rest.instantiate(generate_value(index));
+    }
+    CasePattern::constructor(name, pattern)
+}
+
+fn is_empty_sum(ty: &Value) -> bool {
+    match ty {
+        Value::Sum(constructors) => constructors.is_empty(),
+        _ => false,
+    }
+}
+
+/// Abstract `body` over the generated value `generated`, as a closure taking a `parameter`.
+/// <br/>
+/// `body` is read back and evaluated again with the generated value bound to the argument,
+/// the other generated values, like the outer ones, and the postulates are kept.
+fn abstract_generated(index: u32, generated: u32, parameter: Value, body: Value) -> Closure {
+    let body = body.read_back(index);
+    let mut free = BTreeMap::new();
+    body.generated_values(&mut free);
+    free.remove(&generated);
+    let context = free.into_iter().fold(nil_rc(), |context, (outer, value)| {
+        let name = Pattern::Var(generated_name(outer));
+        up_var_rc(context, name, value)
+    });
+    let body = body.into_expression();
+    let pattern = Pattern::Var(generated_name(generated));
+    Closure::Abstraction(pattern, Some(Box::new(parameter)), body, Box::new(context))
+}
+
+/// Reject the first clause matching a constructor where the clauses before it match a pair,
+/// or the other way around, located at the clause.
+fn check_clauses(clauses: &SourceClauses) -> TCM<()> {
+    match mismatched_clause(&clauses.patterns()) {
+        None => Ok(()),
+        Some((clause, pattern)) => {
+            let ((clause, _), span) = &clauses.clauses[clause];
+            let err = TCE::MismatchedClause(Box::new(clause.clone()), Box::new(pattern));
+            Err(match span {
+                Some(span) => err.with_span(*span),
+                None => err,
+            })
+        }
+    }
+}
+
+/// The type of the values matched by the clauses of `expression`, a `split` checked against
+/// `value` or a `match` on a variable or a tuple. `None` if it's not known.
+fn matched_type(
+    index: u32,
+    tcs: &TCS,
+    expression: &Expression,
+    value: &Value,
+) -> Option<MatchedType> {
+    use crate::ast::Expression as E;
+    let scrutinee = match (expression.unlocated(), value) {
+        (E::Split(_), Value::Pi(domain, _)) => {
+            return Some(MatchedType(index, *domain.clone()));
+        }
+        (E::Application(split, scrutinee), _) if matches!(**split, E::Split(_)) => scrutinee,
+        (E::Constant(_, scrutinee, _), _) => scrutinee,
+        _ => return None,
+    };
+    let matched = check_infer(index, tcs_borrow!(tcs), &mut (**scrutinee).clone()).ok()?;
+    Some(MatchedType(index, matched))
+}
+
+/// The type of the values matched by some clauses. The types of the second components of
+/// `Sigma`s are instantiated with the values generated from the index.
+#[derive(Clone)]
+struct MatchedType(u32, Value);
+
+impl Matched for MatchedType {
+    fn constructors(&self) -> Option<Vec<(String, Option<Self>)>> {
+        let MatchedType(index, matched) = self;
+        let branches = match matched {
+            Value::Sum(branches) => branches,
+            _ => return None,
+        };
+        let constructors = (branches.iter())
+            .map(|(name, branch)| (name, branch.clone().reduce_to_value()))
+            .filter(|(_, fields)| !is_empty_sum(fields))
+            .map(|(name, fields)| (name.clone(), Some(MatchedType(*index, fields))));
+        Some(constructors.collect())
+    }
+
+    fn components(&self) -> (Option<Self>, Option<Self>) {
+        match self {
+            MatchedType(index, Value::Sigma(first, second)) => {
+                let second = second.clone().instantiate(generate_value(*index));
+                let first = MatchedType(index + 1, *first.clone());
+                (Some(first), Some(MatchedType(index + 1, second)))
+            }
+            _ => (None, None),
+        }
+    }
+}
+
+/// The name bound by the closure of a `Sigma`, the name of the field in a record type.
+fn field_name(closure: &Closure) -> Option<&str> {
+    match closure {
+        Closure::Abstraction(pattern, ..) => match pattern.unlocated() {
+            Pattern::Var(name) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 fn generate_for(index: u32, ty: &Value) -> Value {
     match &ty {
         Value::One => Value::Unit,
@@ -39,10 +39,11 @@ pub mod expr;
 /// $$
 pub mod decl;
 
-use self::decl::check_declaration;
+use self::decl::{check_declaration, check_exports, check_natural, postulate_declaration};
 use self::expr::{check, check_infer};
-use self::tcm::{TCM, TCS};
-use crate::ast::{Declaration, Expression, Value};
+use self::read_back::{NormalExpression, ReadBack};
+use self::tcm::{TCE, TCM, TCS};
+use crate::ast::{up_var_rc, Declaration, Expression, Neutral, Pattern, Value};
 
 /// `checkMain` in Mini-TT.
 pub fn check_main<'a>(expression: Expression) -> TCM<TCS<'a>> {
@@ -50,18 +51,187 @@ pub fn check_main<'a>(expression: Expression) -> TCM<TCS<'a>> {
 }
 
 /// For REPL: check an expression under an existing context
-pub fn check_contextual(tcs: TCS, expression: Expression) -> TCM<TCS> {
-    check(0, tcs, expression, Value::One)
+pub fn check_contextual(tcs: TCS, mut expression: Expression) -> TCM<TCS> {
+    check(0, tcs, &mut expression, Value::One)
 }
 
 /// For REPL: infer the type of an expression under an existing context
-pub fn check_infer_contextual(tcs: TCS, expression: Expression) -> TCM<Value> {
-    check_infer(0, tcs, expression)
+pub fn check_infer_contextual(tcs: TCS, mut expression: Expression) -> TCM<Value> {
+    check_infer(0, tcs, &mut expression)
+}
+
+/// For REPL: infer the type of an expression under an existing context, read back to an expression
+/// that refers to the types defined in the context by their names.
+pub fn check_infer_named_contextual(tcs: TCS, mut expression: Expression) -> TCM<Expression> {
+    let names = named_types(&tcs);
+    let inferred = check_infer(0, tcs, &mut expression)?;
+    Ok(inferred.read_back_please().into_named_expression(&names))
+}
+
+/// The normal forms of the types defined in the context, except the ones that are not printed
+/// shorter by their names.
+fn named_types(tcs: &TCS) -> Vec<(String, NormalExpression)> {
+    (tcs.gamma.iter())
+        .filter(|(_, signature)| matches!(signature, Value::Type(_)))
+        .filter_map(|(name, _)| {
+            let normal = Expression::Var(name.clone()).eval(tcs.context());
+            let normal = normal.read_back_please();
+            match normal {
+                NormalExpression::Sum(_)
+                | NormalExpression::Pi(..)
+                | NormalExpression::Sigma(..) => Some((name.clone(), normal)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// For REPL: infer the type of an expression under an existing context, and return the expression
+/// with its field projections and record literals elaborated, so it can be evaluated.
+pub fn check_elaborate_contextual(tcs: TCS, mut expression: Expression) -> TCM<Expression> {
+    check_infer(0, tcs, &mut expression)?;
+    Ok(expression)
+}
+
+/// Similar to `checkMain` in Mini-TT, but does not stop at the first error.
+/// Returns the checked state and all the errors.
+pub fn check_main_recovering<'a>(expression: Expression) -> (TCS<'a>, Vec<TCE>) {
+    check_contextual_recovering(Default::default(), expression)
+}
+
+/// For REPL: check an expression under an existing context, without stopping at the first error.
+///
+/// A declaration that fails to type-check is replaced with a postulate of its signature, a constant
+/// that fails to type-check is bound without a type, so the rest of the declarations can still be
+/// checked. References to the names left without a type are not reported again.
+pub fn check_contextual_recovering(tcs: TCS, expression: Expression) -> (TCS, Vec<TCE>) {
+    let mut recovering = Recovering {
+        errors: Vec::new(),
+        failed: Vec::new(),
+        postulate: u32::MAX,
+    };
+    let tcs = recovering.check(tcs, expression);
+    (tcs, recovering.errors)
+}
+
+/// State of `check_contextual_recovering`.
+struct Recovering {
+    errors: Vec<TCE>,
+    /// Names of the definitions that failed to type-check.
+    failed: Vec<String>,
+    /// The index of the last postulate.<br/>
+    /// Postulates are indexed downwards from `u32::MAX`, away from the indices generated for
+    /// binders, and they're never used as an index.
+    postulate: u32,
+}
+
+impl Recovering {
+    /// Report `err`, unless it's a reference to a definition that failed to type-check.
+    fn report(&mut self, err: TCE) {
+        match err.unlocated() {
+            TCE::UnresolvedName(name) if self.failed.contains(name) => {}
+            _ => self.errors.push(err),
+        }
+    }
+
+    /// Report `err` of the definition of `pattern`, and postulate the names it binds.
+    fn fail(&mut self, err: TCE, pattern: &Pattern) -> Value {
+        self.report(err);
+        let names = pattern.names().into_iter().map(Into::into);
+        self.failed.extend(names);
+        self.postulate(pattern)
+    }
+
+    /// The value binding each name of `pattern` to a postulate of that name.
+    fn postulate(&mut self, pattern: &Pattern) -> Value {
+        match pattern {
+            Pattern::Var(name) => {
+                self.postulate -= 1;
+                Value::Neutral(Neutral::Postulate(self.postulate, name.clone()))
+            }
+            Pattern::Pair(first, second) => {
+                let first = self.postulate(first);
+                Value::Pair(Box::new(first), Box::new(self.postulate(second)))
+            }
+            Pattern::Unit => Value::Unit,
+            Pattern::Located(_, pattern) => self.postulate(pattern),
+        }
+    }
+
+    fn check<'a>(&mut self, mut tcs: TCS<'a>, mut expression: Expression) -> TCS<'a> {
+        loop {
+            match expression {
+                Expression::Declaration(mut declaration, rest) => {
+                    let checked = check_declaration(0, tcs_borrow!(tcs), &mut declaration)
+                        .map(TCS::into_owned);
+                    tcs = checked.unwrap_or_else(|err| {
+                        let postulate = self.fail(err, &declaration.pattern);
+                        postulate_declaration(postulate, tcs, *declaration)
+                    });
+                    expression = *rest;
+                }
+                // The names of a module that fails to type-check are left unbound
+                Expression::Module(module, rest) => {
+                    let names: Vec<_> = (module.names().iter())
+                        .map(|name| module.qualify(name))
+                        .collect();
+                    let module = Expression::Module(module, Box::new(Expression::Void));
+                    match check_contextual(tcs_borrow!(tcs), module).map(TCS::into_owned) {
+                        Ok(checked) => tcs = checked,
+                        Err(err) => {
+                            self.report(err);
+                            self.failed.extend(names);
+                        }
+                    }
+                    expression = *rest;
+                }
+                Expression::Open(open, rest) => {
+                    let open = Expression::Open(open, Box::new(Expression::Void));
+                    match check_contextual(tcs_borrow!(tcs), open).map(TCS::into_owned) {
+                        Ok(checked) => tcs = checked,
+                        Err(err) => self.report(err),
+                    }
+                    expression = *rest;
+                }
+                Expression::Export(export, rest) => {
+                    let tcs = self.check(tcs, *rest);
+                    if let Err(err) = check_exports(&tcs, &export) {
+                        self.report(err);
+                    }
+                    return tcs;
+                }
+                Expression::Fixity(fixity, rest) => expression = fixity.into_constant(*rest),
+                Expression::Natural(natural, rest) => {
+                    if let Err(err) = check_natural(0, tcs_borrow!(tcs), &natural) {
+                        self.report(err);
+                    }
+                    expression = *rest;
+                }
+                Expression::Constant(pattern, body, rest) => {
+                    let constant =
+                        Expression::Constant(pattern.clone(), body, Box::new(Expression::Void));
+                    let checked = check_contextual(tcs_borrow!(tcs), constant).map(TCS::into_owned);
+                    tcs = checked.unwrap_or_else(|err| {
+                        let postulate = self.fail(err, &pattern);
+                        let context = up_var_rc(tcs.context, pattern, postulate);
+                        TCS::with_shared(tcs.gamma, context, tcs.warnings, tcs.hidden)
+                    });
+                    expression = *rest;
+                }
+                expression => {
+                    if let Err(err) = check_contextual(tcs_borrow!(tcs), expression) {
+                        self.report(err);
+                    }
+                    return tcs;
+                }
+            }
+        }
+    }
 }
 
 /// Similar to `checkMain` in Mini-TT, but for a declaration.
-pub fn check_declaration_main<'a>(declaration: Declaration) -> TCM<TCS<'a>> {
-    check_declaration(0, Default::default(), declaration)
+pub fn check_declaration_main<'a>(mut declaration: Declaration) -> TCM<TCS<'a>> {
+    check_declaration(0, Default::default(), &mut declaration)
 }
 
 #[cfg(test)]
@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
 use std::rc::Rc;
 
@@ -45,6 +45,14 @@ pub enum NormalExpression {
     Sum(NormalCaseTree),
     /// $[K]$
     Neutral(NormalNeutral),
+    /// `String`, this is an extension
+    StringType,
+    /// `Char`, this is an extension
+    CharType,
+    /// `"bla"` or `'c'`, this is an extension
+    Literal(Literal),
+    /// Partially applied built-in function, this is an extension
+    Primitive(Primitive, Vec<Self>),
 }
 
 /// `genV` in Mini-TT.
@@ -166,10 +174,23 @@ impl ReadBack for Value {
             Value::Split(case_tree) => Split(read_back_branches(index, case_tree)),
             Value::Sum(constructors) => Sum(read_back_branches(index, constructors)),
             Value::Neutral(neutral) => Neutral(neutral.read_back(index)),
+            Value::StringType => StringType,
+            Value::CharType => CharType,
+            Value::Literal(literal) => Literal(literal),
+            Value::Primitive(primitive, arguments) => {
+                Primitive(primitive, read_back_arguments(index, arguments))
+            }
         }
     }
 }
 
+fn read_back_arguments(index: u32, arguments: Vec<Value>) -> Vec<NormalExpression> {
+    arguments
+        .into_iter()
+        .map(|argument| argument.read_back(index))
+        .collect()
+}
+
 fn read_back_branches(index: u32, branches: CaseTree) -> NormalCaseTree {
     let mut read_back_constructors = BTreeMap::new();
     for (name, case) in branches.into_iter() {
@@ -207,6 +228,11 @@ impl ReadBack for &GenericTelescope<Value> {
             UpDec(context, declaration) => {
                 Rc::new(UpDec(context.read_back(index), declaration.clone()))
             }
+            // Only the expressions from `into_expression` refer to the generated variables,
+            // and their values are what they are read back to
+            UpVar(context, Pattern::Var(name), _) if is_generated_name(name) => {
+                context.read_back(index)
+            }
             UpVar(context, pattern, val) => Rc::new(UpVar(
                 context.read_back(index),
                 pattern.clone(),
@@ -234,6 +260,7 @@ impl ReadBack for Neutral {
         use crate::ast::GenericNeutral::*;
         match self {
             Generated(index) => Generated(index),
+            Postulate(index, name) => Postulate(index, name),
             Application(function, argument) => Application(
                 Box::new(function.read_back(index)),
                 Box::new(argument.read_back(index)),
@@ -244,6 +271,361 @@ impl ReadBack for Neutral {
                 read_back_branches(index, case_tree),
                 Box::new(body.read_back(index)),
             ),
+            Primitive(primitive, arguments) => {
+                Primitive(primitive, read_back_arguments(index, arguments))
+            }
+        }
+    }
+}
+
+/// The variable standing for the generated value $\textsf{x}_i$ in the expressions of the
+/// normal forms, printed the same way.
+pub fn generated_name(index: u32) -> String {
+    format!("<{}>", index)
+}
+
+fn is_generated_name(name: &str) -> bool {
+    let index = name
+        .strip_prefix('<')
+        .and_then(|name| name.strip_suffix('>'));
+    index.is_some_and(|index| index.parse::<u32>().is_ok())
+}
+
+impl NormalExpression {
+    /// This is not present in Mini-TT.<br/>
+    /// The expression evaluating to `self`, where the generated value $\textsf{x}_i$ is the
+    /// variable `generated_name(i)`.
+    /// The contexts of the case trees are rebuilt by declarations around their expressions.
+    pub fn into_expression(self) -> Expression {
+        use crate::ast::Expression as E;
+        use crate::check::read_back::NormalExpression::*;
+        let var = |index| Pattern::Var(generated_name(index));
+        match self {
+            Lambda(index, body) => E::Lambda(var(index), None, Box::new(body.into_expression())),
+            Pair(first, second) => E::Pair(
+                Box::new(first.into_expression()),
+                Box::new(second.into_expression()),
+            ),
+            Unit => E::Unit,
+            One => E::One,
+            Type(level) => E::Type(level),
+            Pi(input, index, output) => E::Pi(
+                Typed::new(var(index), input.into_expression()),
+                Box::new(output.into_expression()),
+            ),
+            Sigma(first, index, second) => E::Sigma(
+                Typed::new(var(index), first.into_expression()),
+                Box::new(second.into_expression()),
+            ),
+            Constructor(name, body) => E::Constructor(name, Box::new(body.into_expression())),
+            Split(case_tree) => case_tree_into_expression(case_tree, false),
+            Sum(case_tree) => case_tree_into_expression(case_tree, true),
+            Neutral(neutral) => neutral.into_expression(),
+            StringType => E::StringType,
+            CharType => E::CharType,
+            Literal(literal) => E::Literal(literal),
+            Primitive(primitive, arguments) => applications(E::Primitive(primitive), arguments),
+        }
+    }
+}
+
+impl NormalExpression {
+    /// This is not present in Mini-TT.<br/>
+    /// Collect the generated values and the postulates in `self` into `generated` by their
+    /// indices, including the ones in the contexts of the case trees.
+    pub fn generated_values(&self, generated: &mut BTreeMap<u32, Value>) {
+        use crate::check::read_back::NormalExpression::*;
+        match self {
+            Lambda(_, body) | Constructor(_, body) => body.generated_values(generated),
+            Pair(first, second) | Pi(first, _, second) | Sigma(first, _, second) => {
+                first.generated_values(generated);
+                second.generated_values(generated);
+            }
+            Split(case_tree) | Sum(case_tree) => case_tree_generated_values(case_tree, generated),
+            Neutral(neutral) => neutral.generated_values(generated),
+            Primitive(_, arguments) => {
+                (arguments.iter()).for_each(|argument| argument.generated_values(generated))
+            }
+            Unit | One | Type(_) | StringType | CharType | Literal(_) => {}
+        }
+    }
+}
+
+impl NormalNeutral {
+    /// `NormalExpression::generated_values` of a neutral value.
+    pub fn generated_values(&self, generated: &mut BTreeMap<u32, Value>) {
+        use crate::ast::GenericNeutral::*;
+        match self {
+            Generated(index) => {
+                generated.insert(*index, generate_value(*index));
+            }
+            Postulate(index, name) => {
+                let postulate = Postulate(*index, name.clone());
+                generated.insert(*index, Value::Neutral(postulate));
+            }
+            Application(function, argument) => {
+                function.generated_values(generated);
+                argument.generated_values(generated);
+            }
+            First(neutral) | Second(neutral) => neutral.generated_values(generated),
+            Split(case_tree, body) => {
+                case_tree_generated_values(case_tree, generated);
+                body.generated_values(generated);
+            }
+            Primitive(_, arguments) => {
+                (arguments.iter()).for_each(|argument| argument.generated_values(generated))
+            }
+        }
+    }
+}
+
+fn case_tree_generated_values(case_tree: &NormalCaseTree, generated: &mut BTreeMap<u32, Value>) {
+    for case in case_tree.values() {
+        if let Either::Left(normal) = &case.expression {
+            normal.generated_values(generated);
+        }
+        let mut context = &case.context;
+        while let GenericTelescope::UpVar(outer, _, _) | GenericTelescope::UpDec(outer, _) =
+            &**context
+        {
+            if let GenericTelescope::UpVar(_, _, value) = &**context {
+                value.generated_values(generated);
+            }
+            context = outer;
+        }
+    }
+}
+
+impl NormalNeutral {
+    /// This is not present in Mini-TT.<br/>
+    /// The expression evaluating to `self`, see `NormalExpression::into_expression`.
+    pub fn into_expression(self) -> Expression {
+        use crate::ast::Expression as E;
+        use crate::ast::GenericNeutral::*;
+        match self {
+            Generated(index) | Postulate(index, _) => E::Var(generated_name(index)),
+            Application(function, argument) => E::Application(
+                Box::new(function.into_expression()),
+                Box::new(argument.into_expression()),
+            ),
+            First(neutral) => E::First(Box::new(neutral.into_expression())),
+            Second(neutral) => E::Second(Box::new(neutral.into_expression())),
+            Split(case_tree, body) => E::Application(
+                Box::new(case_tree_into_expression(case_tree, false)),
+                Box::new(body.into_expression()),
+            ),
+            Primitive(primitive, arguments) => applications(E::Primitive(primitive), arguments),
+        }
+    }
+}
+
+fn applications(function: Expression, arguments: Vec<NormalExpression>) -> Expression {
+    (arguments.into_iter()).fold(function, |function, argument| {
+        Expression::Application(Box::new(function), Box::new(argument.into_expression()))
+    })
+}
+
+impl NormalExpression {
+    /// This is not present in Mini-TT.<br/>
+    /// The expression printing `self` for the types named by `names`: subterms that are the
+    /// normal form of one of them are printed as its name, binders not used by their bodies
+    /// are printed as `_` and the constants of the evaluated cases as their normal forms.
+    /// Unlike `into_expression`, the contexts of the case trees are left out.
+    pub fn into_named_expression(self, names: &[(String, NormalExpression)]) -> Expression {
+        self.named(names, &mut Default::default())
+    }
+
+    /// `into_named_expression`, collecting the generated values used in `used`.
+    fn named(self, names: &[(String, Self)], used: &mut BTreeSet<u32>) -> Expression {
+        use crate::ast::Expression as E;
+        use crate::check::read_back::NormalExpression::*;
+        if let Some((name, _)) = names.iter().find(|(_, normal)| *normal == self) {
+            return E::Var(name.clone());
+        }
+        let binder = |index, used: &mut BTreeSet<u32>| match used.remove(&index) {
+            true => Pattern::Var(generated_name(index)),
+            false => Pattern::Unit,
+        };
+        match self {
+            Lambda(index, body) => {
+                let body = body.named(names, used);
+                E::Lambda(binder(index, used), None, Box::new(body))
+            }
+            Pair(first, second) => E::Pair(
+                Box::new(first.named(names, used)),
+                Box::new(second.named(names, used)),
+            ),
+            Pi(input, index, output) => {
+                let input = input.named(names, used);
+                let output = output.named(names, used);
+                E::Pi(Typed::new(binder(index, used), input), Box::new(output))
+            }
+            Sigma(first, index, second) => {
+                let first = first.named(names, used);
+                let second = second.named(names, used);
+                E::Sigma(Typed::new(binder(index, used), first), Box::new(second))
+            }
+            Constructor(name, body) => E::Constructor(name, Box::new(body.named(names, used))),
+            Split(case_tree) => E::Split(named_case_tree(case_tree, names, used)),
+            Sum(case_tree) => E::Sum(named_case_tree(case_tree, names, used)),
+            Neutral(neutral) => neutral.named(names, used),
+            Primitive(primitive, arguments) => {
+                (arguments.into_iter()).fold(E::Primitive(primitive), |function, argument| {
+                    let argument = argument.named(names, used);
+                    E::Application(Box::new(function), Box::new(argument))
+                })
+            }
+            normal => normal.into_expression(),
+        }
+    }
+}
+
+impl NormalNeutral {
+    /// `NormalExpression::into_named_expression` of a neutral value.
+    fn named(self, names: &[(String, NormalExpression)], used: &mut BTreeSet<u32>) -> Expression {
+        use crate::ast::Expression as E;
+        use crate::ast::GenericNeutral::*;
+        match self {
+            Generated(index) => {
+                used.insert(index);
+                E::Var(generated_name(index))
+            }
+            Postulate(_, name) => E::Var(name),
+            Application(function, argument) => E::Application(
+                Box::new(function.named(names, used)),
+                Box::new(argument.named(names, used)),
+            ),
+            First(neutral) => E::First(Box::new(neutral.named(names, used))),
+            Second(neutral) => E::Second(Box::new(neutral.named(names, used))),
+            Split(case_tree, body) => E::Application(
+                Box::new(E::Split(named_case_tree(case_tree, names, used))),
+                Box::new(body.named(names, used)),
+            ),
+            Primitive(primitive, arguments) => {
+                NormalExpression::Primitive(primitive, arguments).named(names, used)
+            }
+        }
+    }
+}
+
+/// The cases of `case_tree` for `NormalExpression::into_named_expression`. A case that is a
+/// constant of its context, like the ones bound by `into_expression`, is its value.
+fn named_case_tree(
+    case_tree: NormalCaseTree,
+    names: &[(String, NormalExpression)],
+    used: &mut BTreeSet<u32>,
+) -> Branch {
+    let constant = |context: &NormalTelescope, name: &str| {
+        let mut context = context;
+        loop {
+            context = match &**context {
+                GenericTelescope::UpVar(_, pattern, value) if pattern.contains(name) => {
+                    return match pattern.unlocated() {
+                        Pattern::Var(_) => Some(value.clone()),
+                        _ => None,
+                    };
+                }
+                GenericTelescope::UpDec(_, declaration) if declaration.pattern.contains(name) => {
+                    return None;
+                }
+                GenericTelescope::UpVar(context, _, _) | GenericTelescope::UpDec(context, _) => {
+                    context
+                }
+                GenericTelescope::Nil => return None,
+            }
+        }
+    };
+    (case_tree.into_iter())
+        .map(|(name, case)| {
+            let GenericCase {
+                expression,
+                context,
+            } = *case;
+            let expression = match expression {
+                Either::Left(normal) => normal.named(names, used),
+                Either::Right(expression) => match expression.unlocated() {
+                    Expression::Var(variable) => match constant(&context, variable) {
+                        Some(normal) => normal.named(names, used),
+                        None => expression,
+                    },
+                    _ => expression,
+                },
+            };
+            (name, Box::new(expression))
+        })
+        .collect()
+}
+
+/// A `Sum` or `split` evaluating to `case_tree`.<br/>
+/// The cases sharing a context are put under the same declarations, so the contexts read back
+/// the same way. The evaluated cases are bound as constants, since the values bound to the
+/// generated variables are left out of the contexts read back.
+fn case_tree_into_expression(case_tree: NormalCaseTree, sum: bool) -> Expression {
+    let case_tree_of = |branch| match sum {
+        true => Expression::Sum(branch),
+        false => Expression::Split(branch),
+    };
+    let mut evaluated = Vec::new();
+    let mut groups: Vec<(NormalTelescope, Branch)> = Vec::new();
+    for (name, case) in case_tree.into_iter() {
+        let GenericCase {
+            expression,
+            context,
+        } = *case;
+        let expression = match expression {
+            Either::Left(normal) => {
+                evaluated.push((name, normal.into_expression()));
+                continue;
+            }
+            Either::Right(expression) => Box::new(expression),
+        };
+        match groups.iter_mut().find(|(shared, _)| *shared == context) {
+            Some((_, branch)) => {
+                branch.insert(name, expression);
+            }
+            None => groups.push((context, Some((name, expression)).into_iter().collect())),
+        }
+    }
+    let mut case_trees: Vec<_> = (groups.into_iter())
+        .map(|(context, branch)| with_context(&context, case_tree_of(branch)))
+        .collect();
+    if !evaluated.is_empty() {
+        let constant = |name: &str| format!("<{}>", name);
+        let branch = (evaluated.iter())
+            .map(|(name, _)| (name.clone(), Box::new(Expression::Var(constant(name)))))
+            .collect();
+        let constants =
+            evaluated
+                .into_iter()
+                .rev()
+                .fold(case_tree_of(branch), |rest, (name, expression)| {
+                    let pattern = Pattern::Var(constant(&name));
+                    Expression::Constant(pattern, Box::new(expression), Box::new(rest))
+                });
+        case_trees.push(constants);
+    }
+    // The cases of a split always share their context, only sums have more to merge
+    (case_trees.into_iter())
+        .reduce(|left, right| Expression::Merge(Box::new(left), Box::new(right)))
+        .unwrap_or_else(|| case_tree_of(Default::default()))
+}
+
+/// `expression` under the declarations and constants of `context`.
+fn with_context(context: &NormalTelescope, expression: Expression) -> Expression {
+    use crate::ast::GenericTelescope::*;
+    match &**context {
+        Nil => expression,
+        UpDec(context, declaration) => {
+            let declaration = Box::new(declaration.clone());
+            with_context(
+                context,
+                Expression::Declaration(declaration, Box::new(expression)),
+            )
+        }
+        UpVar(context, pattern, value) => {
+            let value = Box::new(value.clone().into_expression());
+            let constant = Expression::Constant(pattern.clone(), value, Box::new(expression));
+            with_context(context, constant)
         }
     }
 }
@@ -18,7 +18,7 @@ pub fn check_subtype(
             if sub_level <= super_level {
                 Ok(tcs)
             } else {
-                Err(TCE::TypeMismatch(Type(sub_level), Type(super_level)))
+                Err(TCE::mismatch(Type(sub_level), Type(super_level)))
             }
         }
         (Sum(sub_tree), Sum(super_tree)) => check_subtype_sum(index, tcs, sub_tree, super_tree),
@@ -39,7 +39,7 @@ pub fn check_subtype(
             if read_back {
                 compare_normal(index, tcs, subtype, supertype)
             } else {
-                Err(TCE::TypeMismatch(subtype, supertype))
+                Err(TCE::mismatch(subtype, supertype))
             }
         }
     }
@@ -63,7 +63,7 @@ pub fn check_subtype_sum(
     for (constructor, sub_parameter) in sub_tree.into_iter() {
         let super_parameter = super_tree
             .remove(constructor.as_str())
-            .ok_or_else(|| TCE::UnexpectedCases(constructor))?;
+            .ok_or(TCE::UnexpectedCases(constructor))?;
         let sub_parameter = sub_parameter.reduce_to_value();
         let super_parameter = super_parameter.reduce_to_value();
         compare_normal(
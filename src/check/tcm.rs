@@ -1,12 +1,21 @@
 use core::fmt::Write;
 use std::borrow::Cow;
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Display, Error, Formatter};
+use std::rc::Rc;
 
 use either::{Either, Left, Right};
+#[cfg(feature = "serial")]
+use serde::{Deserialize, Serialize};
 
 use super::read_back::NormalExpression;
-use crate::ast::{nil_rc, up_var_rc, Closure, Expression, Level, Pattern, Telescope, Value};
+use crate::ast::{
+    nil_rc, up_var_rc, Closure, Export, Expression, Level, Natural, Open, Pattern, Span, Telescope,
+    Value,
+};
+use crate::case::CasePattern;
+use crate::pretty::Pretty;
 
 /// Since we have no place to document `lookupG` I'll put it here:
 /// $$
@@ -30,18 +39,36 @@ pub enum TCE {
     UpdateGammaFailed(Pattern),
     CannotInfer(Expression),
     UnresolvedName(String),
+    /// An `import` left for the type-checker, modules are loaded before type-checking.
+    UnresolvedImport(String),
+    /// A reference to a name that is private in, or not exported by, the module (the second).
+    HiddenName(String, String),
+    /// `open` of a module that defines nothing in scope.
+    UnresolvedModule(String),
     InvalidConstructor(String),
-    MissingCase(String),
+    MissingCase(CasePattern),
+    /// Missing case of nested `split`s, the missing case of each variable.<br/>
+    /// The case of the value being split by the innermost `split` has an empty name.
+    MissingCaseIn(BTreeMap<String, CasePattern>),
     UnexpectedCases(String),
     /// Reaching somewhere that is not expected to reach.
     Unreachable(&'static str, u32, u32),
     WantSumBut(Either<Value, Expression>),
+    /// An absurd pattern is matching a value of this type, which is not an empty sum type.
+    WantEmptyBut(Value),
     DuplicateBranch(String),
+    /// The clause (first) matches a constructor where the clauses before it match a pair,
+    /// or the other way around, by the pattern (second).
+    MismatchedClause(Box<CasePattern>, Box<CasePattern>),
     WantSigmaBut(Value),
+    /// The projected field, or the field of a record literal, is not found in the record type.
+    NoSuchField(String, Box<Value>),
+    /// The field of the record type is not given by the record literal.
+    MissingField(String, Box<Value>),
     /// We can get the argument of application here, to better report error.
-    WantPiBut(Value, Expression),
+    WantPiBut(Box<Value>, Expression),
     /// Actually first value, expected second value.
-    TypeMismatch(Value, Value),
+    TypeMismatch(Box<Value>, Box<Value>),
     /// Want a type's type, but unfortunately it's not.
     NotTypeType(Value),
     /// Actually first level, expected second level.
@@ -49,23 +76,98 @@ pub enum TCE {
     /// First argument is inferred value, second is expected.
     ReadBackTypeMismatch(NormalExpression, NormalExpression),
     Located(Box<TCE>, Pattern),
+    /// The natural number type of a `BUILTIN NATURAL` pragma doesn't have the constructors.
+    InvalidNatural(Box<TCE>, Box<Natural>),
+    /// The source code location where the error occurs.
+    Spanned(Box<TCE>, Span),
 }
 
+/// Type-Checking Warning.
+#[derive(Clone, Debug)]
+pub enum TCW {
+    /// The clause (whose pattern is stored) of a `split` or a `match` is unreachable,
+    /// since every value it matches is already matched by the clauses before it.
+    UnreachableClause(CasePattern),
+    /// The source code location where the warning occurs.
+    Spanned(Box<TCW>, Span),
+}
+
+/// Warnings reported during type-checking.<br/>
+/// Shared by all the states derived from the same one, so warnings found when checking under a
+/// temporary state are kept.
+pub type Warnings = Rc<RefCell<Vec<TCW>>>;
+
+/// Names of the imported modules that are not exported, with the names of the modules, to
+/// tell the references to them from the unresolved ones.
+pub type Hidden = Rc<BTreeMap<String, String>>;
+
 /// `G` in Mini-TT.<br/>
 /// Type-Checking Monad.
 pub type TCM<T> = Result<T, TCE>;
 
+/// The names a checked module shares with the modules importing it.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serial", derive(Serialize, Deserialize))]
+pub struct Interface {
+    /// The `export` list of the module, every name is exported if absent.
+    pub exports: Option<Export>,
+    /// The names of the `private` declarations of the module.
+    pub private: BTreeSet<String>,
+}
+
+impl Interface {
+    /// The interface of the module parsed as `expression`.
+    pub fn of(expression: &Expression) -> Self {
+        let exports = match expression.unlocated() {
+            Expression::Export(export, _) => Some((**export).clone()),
+            _ => None,
+        };
+        let mut private = BTreeSet::new();
+        for (name, is_private) in expression.definitions() {
+            if is_private {
+                private.insert(name);
+            } else {
+                private.remove(&name);
+            }
+        }
+        Self { exports, private }
+    }
+
+    /// Whether the name is visible to the importing modules.
+    pub fn exports(&self, name: &str) -> bool {
+        !self.private.contains(name) && self.exports.as_ref().is_none_or(|e| e.contains(name))
+    }
+}
+
 /// Type-Checking State <del>, not "Theoretical Computer Science"</del>.<br/>
 /// This is not present in Mini-TT.
 #[derive(Debug)]
 pub struct TCS<'a> {
     pub gamma: Gamma<'a>,
     pub context: Telescope,
+    pub warnings: Warnings,
+    pub hidden: Hidden,
 }
 
 impl<'a> TCS<'a> {
+    /// A state with no warnings reported yet and no hidden names.
     pub fn new(gamma: Gamma<'a>, context: Telescope) -> Self {
-        Self { gamma, context }
+        Self::with_shared(gamma, context, Default::default(), Default::default())
+    }
+
+    /// A state sharing the `warnings` and the `hidden` names with another one.
+    pub fn with_shared(
+        gamma: Gamma<'a>,
+        context: Telescope,
+        warnings: Warnings,
+        hidden: Hidden,
+    ) -> Self {
+        Self {
+            gamma,
+            context,
+            warnings,
+            hidden,
+        }
     }
 
     /// Since `context` is ref-counted, it's gonna be cheap to clone.
@@ -73,12 +175,79 @@ impl<'a> TCS<'a> {
         self.context.clone()
     }
 
+    /// Detach the state from the borrowed Gamma, if any.
+    pub fn into_owned<'b>(self) -> TCS<'b> {
+        let gamma = Cow::Owned(self.gamma.into_owned());
+        TCS::with_shared(gamma, self.context, self.warnings, self.hidden)
+    }
+
     pub fn update(self, pattern: Pattern, type_val: Value, body: Value) -> TCM<TCS<'a>> {
         Ok(TCS {
             gamma: update_gamma_borrow(self.gamma, &pattern, type_val.clone(), &body)?,
             context: up_var_rc(self.context, pattern.clone(), body),
+            warnings: self.warnings,
+            hidden: self.hidden,
         })
     }
+
+    /// Bring the definitions checked in the state of another module into scope, shadowing the
+    /// existing ones of the same names. The values keep the context of the module.<br/>
+    /// The names not in the `interface` of the module are hidden.
+    pub fn import(self, module: &TCS, name: &str, interface: &Interface) -> TCM<TCS<'a>> {
+        let mut hidden = self.hidden.clone();
+        let tcs = module
+            .gamma
+            .iter()
+            .try_fold(self, |tcs, (defined, signature)| {
+                if !interface.exports(defined) {
+                    Rc::make_mut(&mut hidden).insert(defined.clone(), name.to_owned());
+                    return Ok(tcs);
+                }
+                let value = module.context.resolve(defined).map_err(TCE::Textual)?;
+                tcs.update(Pattern::Var(defined.clone()), signature.clone(), value)
+            })?;
+        Ok(TCS { hidden, ..tcs })
+    }
+
+    /// Hide the names from the state, they're defined in the module `name`.
+    pub fn hide(mut self, names: impl IntoIterator<Item = String>, name: &str) -> Self {
+        let hidden = Rc::make_mut(&mut self.hidden);
+        hidden.extend(names.into_iter().map(|hiding| (hiding, name.to_owned())));
+        self
+    }
+
+    /// Bind the names qualified by the module name without the qualifier, `open Nat`.
+    pub fn open(self, open: &Open) -> TCM<TCS<'a>> {
+        let names: Vec<_> = (self.gamma.keys())
+            .filter(|name| open.unqualify(name).is_some())
+            .cloned()
+            .collect();
+        if names.is_empty() {
+            return Err(TCE::UnresolvedModule(open.name.clone()));
+        }
+        let context = self.context();
+        names.into_iter().try_fold(self, |tcs, name| {
+            let signature = tcs.gamma[&name].clone();
+            let value = context.resolve(&name).map_err(TCE::Textual)?;
+            let pattern = Pattern::Var(open.unqualify(&name).unwrap().to_owned());
+            tcs.update(pattern, signature, value)
+        })
+    }
+
+    /// Report a warning.
+    pub fn warn(&self, warning: TCW) {
+        self.warnings.borrow_mut().push(warning)
+    }
+
+    /// Attach a source code location to the warnings reported after the first `since` ones,
+    /// unless they already have a more precise one.
+    pub fn locate_warnings(&self, since: usize, span: Span) {
+        for warning in self.warnings.borrow_mut().iter_mut().skip(since) {
+            if warning.span().is_none() {
+                *warning = TCW::Spanned(Box::new(warning.clone()), span);
+            }
+        }
+    }
 }
 
 impl<'a> Default for TCS<'a> {
@@ -91,8 +260,19 @@ impl<'a> Default for TCS<'a> {
 #[macro_export]
 macro_rules! tcs_borrow {
     ($tcs:expr) => {{
-        let TCS { gamma, context } = &$tcs;
-        TCS::new(std::borrow::Cow::Borrowed(&*gamma), context.clone())
+        let TCS {
+            gamma,
+            context,
+            warnings,
+            hidden,
+        } = &$tcs;
+        let gamma: &$crate::check::tcm::GammaRaw = &*gamma;
+        TCS::with_shared(
+            std::borrow::Cow::Borrowed(gamma),
+            context.clone(),
+            warnings.clone(),
+            hidden.clone(),
+        )
     }};
 }
 
@@ -109,10 +289,90 @@ impl TCE {
     pub fn default_error<T>(str: String) -> TCM<T> {
         Err(TCE::Textual(str))
     }
+
+    /// `TypeMismatch` of the actual type (the first) and the expected one.
+    pub fn mismatch(inferred: Value, expected: Value) -> Self {
+        TCE::TypeMismatch(Box::new(inferred), Box::new(expected))
+    }
+
+    /// Attach a source code location to the error, unless it already has a more precise one.
+    pub fn with_span(self, span: Span) -> Self {
+        match self.span() {
+            Some(_) => self,
+            None => TCE::Spanned(Box::new(self), span),
+        }
+    }
+
+    /// Remove the source code location of a missing case of a nested `split`, so it's located
+    /// to the outermost one after the missing cases are combined.
+    pub fn missing_case(self) -> Self {
+        match self {
+            TCE::Spanned(wrapped, span) => match *wrapped {
+                err @ (TCE::MissingCase(_) | TCE::MissingCaseIn(_)) => err,
+                err => TCE::Spanned(Box::new(err), span),
+            },
+            err => err,
+        }
+    }
+
+    /// The error itself, without its source code location and the declaration where it occurs.
+    pub fn unlocated(&self) -> &Self {
+        match self {
+            TCE::Spanned(wrapped, _)
+            | TCE::Located(wrapped, _)
+            | TCE::InvalidNatural(wrapped, _) => wrapped.unlocated(),
+            err => err,
+        }
+    }
+
+    /// The source code location of the error, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TCE::Spanned(_, span) => Some(*span),
+            TCE::Located(wrapped, _) | TCE::InvalidNatural(wrapped, _) => wrapped.span(),
+            _ => None,
+        }
+    }
+}
+
+impl TCW {
+    /// The source code location of the warning, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            TCW::Spanned(_, span) => Some(*span),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TCW {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for TCW {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            TCW::UnreachableClause(pattern) => {
+                f.write_str("Unreachable clause: `")?;
+                pattern.fmt(f)?;
+                f.write_str("`, the values it matches are matched by the clauses before it.")
+            }
+            // The location is printed by the caller, who has access to the source code.
+            TCW::Spanned(wrapped, _) => wrapped.pretty(naturals, f),
+        }
+    }
 }
 
 impl Display for TCE {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for TCE {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), Error> {
         match self {
             TCE::Textual(s) => f.write_str(s.as_str()),
             TCE::UpdateGammaFailed(pattern) => {
@@ -122,7 +382,7 @@ impl Display for TCE {
             }
             TCE::CannotInfer(expression) => {
                 f.write_str("Cannot infer type of: `")?;
-                expression.fmt(f)?;
+                expression.pretty(naturals, f)?;
                 f.write_str("`.")
             }
             TCE::UnresolvedName(name) => {
@@ -130,6 +390,23 @@ impl Display for TCE {
                 f.write_str(name.as_str())?;
                 f.write_str("`.")
             }
+            TCE::UnresolvedImport(name) => {
+                f.write_str("Unresolved import: `")?;
+                f.write_str(name.as_str())?;
+                f.write_str("`, modules are loaded before type-checking.")
+            }
+            TCE::HiddenName(name, module) => {
+                f.write_str("Hidden reference: `")?;
+                f.write_str(name.as_str())?;
+                f.write_str("` is not visible outside of the module `")?;
+                f.write_str(module.as_str())?;
+                f.write_str("`.")
+            }
+            TCE::UnresolvedModule(name) => {
+                f.write_str("Unresolved module: `")?;
+                f.write_str(name.as_str())?;
+                f.write_str("`.")
+            }
             TCE::InvalidConstructor(name) => {
                 f.write_str("Invalid constructor: `")?;
                 f.write_str(name.as_str())?;
@@ -137,17 +414,48 @@ impl Display for TCE {
             }
             TCE::WantSigmaBut(expression) => {
                 f.write_str("Expected \u{03A3} type, instead got: `")?;
-                expression.fmt(f)?;
+                expression.pretty(naturals, f)?;
+                f.write_str("`.")
+            }
+            TCE::NoSuchField(name, value) => {
+                f.write_str("Expected a record with the field `")?;
+                f.write_str(name)?;
+                f.write_str("`, instead got: `")?;
+                value.pretty(naturals, f)?;
+                f.write_str("`.")
+            }
+            TCE::MissingField(name, value) => {
+                f.write_str("Missing the field `")?;
+                f.write_str(name)?;
+                f.write_str("` of the record type `")?;
+                value.pretty(naturals, f)?;
                 f.write_str("`.")
             }
             TCE::WantSumBut(either) => {
                 f.write_str("Expected Sum type, instead got: `")?;
                 match either {
-                    Left(value) => value.fmt(f)?,
-                    Right(expression) => expression.fmt(f)?,
+                    Left(value) => value.pretty(naturals, f)?,
+                    Right(expression) => expression.pretty(naturals, f)?,
                 };
                 f.write_str("`.")
             }
+            TCE::WantEmptyBut(value) => {
+                f.write_str("Expected an empty Sum type for the absurd pattern, instead got: `")?;
+                value.pretty(naturals, f)?;
+                f.write_str("`.")
+            }
+            TCE::MismatchedClause(clause, pattern) => {
+                f.write_str("Ill-typed clause: `")?;
+                clause.fmt(f)?;
+                f.write_str("`, the pattern `")?;
+                pattern.fmt(f)?;
+                f.write_str(match **pattern {
+                    CasePattern::Pair(..) => {
+                        "` matches a pair where the clauses before it match a constructor."
+                    }
+                    _ => "` matches a constructor where the clauses before it match a pair.",
+                })
+            }
             TCE::DuplicateBranch(branch) => {
                 f.write_str("Found duplicated branch: `")?;
                 f.write_str(branch)?;
@@ -167,14 +475,14 @@ impl Display for TCE {
             }
             TCE::WantPiBut(expression, argument) => {
                 f.write_str("Expected \u{03A0} type, instead got: `")?;
-                expression.fmt(f)?;
+                expression.pretty(naturals, f)?;
                 f.write_str("`\nWhen checking the application whose argument is `")?;
-                argument.fmt(f)?;
+                argument.pretty(naturals, f)?;
                 f.write_str("`.")
             }
             TCE::NotTypeType(value) => {
                 f.write_str("Expected a type expression, instead got: `")?;
-                value.fmt(f)?;
+                value.pretty(naturals, f)?;
                 f.write_str("`.")
             }
             TCE::LevelMismatch(actual, expected) => {
@@ -184,9 +492,25 @@ impl Display for TCE {
                 actual.fmt(f)?;
                 f.write_str("`.")
             }
-            TCE::MissingCase(name) => {
+            TCE::MissingCase(case) => {
                 f.write_str("Missing case-split: `")?;
-                f.write_str(name.as_str())?;
+                case.fmt(f)?;
+                f.write_str("`.")
+            }
+            TCE::MissingCaseIn(cases) => {
+                f.write_str("Missing case-split: `")?;
+                let mut started = false;
+                for (name, case) in cases.iter() {
+                    if started {
+                        f.write_str(", ")?;
+                    } else {
+                        started = true;
+                    }
+                    if !name.is_empty() {
+                        write!(f, "{} = ", name)?;
+                    }
+                    case.fmt(f)?;
+                }
                 f.write_str("`.")
             }
             TCE::UnexpectedCases(joined_name) => {
@@ -194,21 +518,41 @@ impl Display for TCE {
                 f.write_str(joined_name.as_str())?;
                 f.write_str("`.")
             }
-            TCE::ReadBackTypeMismatch(inferred, expected) => mismatch(f, inferred, expected),
-            TCE::TypeMismatch(inferred, expected) => mismatch(f, inferred, expected),
+            TCE::ReadBackTypeMismatch(inferred, expected) => {
+                mismatch(f, naturals, inferred, expected)
+            }
+            TCE::TypeMismatch(inferred, expected) => mismatch(f, naturals, inferred, expected),
             TCE::Located(wrapped, pattern) => {
-                wrapped.fmt(f)?;
+                wrapped.pretty(naturals, f)?;
                 f.write_str("\nWhen checking the declaration of `")?;
                 pattern.fmt(f)?;
                 f.write_str("`.")
             }
+            TCE::InvalidNatural(wrapped, natural) => {
+                wrapped.pretty(naturals, f)?;
+                let Natural {
+                    name, zero, suc, ..
+                } = &**natural;
+                write!(
+                    f,
+                    "\nThe `BUILTIN NATURAL` pragma wants the constructors `{}: {}` and \
+                     `{}: {} -> {}`.",
+                    zero, name, suc, name, name
+                )
+            }
+            // The location is printed by the caller, who has access to the source code.
+            TCE::Spanned(wrapped, _) => wrapped.pretty(naturals, f),
         }
     }
 }
 
+/// `$update` is the function itself, called with `$body` for the located pattern.
 macro_rules! update_gamma {
-    ($gamma:expr, $pattern:expr, $type_val:expr, $clone:expr) => {
+    ($update:ident, $gamma:expr, $pattern:expr, $type_val:expr, $body:expr, $clone:expr) => {
         match $pattern {
+            Pattern::Located(span, pattern) => {
+                $update($gamma, pattern, $type_val, $body).map_err(|err| err.with_span(**span))
+            }
             Pattern::Pair(pattern_first, pattern_second) => match $type_val {
                 Value::Sigma(first, second) => update_gamma_by_pair(
                     $gamma,
@@ -244,7 +588,7 @@ pub fn update_gamma<'a>(
     type_val: Value,
     body: Value,
 ) -> TCM<Gamma<'a>> {
-    update_gamma!(gamma, pattern, type_val, body)
+    update_gamma!(update_gamma, gamma, pattern, type_val, body, body)
 }
 
 /// Borrow version of `upG` in Mini-TT.
@@ -254,7 +598,14 @@ pub fn update_gamma_borrow<'a>(
     type_val: Value,
     body: &Value,
 ) -> TCM<Gamma<'a>> {
-    update_gamma!(gamma, pattern, type_val, body.clone())
+    update_gamma!(
+        update_gamma_borrow,
+        gamma,
+        pattern,
+        type_val,
+        body,
+        body.clone()
+    )
 }
 
 /// Lazy version of `upG` in Mini-TT.
@@ -264,14 +615,14 @@ pub fn update_gamma_lazy<'a>(
     type_val: Value,
     body: impl FnOnce() -> Value,
 ) -> TCM<Gamma<'a>> {
-    update_gamma!(gamma, pattern, type_val, body())
+    update_gamma!(update_gamma_lazy, gamma, pattern, type_val, body, body())
 }
 
 /// Some minor helper specialized from other functions.
 fn update_gamma_by_pair<'a>(
     gamma: Gamma<'a>,
-    pattern_first: &Box<Pattern>,
-    pattern_second: &Box<Pattern>,
+    pattern_first: &Pattern,
+    pattern_second: &Pattern,
     first: Value,
     second: Closure,
     generated_val: Value,
@@ -283,17 +634,22 @@ fn update_gamma_by_pair<'a>(
 }
 
 /// Some minor helper specialized from other functions.
-fn update_gamma_by_var<'a>(gamma: Gamma<'a>, type_val: Value, name: &String) -> TCM<Gamma<'a>> {
+fn update_gamma_by_var<'a>(gamma: Gamma<'a>, type_val: Value, name: &str) -> TCM<Gamma<'a>> {
     let mut gamma = gamma.into_owned();
-    gamma.insert(name.clone(), type_val);
+    gamma.insert(name.to_string(), type_val);
     Ok(Cow::Owned(gamma))
 }
 
 #[inline]
-fn mismatch<E: Display>(f: &mut Formatter, inferred: &E, expected: &E) -> Result<(), Error> {
+fn mismatch<E: Pretty>(
+    f: &mut Formatter,
+    naturals: &[Natural],
+    inferred: &E,
+    expected: &E,
+) -> Result<(), Error> {
     f.write_str("Type mismatch: expected `")?;
-    expected.fmt(f)?;
+    expected.pretty(naturals, f)?;
     f.write_str("`, got (inferred): `")?;
-    inferred.fmt(f)?;
+    inferred.pretty(naturals, f)?;
     f.write_str("`.")
 }
@@ -1,5 +1,5 @@
-use super::{check_declaration_main, check_main};
-use crate::ast::{Declaration, Expression, Pattern};
+use super::{check_declaration_main, check_main, check_main_recovering};
+use crate::ast::{Declaration, Expression, Pattern, Span, Typed, Value};
 
 #[test]
 fn simple_check() {
@@ -36,3 +36,225 @@ fn check_pair() {
     );
     check_main(expr).unwrap();
 }
+
+#[test]
+fn located_error() {
+    let span = Span::new(12, 16, 1, 13);
+    let declaration = Declaration::simple(
+        Pattern::Var("x".to_string()),
+        vec![],
+        Expression::One,
+        Expression::Located(span, Box::new(Expression::Type(0))),
+    );
+    let error = check_declaration_main(declaration).unwrap_err();
+    let error_span = error.span().unwrap();
+    assert_eq!((error_span.start, error_span.end), (12, 16));
+    assert_eq!((error_span.line, error_span.column), (1, 13));
+}
+
+#[test]
+fn recovering_check() {
+    let postulated = "x".to_string();
+    let expr = Expression::Declaration(
+        Box::new(Declaration::simple(
+            Pattern::Var(postulated.clone()),
+            vec![],
+            Expression::One,
+            Expression::Type(0),
+        )),
+        Box::new(Expression::Declaration(
+            Box::new(Declaration::simple(
+                Pattern::Unit,
+                vec![],
+                Expression::One,
+                Expression::Var(postulated.clone()),
+            )),
+            Box::new(Expression::Declaration(
+                Box::new(Declaration::simple(
+                    Pattern::Unit,
+                    vec![],
+                    Expression::One,
+                    Expression::One,
+                )),
+                Box::new(Expression::Void),
+            )),
+        )),
+    );
+    let (tcs, errors) = check_main_recovering(expr);
+    assert_eq!(errors.len(), 2);
+    assert!(tcs.gamma.contains_key(&postulated));
+    let postulate = tcs.context().resolve(&postulated).unwrap();
+    assert_eq!(postulate.to_string(), "[x]");
+}
+
+#[test]
+fn recovering_pi_signature() {
+    // let f: \Pi a: Type. \Pi b: Type. \Pi c: Type. a = Type;
+    let pi = |name: &str, output| {
+        let parameter = Typed::new(Pattern::Var(name.to_string()), Expression::Type(0));
+        Expression::Pi(parameter, Box::new(output))
+    };
+    let signature = pi("a", pi("b", pi("c", Expression::Var("a".to_string()))));
+    let declaration = Declaration::simple(
+        Pattern::Var("f".to_string()),
+        vec![],
+        signature,
+        Expression::Type(0),
+    );
+    let expr = Expression::Declaration(Box::new(declaration), Box::new(Expression::Void));
+    let (tcs, errors) = check_main_recovering(expr);
+    assert_eq!(errors.len(), 1);
+    assert!(tcs.gamma.contains_key("f"));
+}
+
+#[test]
+fn recovering_constant() {
+    let failed = "x".to_string();
+    let expr = Expression::Constant(
+        Pattern::Var(failed.clone()),
+        Box::new(Expression::Var("undefined".to_string())),
+        Box::new(Expression::Declaration(
+            Box::new(Declaration::simple(
+                Pattern::Unit,
+                vec![],
+                Expression::One,
+                Expression::Var(failed),
+            )),
+            Box::new(Expression::Void),
+        )),
+    );
+    let (_, errors) = check_main_recovering(expr);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn infer_annotated_lambda() {
+    let name = "f".to_string();
+    let expr = Expression::Constant(
+        Pattern::Var(name.clone()),
+        Box::new(Expression::Lambda(
+            Pattern::Var("x".to_string()),
+            Some(Box::new(Expression::One)),
+            Box::new(Expression::Var("x".to_string())),
+        )),
+        Box::new(Expression::Void),
+    );
+    let tcs = check_main(expr).unwrap();
+    match &tcs.gamma[&name] {
+        Value::Pi(input, _) => match **input {
+            Value::One => {}
+            _ => panic!("expected the parameter type to be 1"),
+        },
+        _ => panic!("expected a pi type"),
+    }
+}
+
+#[test]
+fn infer_dependent_lambda() {
+    use crate::check::read_back::ReadBack;
+    let var = |name: &str| Expression::Var(name.to_string());
+    let lambda = |name: &str, parameter_type, body| {
+        let parameter_type = Some(Box::new(parameter_type));
+        Expression::Lambda(
+            Pattern::Var(name.to_string()),
+            parameter_type,
+            Box::new(body),
+        )
+    };
+    // `\lambda (a : Type) (x : a). x`
+    let identity = lambda("a", Expression::Type(0), lambda("x", var("a"), var("x")));
+    let expr = Expression::Constant(
+        Pattern::Var("id".to_string()),
+        Box::new(identity),
+        Box::new(Expression::Void),
+    );
+    let tcs = check_main(expr).unwrap();
+    let output = match tcs.gamma["id"].clone() {
+        Value::Pi(_, output) => output.instantiate(Value::One),
+        _ => panic!("expected a pi type"),
+    };
+    // The type of `x` is the argument
+    match output {
+        Value::Pi(input, _) => assert_eq!(input.read_back_please(), Value::One.read_back_please()),
+        _ => panic!("expected a pi type"),
+    }
+}
+
+#[test]
+fn infer_ascription() {
+    let ascribed = |expression, expression_type| {
+        let ascription = Expression::Ascription(Box::new(expression), Box::new(expression_type));
+        Expression::Constant(
+            Pattern::Unit,
+            Box::new(Expression::Application(
+                Box::new(ascription),
+                Box::new(Expression::Unit),
+            )),
+            Box::new(Expression::Void),
+        )
+    };
+    let identity = Expression::Lambda(
+        Pattern::Var("x".to_string()),
+        None,
+        Box::new(Expression::Var("x".to_string())),
+    );
+    let function_type = |output| Expression::Pi(Typed::new(Pattern::Unit, Expression::One), output);
+    check_main(ascribed(
+        identity.clone(),
+        function_type(Box::new(Expression::One)),
+    ))
+    .unwrap();
+    check_main(ascribed(
+        identity,
+        function_type(Box::new(Expression::Type(0))),
+    ))
+    .unwrap_err();
+}
+
+#[test]
+fn unreachable_catch_all() {
+    use crate::ast::Branch;
+    use crate::case::{CasePattern, SourceClauses, DEFAULT_CLAUSE};
+    let wildcard = || CasePattern::Pattern(Pattern::Unit);
+    let clause = || {
+        Box::new(Expression::Lambda(
+            Pattern::Unit,
+            None,
+            Box::new(Expression::Unit),
+        ))
+    };
+    let split_on = |constructors: &[&str]| {
+        let sum: Branch = constructors
+            .iter()
+            .map(|name| (name.to_string(), Box::new(Expression::One)))
+            .collect();
+        let mut clauses: Branch = Default::default();
+        clauses.insert("A".to_string(), clause());
+        clauses.insert(DEFAULT_CLAUSE.to_string(), clause());
+        let signature = Expression::Pi(
+            Typed::new(Pattern::Unit, Expression::Sum(sum)),
+            Box::new(Expression::One),
+        );
+        let body = || Some(Expression::Unit);
+        let patterns = SourceClauses {
+            scrutinee: None,
+            clauses: vec![
+                (
+                    (
+                        CasePattern::constructor("A".to_string(), wildcard()),
+                        body(),
+                    ),
+                    None,
+                ),
+                ((wildcard(), body()), None),
+            ],
+        };
+        let split = Expression::Clauses(Box::new(patterns), Box::new(Expression::Split(clauses)));
+        let declaration = Declaration::simple(Pattern::Unit, vec![], signature, split);
+        let tcs = check_declaration_main(declaration).unwrap();
+        let warnings = tcs.warnings.borrow().len();
+        warnings
+    };
+    assert_eq!(split_on(&["A", "B"]), 0);
+    assert_eq!(split_on(&["A"]), 1);
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::{App, AppSettings};
 use minitt_util::cli::{cli_completion_generation, GenShellSubCommand};
 use structopt::StructOpt;
@@ -25,13 +27,44 @@ pub struct CliOptions {
     #[structopt(alias = "repl-plain", short = "j", long)]
     pub interactive_plain: bool,
     /// Prints errors only
-    #[structopt(short = "q", long)]
+    #[structopt(short = "q", long, global = true)]
     pub quiet: bool,
-    /// the input file to type-check (Notice: file should be UTF-8 encoded)
+    /// Reports all type errors instead of stopping at the first one
+    #[structopt(short = "a", long, global = true)]
+    pub all_errors: bool,
+    /// Checks the modules again instead of loading their interface files, and does not save them
+    #[structopt(long, global = true)]
+    pub no_interfaces: bool,
+    /// Searches the imported modules in this directory as well, after the one of the importing
+    /// file
+    #[structopt(
+        short = "I",
+        long,
+        name = "DIR",
+        number_of_values = 1,
+        global = true,
+        parse(from_os_str)
+    )]
+    pub include: Vec<PathBuf>,
+    /// the input file to type-check (Notice: file should be UTF-8 encoded),
+    /// only the `minitt` code blocks are checked in `.md` or `.lminitt` files
     #[structopt(name = "FILE")]
     pub file: Option<String>,
     #[structopt(subcommand)]
-    completion: Option<GenShellSubCommand>,
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Type-checks all the modules of the project described by a manifest, the imported ones
+    /// first
+    Build {
+        /// The manifest of the project
+        #[structopt(long, default_value = "minitt.toml", parse(from_os_str))]
+        manifest_path: PathBuf,
+    },
+    #[structopt(flatten)]
+    Completion(GenShellSubCommand),
 }
 
 fn app<'a, 'b>() -> App<'a, 'b> {
@@ -43,7 +76,10 @@ fn app<'a, 'b>() -> App<'a, 'b> {
 }
 
 pub fn pre() -> CliOptions {
-    let args: CliOptions = CliOptions::from_clap(&app().get_matches());
-    cli_completion_generation(&args.completion, app);
+    let mut args: CliOptions = CliOptions::from_clap(&app().get_matches());
+    match args.command.take() {
+        Some(Command::Completion(completion)) => cli_completion_generation(&Some(completion), app),
+        command => args.command = command,
+    }
     args
 }
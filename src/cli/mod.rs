@@ -12,15 +12,37 @@ mod util;
 /// REPL
 mod repl;
 
+/// Module loading: resolves `import`s to files.
+mod module;
+
+/// Projects: `minitt.toml` manifests and `minittc build`.
+mod project;
+
 pub fn main() {
-    use minitt::check::check_main;
+    use minitt::check::tcm::TCS;
+    use minitt::check::{check_contextual, check_contextual_recovering};
     let args = args::pre();
+    if let Some(args::Command::Build { manifest_path }) = &args.command {
+        let built = project::build(manifest_path, &args);
+        std::process::exit(if built { 0 } else { 1 });
+    }
+    let loader = module::Loader::new(args.include.clone());
+    let mut loader = loader.with_interfaces(!args.no_interfaces);
 
     // Parse
-    let checked = args
-        .file
-        .clone()
-        .and_then(|s| util::parse_file(s.as_str(), args.lexical_json))
+    let file = args.file.clone();
+    let source = file.as_ref().and_then(|file| util::read_source(file));
+    let imported = match (&file, &source) {
+        (Some(file), Some(source)) => loader.imported_naturals(file, source),
+        _ => Vec::new(),
+    };
+    let (checked, naturals) = source
+        .as_ref()
+        .and_then(|source| {
+            let parsed = util::parse_source(file.as_ref()?, source, &imported, args.lexical_json);
+            // The syntax errors are printed already
+            Some(parsed.unwrap_or_else(|_| std::process::exit(1)))
+        })
         .map(|ast| {
             if !args.quiet {
                 println!("Parse successful.");
@@ -28,27 +50,85 @@ pub fn main() {
                     println!("{}", ast);
                 }
             }
-            if !args.parse_only {
+            let naturals = [imported, ast.naturals()].concat();
+            let print_error = |err| {
+                let file = file.as_ref().unwrap();
+                util::print_error(file, source.as_ref().unwrap(), &naturals, &err)
+            };
+            let tcs = TCS::default();
+            let warnings = tcs.warnings.clone();
+            let (tcs, ast, hash) = if args.parse_only {
+                (tcs, ast, 0)
+            } else {
+                let (file, source) = (file.as_ref().unwrap(), source.as_ref().unwrap());
+                // Load the imported modules
+                let imported = loader.import(tcs, file, source, ast);
+                let (tcs, ast, imported) = imported.unwrap_or_else(|| {
+                    eprintln!("Type-Check failed.");
+                    std::process::exit(1);
+                });
+                (tcs, ast, module::hash(source, imported))
+            };
+            // Modules with warnings are checked again, so the warnings are reported again
+            let save_interface = |checked: &TCS| {
+                if warnings.borrow().is_empty() {
+                    loader.save_interface(file.as_ref().unwrap(), hash, checked)
+                }
+            };
+            let print_warnings = || {
+                let file = file.as_ref().unwrap();
+                let warnings = warnings.borrow();
+                util::print_warnings(file, source.as_ref().unwrap(), &naturals, &warnings, 0)
+            };
+            // Load the interface of the unchanged file instead of checking it again
+            let loaded = (!args.parse_only)
+                .then(|| loader.load_interface(file.as_ref().unwrap(), hash))
+                .flatten();
+            let checked = if let Some(loaded) = loaded {
+                if !args.quiet {
+                    println!("Type-Check successful.");
+                }
+                loaded
+            } else if args.all_errors && !args.parse_only {
+                // Type Check, reporting all the errors
+                let (checked, errors) = check_contextual_recovering(tcs, ast);
+                print_warnings();
+                if !errors.is_empty() {
+                    let count = errors.len();
+                    errors.into_iter().for_each(print_error);
+                    eprintln!("Type-Check failed with {} error(s).", count);
+                    std::process::exit(1);
+                }
+                save_interface(&checked);
+                if !args.quiet {
+                    println!("Type-Check successful.");
+                }
+                checked
+            } else if !args.parse_only {
                 // Type Check
-                let checked = check_main(ast)
-                    .map_err(|err| eprintln!("{}", err))
-                    .unwrap_or_else(|()| {
-                        eprintln!("Type-Check failed.");
-                        std::process::exit(1);
-                    });
+                let checked = check_contextual(tcs, ast);
+                print_warnings();
+                let checked = checked.map_err(print_error).unwrap_or_else(|()| {
+                    eprintln!("Type-Check failed.");
+                    std::process::exit(1);
+                });
+                save_interface(&checked);
                 if !args.quiet {
                     println!("Type-Check successful.");
                 }
                 checked
             } else {
                 Default::default()
-            }
+            };
+            (checked, naturals)
         })
-        .unwrap_or_else(|| Default::default());
+        .unwrap_or_default();
 
     // REPL
     repl::repl(
         checked,
+        loader,
+        naturals,
         if args.interactive_plain {
             Some(ReplEnvType::Plain)
         } else if args.interactive {
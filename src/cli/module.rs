@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use minitt::ast::{Expression, Import, Natural};
+use minitt::check::tcm::{Interface, TCS};
+use minitt::check::{check_contextual, check_contextual_recovering};
+use minitt::parser::parse_str_recovering;
+use minitt::serial::SerialModule;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{parse_source, print_error, print_located, print_warnings, read_source};
+
+/// Extensions of the files of a module, in the order of preference.
+pub const EXTENSIONS: [&str; 3] = ["minitt", "lminitt", "md"];
+
+/// Extension of the interface files, saved next to the checked modules.
+const INTERFACE: &str = "minitti";
+
+/// The saved state of a checked module, loaded instead of checking it again if the hash matches.
+#[derive(Serialize, Deserialize)]
+struct InterfaceFile {
+    /// The `hash` of the module
+    hash: u64,
+    module: SerialModule,
+}
+
+/// The FNV-1a hash of `bytes`, continuing from `hash`. Unlike the hashers of the standard
+/// library, it's the same for every build.
+fn fnv(hash: u64, bytes: &[u8]) -> u64 {
+    (bytes.iter()).fold(hash, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The hash before any byte.
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The hash of a module, from its source and the `hash` of the modules it imports, from
+/// `Loader::import`. The interfaces saved by the other versions are ignored.
+pub fn hash(source: &str, imported: u64) -> u64 {
+    let version = fnv(imported, env!("CARGO_PKG_VERSION").as_bytes());
+    fnv(version, source.as_bytes())
+}
+
+/// Resolves the `import`s to files and checks each module once.
+#[derive(Default)]
+pub struct Loader {
+    /// Directories searched after the one of the importing file.
+    search_path: Vec<PathBuf>,
+    /// Checked modules, their interfaces and their hashes, by their canonical paths.
+    checked: BTreeMap<PathBuf, (TCS<'static>, Interface, u64)>,
+    /// Files whose imports are being loaded, the importing ones first, by their canonical paths
+    /// and the paths to print.
+    loading: Vec<(PathBuf, String)>,
+    /// Whether to report all the type errors of a module instead of stopping at the first one.
+    all_errors: bool,
+    /// Whether to load the interface files of the unchanged modules, and save them for the
+    /// checked ones.
+    interfaces: bool,
+    /// The `BUILTIN NATURAL` pragmas in scope at the end of the modules, by their canonical paths.
+    naturals: BTreeMap<PathBuf, Vec<Natural>>,
+}
+
+impl Loader {
+    pub fn new(search_path: Vec<PathBuf>) -> Self {
+        Self {
+            search_path,
+            interfaces: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_all_errors(self, all_errors: bool) -> Self {
+        Self { all_errors, ..self }
+    }
+
+    pub fn with_interfaces(self, interfaces: bool) -> Self {
+        Self { interfaces, ..self }
+    }
+
+    /// The state of the module in `file` saved in its interface file, if its hash is `hash`.
+    pub fn load_interface(&self, file: &str, hash: u64) -> Option<TCS<'static>> {
+        if !self.interfaces {
+            return None;
+        }
+        let content = fs::read(Path::new(file).with_extension(INTERFACE)).ok()?;
+        // Stale or invalid interfaces are replaced after the module is checked
+        let saved: InterfaceFile = serde_json::from_slice(&content).ok()?;
+        if saved.hash != hash {
+            return None;
+        }
+        saved.module.into_tcs().ok()
+    }
+
+    /// Save the state of the checked module in `file` to its interface file, with its hash.
+    pub fn save_interface(&self, file: &str, hash: u64, tcs: &TCS) {
+        if !self.interfaces {
+            return;
+        }
+        let path = Path::new(file).with_extension(INTERFACE);
+        let saved = SerialModule::new(tcs).map(|module| InterfaceFile { hash, module });
+        // Interfaces only save time, the module is checked again if it cannot be saved
+        if let Some(content) = saved.ok().and_then(|s| serde_json::to_vec(&s).ok()) {
+            let _ = fs::write(path, content);
+        }
+    }
+
+    /// Type-check the module in `file`, parsed already, unless it is checked as an import of
+    /// another one. Returns whether it type-checks, after printing the errors.
+    pub fn check(&mut self, canonical: &Path, file: &str, source: &str, ast: Expression) -> bool {
+        if self.checked.contains_key(canonical) {
+            return true;
+        }
+        match self.check_parsed(file, source, ast) {
+            Some(module) => {
+                self.checked.insert(canonical.to_path_buf(), module);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Load the imports at the beginning of `expression`, parsed from `file`, and bring the
+    /// imported definitions into `tcs`. Returns the state, the rest of the expression and the
+    /// hash of the imported modules, or `None` if an import cannot be loaded, after printing the
+    /// errors.
+    pub fn import<'a>(
+        &mut self,
+        tcs: TCS<'a>,
+        file: &str,
+        source: &str,
+        expression: Expression,
+    ) -> Option<(TCS<'a>, Expression, u64)> {
+        let path = Path::new(file);
+        let directory = match path.canonicalize() {
+            Ok(canonical) => {
+                self.loading.push((canonical, file.to_owned()));
+                path.parent().unwrap_or(path).to_path_buf()
+            }
+            // Not a file, like the code typed into the REPL
+            Err(_) => return self.import_all(tcs, PathBuf::new(), file, source, expression),
+        };
+        let imported = self.import_all(tcs, directory, file, source, expression);
+        self.loading.pop();
+        imported
+    }
+
+    fn import_all<'a>(
+        &mut self,
+        mut tcs: TCS<'a>,
+        directory: PathBuf,
+        file: &str,
+        source: &str,
+        expression: Expression,
+    ) -> Option<(TCS<'a>, Expression, u64)> {
+        let mut imported = FNV_OFFSET;
+        let naturals = expression.naturals();
+        // The export list is checked with the rest
+        let (export, mut expression) = match expression {
+            Expression::Export(export, rest) => (Some(export), *rest),
+            expression => (None, expression),
+        };
+        while let Expression::Import(import, rest) = expression {
+            let located = |message: String| match import.span {
+                Some(span) => print_located(file, source, span, &message),
+                None => eprintln!("{}", message),
+            };
+            let (path, canonical) = match self.resolve(&directory, &import) {
+                Some(found) => found,
+                None => {
+                    located(format!("Cannot find the module `{}`.", import.name()));
+                    return None;
+                }
+            };
+            let path = path.to_string_lossy();
+            let loading = self
+                .loading
+                .iter()
+                .position(|(loading, _)| loading == &canonical);
+            if let Some(position) = loading {
+                let cycle: Vec<_> = (self.loading[position..].iter())
+                    .map(|(_, file)| file.as_ref())
+                    .chain(Some(path.as_ref()))
+                    .map(|file| format!("`{}`", file))
+                    .collect();
+                located(format!("Cyclic import: {}.", cycle.join(" -> ")));
+                return None;
+            }
+            if !self.checked.contains_key(&canonical) {
+                let module = self.check_module(&path)?;
+                self.checked.insert(canonical.clone(), module);
+            }
+            let (module, interface, hash) = &self.checked[&canonical];
+            imported = fnv(imported, &hash.to_le_bytes());
+            tcs = match tcs.import(module, &import.name(), interface) {
+                Ok(tcs) => tcs,
+                Err(err) => {
+                    print_error(file, source, &naturals, &err);
+                    return None;
+                }
+            };
+            expression = *rest;
+        }
+        Some(match export {
+            Some(export) => (
+                tcs,
+                Expression::Export(export, Box::new(expression)),
+                imported,
+            ),
+            None => (tcs, expression, imported),
+        })
+    }
+
+    /// The `BUILTIN NATURAL` pragmas of the modules imported by the code of `file`, and of the
+    /// modules they import, which are in scope in the code. The modules are only parsed for
+    /// them, the imports that cannot be loaded are reported when the code is checked.
+    pub fn imported_naturals(&mut self, file: &str, source: &str) -> Vec<Natural> {
+        let (parsed, _) = parse_str_recovering(source);
+        self.scan_imports(file, &parsed, &mut BTreeSet::new())
+    }
+
+    fn scan_imports(
+        &mut self,
+        file: &str,
+        parsed: &Expression,
+        visiting: &mut BTreeSet<PathBuf>,
+    ) -> Vec<Natural> {
+        let path = Path::new(file);
+        let directory = match path.canonicalize() {
+            Ok(_) => path.parent().unwrap_or(path).to_path_buf(),
+            // Not a file, like the code typed into the REPL
+            Err(_) => PathBuf::new(),
+        };
+        let imports: Vec<_> = (parsed.imports().into_iter())
+            .filter_map(|import| self.resolve(&directory, import))
+            .collect();
+        (imports.into_iter())
+            .flat_map(|(path, canonical)| self.module_naturals(&path, canonical, visiting))
+            .collect()
+    }
+
+    /// The `BUILTIN NATURAL` pragmas in scope at the end of the module in `file`, the ones it
+    /// declares after the ones it imports.
+    fn module_naturals(
+        &mut self,
+        file: &Path,
+        canonical: PathBuf,
+        visiting: &mut BTreeSet<PathBuf>,
+    ) -> Vec<Natural> {
+        if let Some(naturals) = self.naturals.get(&canonical) {
+            return naturals.clone();
+        }
+        // Cyclic imports are reported when the modules are checked
+        if !visiting.insert(canonical.clone()) {
+            return Vec::new();
+        }
+        let file = file.to_string_lossy();
+        let source = read_source(&file).unwrap_or_default();
+        let (parsed, _) = parse_str_recovering(&source);
+        let mut naturals = self.scan_imports(&file, &parsed, visiting);
+        naturals.extend(parsed.naturals());
+        self.naturals.insert(canonical, naturals.clone());
+        naturals
+    }
+
+    /// The file of the module and its canonical path, searched in `directory` first.
+    pub fn resolve(&self, directory: &Path, import: &Import) -> Option<(PathBuf, PathBuf)> {
+        let relative: PathBuf = import.path.iter().collect();
+        (Some(directory).into_iter())
+            .chain(self.search_path.iter().map(PathBuf::as_path))
+            .flat_map(|directory| {
+                let file = directory.join(&relative);
+                EXTENSIONS.iter().map(move |e| file.with_extension(e))
+            })
+            .find(|file| file.is_file())
+            .and_then(|file| Some((file.clone(), file.canonicalize().ok()?)))
+    }
+
+    /// Parse and type-check an imported module, printing the errors if it fails.
+    fn check_module(&mut self, file: &str) -> Option<(TCS<'static>, Interface, u64)> {
+        let source = read_source(file)?;
+        let imported = self.imported_naturals(file, &source);
+        let ast = parse_source(file, &source, &imported, false).ok()?;
+        self.check_parsed(file, &source, ast)
+    }
+
+    fn check_parsed(
+        &mut self,
+        file: &str,
+        source: &str,
+        ast: Expression,
+    ) -> Option<(TCS<'static>, Interface, u64)> {
+        let interface = Interface::of(&ast);
+        let naturals = [self.imported_naturals(file, source), ast.naturals()].concat();
+        let (tcs, ast, imported) = self.import(TCS::default(), file, source, ast)?;
+        let hash = hash(source, imported);
+        if let Some(loaded) = self.load_interface(file, hash) {
+            return Some((loaded, interface, hash));
+        }
+        let warnings = tcs.warnings.clone();
+        let (checked, errors) = if self.all_errors {
+            check_contextual_recovering(tcs, ast)
+        } else {
+            match check_contextual(tcs, ast) {
+                Ok(checked) => (checked, vec![]),
+                Err(err) => (Default::default(), vec![err]),
+            }
+        };
+        print_warnings(file, source, &naturals, &warnings.borrow(), 0);
+        (errors.iter()).for_each(|err| print_error(file, source, &naturals, err));
+        if errors.is_empty() {
+            // Modules with warnings are checked again, so the warnings are reported again
+            if warnings.borrow().is_empty() {
+                self.save_interface(file, hash, &checked);
+            }
+            Some((checked, interface, hash))
+        } else {
+            None
+        }
+    }
+}
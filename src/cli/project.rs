@@ -0,0 +1,298 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use minitt::ast::Expression;
+use serde::Deserialize;
+
+use crate::args::CliOptions;
+use crate::module::{Loader, EXTENSIONS};
+use crate::util::{parse_source, read_source};
+
+/// File name of the manifest of a project.
+pub const MANIFEST: &str = "minitt.toml";
+
+/// The `minitt.toml` manifest of a project.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub project: Project,
+    /// Other projects whose modules can be imported, by name
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+    #[serde(default)]
+    pub check: CheckOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Project {
+    pub name: String,
+    /// Directories of the modules, relative to the manifest
+    #[serde(default = "default_sources")]
+    pub sources: Vec<PathBuf>,
+    /// The main module, checked first along with its imports
+    pub entry: Option<String>,
+}
+
+fn default_sources() -> Vec<PathBuf> {
+    vec![PathBuf::from("src")]
+}
+
+/// A local project, the directory of its manifest. Without a manifest, the directory is the
+/// only source directory.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    pub path: PathBuf,
+}
+
+/// Default options of the checker, the command line flags can only enable them.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CheckOptions {
+    pub all_errors: bool,
+    pub quiet: bool,
+    /// Directories searched for the imported modules after the source directories
+    pub include: Vec<PathBuf>,
+}
+
+impl Manifest {
+    /// Read the manifest in `path`, printing the error if it fails.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| eprintln!("Cannot read `{}`: {}", path.display(), err))
+            .ok()?;
+        toml::from_str(&content)
+            .map_err(|err| eprintln!("Invalid manifest `{}`: {}", path.display(), err))
+            .ok()
+    }
+
+    /// The source directories of this project, in `directory`, and the ones of its
+    /// dependencies, transitively.
+    fn source_roots(
+        &self,
+        directory: &Path,
+        visited: &mut BTreeSet<PathBuf>,
+        roots: &mut Vec<PathBuf>,
+    ) -> Option<()> {
+        roots.extend(self.project.sources.iter().map(|dir| directory.join(dir)));
+        for (name, dependency) in &self.dependencies {
+            let path = directory.join(&dependency.path);
+            let canonical = (path.canonicalize())
+                .map_err(|err| {
+                    let message = format!("Cannot find the dependency `{}`", name);
+                    eprintln!("{} in `{}`: {}", message, path.display(), err)
+                })
+                .ok()?;
+            if !visited.insert(canonical) {
+                continue;
+            }
+            let manifest = path.join(MANIFEST);
+            if manifest.is_file() {
+                Manifest::load(&manifest)?.source_roots(&path, visited, roots)?;
+            } else {
+                roots.push(path);
+            }
+        }
+        Some(())
+    }
+}
+
+/// A module of the project being built.
+struct Unit {
+    /// The qualified name, as imported
+    name: String,
+    file: String,
+    source: String,
+    /// `None` if it failed to parse, or after it's checked
+    ast: Option<Expression>,
+    /// The modules of the project it imports, by their canonical paths
+    imports: Vec<PathBuf>,
+}
+
+enum Outcome {
+    Checked,
+    Failed,
+    /// Not checked because an imported module fails
+    Skipped(String),
+}
+
+/// Type-check all the modules of the project described by the manifest in `manifest_path`,
+/// the imported ones first, and print the outcome for each of them. Returns whether they all
+/// type-check.
+pub fn build(manifest_path: &Path, args: &CliOptions) -> bool {
+    let manifest = match Manifest::load(manifest_path) {
+        Some(manifest) => manifest,
+        None => return false,
+    };
+    let directory = manifest_path.parent().unwrap_or_else(|| Path::new(""));
+    let mut search_path = Vec::new();
+    let mut visited = BTreeSet::new();
+    if manifest
+        .source_roots(directory, &mut visited, &mut search_path)
+        .is_none()
+    {
+        return false;
+    }
+    let check = &manifest.check;
+    search_path.extend(check.include.iter().map(|dir| directory.join(dir)));
+    search_path.extend(args.include.iter().cloned());
+    let quiet = args.quiet || check.quiet;
+    let loader = Loader::new(search_path).with_interfaces(!args.no_interfaces);
+    let mut loader = loader.with_all_errors(args.all_errors || check.all_errors);
+
+    // Find and parse the modules
+    let mut files = Vec::new();
+    for root in &manifest.project.sources {
+        let root = directory.join(root);
+        if let Err(err) = module_files(&root, &root, &mut files) {
+            eprintln!("Cannot read the sources in `{}`: {}", root.display(), err);
+            return false;
+        }
+    }
+    files.sort();
+    let mut units = BTreeMap::new();
+    for (name, path) in files {
+        let file = path.to_string_lossy().into_owned();
+        let source = match read_source(&file) {
+            Some(source) => source,
+            None => return false,
+        };
+        // A Markdown file without `minitt` blocks is a document, like a `README.md`
+        if path.extension().is_some_and(|e| e == "md") && source.trim().is_empty() {
+            continue;
+        }
+        // The imports of a module that fails to parse are still checked before it
+        let imported = loader.imported_naturals(&file, &source);
+        let parsed = parse_source(&file, &source, &imported, false);
+        let imports = match &parsed {
+            Ok(ast) | Err(ast) => ast.imports(),
+        };
+        let imports = (imports.into_iter())
+            .filter_map(|import| loader.resolve(path.parent()?, import))
+            .map(|(_, canonical)| canonical)
+            .collect();
+        let ast = parsed.ok();
+        let canonical = path.canonicalize().unwrap_or(path);
+        let unit = Unit {
+            name,
+            file,
+            source,
+            ast,
+            imports,
+        };
+        units.insert(canonical, unit);
+    }
+
+    let project: BTreeSet<_> = units.keys().cloned().collect();
+    for unit in units.values_mut() {
+        unit.imports.retain(|import| project.contains(import));
+    }
+
+    // The entry module and its imports go first
+    let mut roots: Vec<_> = units.keys().cloned().collect();
+    roots.sort_by(|a, b| units[a].name.cmp(&units[b].name));
+    if let Some(entry) = &manifest.project.entry {
+        match units.iter().find(|(_, unit)| &unit.name == entry) {
+            Some((canonical, _)) => roots.insert(0, canonical.clone()),
+            None => {
+                eprintln!("Cannot find the entry module `{}`.", entry);
+                return false;
+            }
+        }
+    }
+    let mut order = Vec::new();
+    let mut visited = BTreeSet::new();
+    for root in roots {
+        dependency_order(&units, root, &mut visited, &mut order);
+    }
+
+    if !quiet {
+        let project = &manifest.project.name;
+        println!("Building `{}`, {} module(s).", project, order.len());
+    }
+    let mut outcomes = BTreeMap::new();
+    let (mut failed, mut skipped) = (0, 0);
+    for canonical in order {
+        let failed_import = (units[&canonical].imports.iter())
+            .find(|import| {
+                matches!(
+                    outcomes.get(*import),
+                    Some(Outcome::Failed | Outcome::Skipped(_))
+                )
+            })
+            .map(|import| units[import].name.clone());
+        let ast = units.get_mut(&canonical).unwrap().ast.take();
+        let unit = &units[&canonical];
+        let outcome = match (failed_import, ast) {
+            (Some(import), _) => Outcome::Skipped(import),
+            (None, Some(ast)) => match loader.check(&canonical, &unit.file, &unit.source, ast) {
+                true => Outcome::Checked,
+                false => Outcome::Failed,
+            },
+            (None, None) => Outcome::Failed,
+        };
+        match &outcome {
+            Outcome::Checked if !quiet => println!("Checked `{}` ({}).", unit.name, unit.file),
+            Outcome::Checked => {}
+            Outcome::Failed => {
+                failed += 1;
+                eprintln!("Failed `{}` ({}).", unit.name, unit.file);
+            }
+            Outcome::Skipped(import) => {
+                skipped += 1;
+                let (name, file) = (&unit.name, &unit.file);
+                eprintln!("Skipped `{}` ({}), it imports `{}`.", name, file, import);
+            }
+        }
+        outcomes.insert(canonical, outcome);
+    }
+    if failed + skipped > 0 {
+        eprintln!("Build failed: {} failed, {} skipped.", failed, skipped);
+        false
+    } else {
+        if !quiet {
+            println!("Build successful.");
+        }
+        true
+    }
+}
+
+/// Collect the files of the modules in `directory`, recursively, with their names relative to
+/// `root`.
+fn module_files(
+    root: &Path,
+    directory: &Path,
+    files: &mut Vec<(String, PathBuf)>,
+) -> std::io::Result<()> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|e| e.to_str());
+        if path.is_dir() {
+            module_files(root, &path, files)?;
+        } else if extension.is_some_and(|e| EXTENSIONS.contains(&e)) {
+            let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+            let name: Vec<_> = relative.iter().map(|c| c.to_string_lossy()).collect();
+            files.push((name.join("."), path));
+        }
+    }
+    Ok(())
+}
+
+/// Append the module in `canonical` to `order`, after the modules it imports.
+fn dependency_order(
+    units: &BTreeMap<PathBuf, Unit>,
+    canonical: PathBuf,
+    visited: &mut BTreeSet<PathBuf>,
+    order: &mut Vec<PathBuf>,
+) {
+    if !visited.insert(canonical.clone()) {
+        return;
+    }
+    // Cyclic imports are reported by the loader when checking the modules
+    for import in &units[&canonical].imports {
+        dependency_order(units, import.clone(), visited, order);
+    }
+    order.push(canonical);
+}
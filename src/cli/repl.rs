@@ -1,53 +1,68 @@
+use std::cell::RefCell;
+
 use minitt_util::io::history_file;
 use minitt_util::repl::{repl as repl_impl, MiniHelper, ReplEnvType};
 use rustyline::Editor;
 
-use minitt::ast::{Expression, GenericTelescope, Telescope, Value};
+use minitt::ast::{Expression, GenericTelescope, Natural, Telescope, Value};
 use minitt::check::read_back::ReadBack;
 use minitt::check::tcm::{TCE, TCS};
-use minitt::check::{check_contextual, check_infer_contextual};
-use minitt::parser::{parse_str_err_printed, parse_str_to_json};
+use minitt::check::{
+    check_contextual, check_elaborate_contextual, check_infer_contextual,
+    check_infer_named_contextual,
+};
+use minitt::parser::{parse_str_err_printed_in, parse_str_to_json, Scope};
+use minitt::pretty::with_naturals;
 
-use crate::util::parse_file;
+use crate::module::Loader;
+use crate::util::{parse_source, print_error, print_warnings, read_source};
 
-const PROMPT: &'static str = "=> ";
-const QUIT_CMD: &'static str = ":quit";
-const GAMMA_CMD: &'static str = ":gamma";
-const DEBUG_CMD: &'static str = ":debug";
-const CTX_CMD: &'static str = ":context";
-const HELP_CMD: &'static str = ":help";
-const LOAD_CMD: &'static str = ":load";
-const TYPE_CMD: &'static str = ":type";
-const INFER_CMD: &'static str = ":infer";
-const INFER_DBG_CMD: &'static str = ":infer-debug";
-const EVAL_CMD: &'static str = ":eval";
-const EVAL_DBG_CMD: &'static str = ":eval-debug";
-const LEVEL_CMD: &'static str = ":level";
-const LEXICAL_CMD: &'static str = ":lexical";
-const NORMALIZE_CMD: &'static str = ":normalize";
+const PROMPT: &str = "=> ";
+const QUIT_CMD: &str = ":quit";
+const GAMMA_CMD: &str = ":gamma";
+const DEBUG_CMD: &str = ":debug";
+const CTX_CMD: &str = ":context";
+const HELP_CMD: &str = ":help";
+const LOAD_CMD: &str = ":load";
+const TYPE_CMD: &str = ":type";
+const INFER_CMD: &str = ":infer";
+const INFER_DBG_CMD: &str = ":infer-debug";
+const EVAL_CMD: &str = ":eval";
+const EVAL_DBG_CMD: &str = ":eval-debug";
+const LEVEL_CMD: &str = ":level";
+const LEXICAL_CMD: &str = ":lexical";
+const NORMALIZE_CMD: &str = ":normalize";
 
 /// Used for REPL command
-const LOAD_PFX: &'static str = ":load ";
+const LOAD_PFX: &str = ":load ";
 /// Used for REPL command
-const TYPE_PFX: &'static str = ":type ";
-const INFER_PFX: &'static str = ":infer ";
-const INFER_DBG_PFX: &'static str = ":infer-debug ";
-const EVAL_PFX: &'static str = ":eval ";
-const EVAL_DBG_PFX: &'static str = ":eval-debug ";
-const NORMALIZE_PFX: &'static str = ":normalize ";
-const LEVEL_PFX: &'static str = ":level ";
-const LEXICAL_PFX: &'static str = ":lexical ";
+const TYPE_PFX: &str = ":type ";
+const INFER_PFX: &str = ":infer ";
+const INFER_DBG_PFX: &str = ":infer-debug ";
+const EVAL_PFX: &str = ":eval ";
+const EVAL_DBG_PFX: &str = ":eval-debug ";
+const NORMALIZE_PFX: &str = ":normalize ";
+const LEVEL_PFX: &str = ":level ";
+const LEXICAL_PFX: &str = ":lexical ";
 
-fn work<'a>(tcs: TCS<'a>, current_mode: ReplEnvType, line: &str) -> Option<TCS<'a>> {
+/// `naturals` are the natural number types declared by the `BUILTIN NATURAL` pragmas in scope,
+/// whose values are printed as decimals.
+fn work<'a>(
+    loader: &RefCell<Loader>,
+    naturals: &RefCell<Vec<Natural>>,
+    tcs: TCS<'a>,
+    current_mode: ReplEnvType,
+    line: &str,
+) -> Option<TCS<'a>> {
     if line == QUIT_CMD {
         None
     } else if line.is_empty() {
         Some(tcs)
     } else if line == GAMMA_CMD {
-        show_gamma(&tcs);
+        show_gamma(&tcs, &naturals.borrow());
         Some(tcs)
     } else if line == CTX_CMD {
-        show_telescope(&tcs);
+        show_telescope(&tcs, &naturals.borrow());
         Some(tcs)
     } else if line == DEBUG_CMD {
         debug(&tcs.context);
@@ -56,41 +71,46 @@ fn work<'a>(tcs: TCS<'a>, current_mode: ReplEnvType, line: &str) -> Option<TCS<'
         help(current_mode);
         Some(tcs)
     } else if line.starts_with(LOAD_PFX) {
-        Some(
-            match parse_file(line.trim_start_matches(LOAD_CMD).trim_start(), false) {
-                Some(ast) => update_tcs(tcs, ast),
-                None => tcs,
-            },
-        )
+        let file = line.trim_start_matches(LOAD_CMD).trim_start();
+        let source = match read_source(file) {
+            Some(source) => source,
+            None => return Some(tcs),
+        };
+        let imported = loader.borrow_mut().imported_naturals(file, &source);
+        Some(match parse_source(file, &source, &imported, false).ok() {
+            Some(ast) => update_tcs(loader, naturals, imported, tcs, ast, file, &source),
+            None => tcs,
+        })
     } else if line.starts_with(TYPE_PFX) {
         infer_normalize(
             tcs_borrow!(tcs),
+            &naturals.borrow(),
             line.trim_start_matches(TYPE_CMD).trim_start(),
         );
         Some(tcs)
     } else if line.starts_with(INFER_PFX) {
         let line = line.trim_start_matches(INFER_CMD).trim_start();
-        infer(tcs_borrow!(tcs), line);
+        infer(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(INFER_DBG_PFX) {
         let line = line.trim_start_matches(INFER_DBG_CMD).trim_start();
-        debug_infer(tcs_borrow!(tcs), line);
+        debug_infer(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(NORMALIZE_PFX) {
         let line = line.trim_start_matches(NORMALIZE_CMD).trim_start();
-        normalize(tcs.context(), line);
+        normalize(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(EVAL_PFX) {
         let line = line.trim_start_matches(EVAL_CMD).trim_start();
-        eval(tcs.context(), line);
+        eval(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(EVAL_DBG_PFX) {
         let line = line.trim_start_matches(EVAL_DBG_CMD).trim_start();
-        debug_eval(tcs.context(), line);
+        debug_eval(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(LEVEL_PFX) {
         let line = line.trim_start_matches(LEVEL_CMD).trim_start();
-        level(tcs.context(), line);
+        level(tcs_borrow!(tcs), &naturals.borrow(), line);
         Some(tcs)
     } else if line.starts_with(LEXICAL_PFX) {
         let line = line.trim_start_matches(LEXICAL_CMD).trim_start();
@@ -104,8 +124,10 @@ fn work<'a>(tcs: TCS<'a>, current_mode: ReplEnvType, line: &str) -> Option<TCS<'
         println!("Maybe you want to get some `:help`?");
         Some(tcs)
     } else {
-        Some(match parse_str_err_printed(line).ok() {
-            Some(expr) => update_tcs(tcs, expr),
+        let imported = loader.borrow_mut().imported_naturals("<repl>", line);
+        let in_scope = [naturals.borrow().clone(), imported.clone()].concat();
+        Some(match parse_line(&in_scope, line).ok() {
+            Some(expr) => update_tcs(loader, naturals, imported, tcs, expr, "<repl>", line),
             None => tcs,
         })
     }
@@ -130,9 +152,11 @@ fn create_editor() -> Editor<MiniHelper> {
     ])
 }
 
-pub fn repl(tcs: TCS, repl_kind: Option<ReplEnvType>) {
+pub fn repl(tcs: TCS, loader: Loader, naturals: Vec<Natural>, repl_kind: Option<ReplEnvType>) {
     if let Some(kind) = repl_kind {
         let history = || history_file("minitt").ok();
+        let loader = RefCell::new(loader);
+        let naturals = RefCell::new(naturals);
         repl_impl(
             tcs,
             PROMPT,
@@ -140,48 +164,74 @@ pub fn repl(tcs: TCS, repl_kind: Option<ReplEnvType>) {
             create_editor,
             history,
             welcome_message,
-            work,
+            |tcs, mode, line| work(&loader, &naturals, tcs, mode, line),
         );
     }
 }
 
-fn infer_normalize(tcs: TCS, line: &str) {
-    infer_impl(tcs, line, |value| println!("{}", value.read_back_please()));
+fn infer_normalize(tcs: TCS, naturals: &[Natural], line: &str) {
+    infer_impl(tcs, naturals, line, |value| {
+        println!("{}", with_naturals(naturals, &value.read_back_please()))
+    });
+}
+
+/// Parse the line in the scope of the `BUILTIN NATURAL` pragmas `naturals`.
+fn parse_line(naturals: &[Natural], line: &str) -> Result<Expression, ()> {
+    let scope = Scope {
+        naturals: naturals.to_vec(),
+        ..Default::default()
+    };
+    parse_str_err_printed_in(line, &scope)
 }
 
-fn infer(tcs: TCS, line: &str) {
-    infer_impl(tcs, line, |value| println!("{}", value));
+/// The type is printed by the names of the types in scope.
+fn infer(tcs: TCS, naturals: &[Natural], line: &str) {
+    parse_line(naturals, line)
+        .map_err(|()| TCE::Textual("".to_string()))
+        .and_then(|ast| check_infer_named_contextual(tcs, ast))
+        .map(|inferred| println!("{}", with_naturals(naturals, &inferred)))
+        .unwrap_or_else(|err| eprintln!("{}", with_naturals(naturals, &err)))
 }
 
-fn infer_impl(tcs: TCS, line: &str, map: impl FnOnce(Value) -> ()) {
-    parse_str_err_printed(line)
+fn infer_impl(tcs: TCS, naturals: &[Natural], line: &str, map: impl FnOnce(Value)) {
+    parse_line(naturals, line)
         .map_err(|()| TCE::Textual("".to_string()))
         .and_then(|ast| check_infer_contextual(tcs, ast))
         .map(map)
-        .unwrap_or_else(|err| eprintln!("{}", err))
+        .unwrap_or_else(|err| eprintln!("{}", with_naturals(naturals, &err)))
 }
 
-fn eval(ctx: Telescope, line: &str) {
-    eval_impl(ctx, line, |value| println!("{}", value));
+fn eval(tcs: TCS, naturals: &[Natural], line: &str) {
+    eval_impl(tcs, naturals, line, |value| {
+        println!("{}", with_naturals(naturals, &value))
+    });
 }
 
-fn normalize(ctx: Telescope, line: &str) {
-    eval_impl(ctx, line, |value| println!("{}", value.read_back_please()));
+fn normalize(tcs: TCS, naturals: &[Natural], line: &str) {
+    eval_impl(tcs, naturals, line, |value| {
+        println!("{}", with_naturals(naturals, &value.read_back_please()))
+    });
 }
 
-fn level(ctx: Telescope, line: &str) {
-    eval_impl(ctx, line, |value: Value| match value.level_safe() {
-        Some(level) => println!("{}", level),
-        None => println!("The given expression is not a type expression."),
+fn level(tcs: TCS, naturals: &[Natural], line: &str) {
+    eval_impl(tcs, naturals, line, |value: Value| {
+        match value.level_safe() {
+            Some(level) => println!("{}", level),
+            None => println!("The given expression is not a type expression."),
+        }
     });
 }
 
-fn eval_impl(ctx: Telescope, line: &str, map: impl FnOnce(Value) -> ()) {
-    parse_str_err_printed(line)
+/// The expression is type-checked before it's evaluated, the evaluation of field projections and
+/// record literals depends on their types.
+fn eval_impl(tcs: TCS, naturals: &[Natural], line: &str, map: impl FnOnce(Value)) {
+    let ctx = tcs.context();
+    parse_line(naturals, line)
         .map_err(|()| TCE::Textual("".to_string()))
+        .and_then(|ast| check_elaborate_contextual(tcs, ast))
         .map(|ast| ast.eval(ctx))
         .map(map)
-        .unwrap_or_else(|err| eprintln!("{}", err))
+        .unwrap_or_else(|err| eprintln!("{}", with_naturals(naturals, &err)))
 }
 
 fn debug(ctx: &Telescope) {
@@ -198,12 +248,12 @@ fn debug(ctx: &Telescope) {
     }
 }
 
-fn debug_eval(ctx: Telescope, line: &str) {
-    eval_impl(ctx, line, |value| println!("{:?}", value));
+fn debug_eval(tcs: TCS, naturals: &[Natural], line: &str) {
+    eval_impl(tcs, naturals, line, |value| println!("{:?}", value));
 }
 
-fn debug_infer(tcs: TCS, line: &str) {
-    infer_impl(tcs, line, |value| println!("{:?}", value));
+fn debug_infer(tcs: TCS, naturals: &[Natural], line: &str) {
+    infer_impl(tcs, naturals, line, |value| println!("{:?}", value));
 }
 
 fn welcome_message(current_mode: ReplEnvType) {
@@ -211,7 +261,6 @@ fn welcome_message(current_mode: ReplEnvType) {
         "Interactive minittc {}\n\
          Source code: https://github.com/owo-lang/minitt-rs\n\
          Issue tracker: https://github.com/owo-lang/minitt-rs/issues/new\n\n\
-
          The REPL has two modes: the RICH mode and the PLAIN mode.\n\
          Completion, history command, hints and (in the future) colored output are available in the \
          rich mode, but does not work entirely under Windows PowerShell ISE and Mintty \
@@ -228,67 +277,88 @@ fn help(current_mode: ReplEnvType) {
     println!(
         "\
          Commands:\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
-         {:<20} {}\n\
+         {:<20} Quit the REPL.\n\
+         {:<20} Show current typing context.\n\
+         {:<20} Show current value context.\n\
+         {:<20} Show debug-printed value context.\n\
+         {:<20} Show the level of the expression, if it's a type.\n\
+         {:<20} Show the lexical information of the expression.\n\
+         {:<20} Load an external file.\n\
+         {:<20} Try to infer the type of the given expression.\n\
+         {:<20} Try to infer the type of the given expression and debug-print it.\n\
+         {:<20} Try to infer and normalize the type of the given expression.\n\
+         {:<20} Try to evaluate the given expression.\n\
+         {:<20} Try to evaluate the given expression and debug-print it.\n\
+         {:<20} Try to evaluate and normalize the type of the given expression.\n\
          ",
         QUIT_CMD,
-        "Quit the REPL.",
         GAMMA_CMD,
-        "Show current typing context.",
         CTX_CMD,
-        "Show current value context.",
         DEBUG_CMD,
-        "Show debug-printed value context.",
         ":level <EXPR>",
-        "Show the level of the expression, if it's a type.",
         ":lexical <EXPR>",
-        "Show the lexical information of the expression.",
         ":load <FILE>",
-        "Load an external file.",
         ":infer <EXPR>",
-        "Try to infer the type of the given expression.",
         ":infer-debug <EXPR>",
-        "Try to infer the type of the given expression and debug-print it.",
         ":type <EXPR>",
-        "Try to infer and normalize the type of the given expression.",
         ":eval <EXPR>",
-        "Try to evaluate the given expression.",
         ":eval-debug <EXPR>",
-        "Try to evaluate the given expression and debug-print it.",
         ":normalize <EXPR>",
-        "Try to evaluate and normalize the type of the given expression.",
     );
 }
 
-fn update_tcs(tcs: TCS, expr: Expression) -> TCS {
-    check_contextual(tcs, expr).unwrap_or_else(|err| {
-        eprintln!("{}", err);
+/// The natural number types declared by `expr`, and the `imported` ones of the modules it imports,
+/// stay in scope if it type-checks.
+fn update_tcs<'a>(
+    loader: &RefCell<Loader>,
+    naturals_in_scope: &RefCell<Vec<Natural>>,
+    imported: Vec<Natural>,
+    tcs: TCS<'a>,
+    expr: Expression,
+    file: &str,
+    source: &str,
+) -> TCS<'a> {
+    let reset = || {
         eprintln!("Type-Checking State reset due to error (maybe implement recover later).");
+        naturals_in_scope.borrow_mut().clear();
         Default::default()
-    })
+    };
+    let naturals = [
+        naturals_in_scope.borrow().clone(),
+        imported,
+        expr.naturals(),
+    ]
+    .concat();
+    let (tcs, expr) = match loader.borrow_mut().import(tcs, file, source, expr) {
+        Some((tcs, expr, _)) => (tcs, expr),
+        None => return reset(),
+    };
+    let warnings = tcs.warnings.clone();
+    let since = warnings.borrow().len();
+    let checked = check_contextual(tcs, expr);
+    print_warnings(file, source, &naturals, &warnings.borrow(), since);
+    match checked {
+        Ok(tcs) => {
+            *naturals_in_scope.borrow_mut() = naturals;
+            tcs
+        }
+        Err(err) => {
+            print_error(file, source, &naturals, &err);
+            reset()
+        }
+    }
 }
 
-fn show_telescope(tcs: &TCS) {
+fn show_telescope(tcs: &TCS, naturals: &[Natural]) {
     match tcs.context.as_ref() {
         GenericTelescope::Nil => println!("Current Telescope is empty."),
         context => {
-            println!("Current Telescope:\n{}", context);
+            println!("Current Telescope:\n{}", with_naturals(naturals, context));
         }
     }
 }
 
-fn show_gamma(tcs: &TCS) {
+fn show_gamma(tcs: &TCS, naturals: &[Natural]) {
     if tcs.gamma.is_empty() {
         println!("Current Gamma is empty.");
     } else {
@@ -296,5 +366,5 @@ fn show_gamma(tcs: &TCS) {
     }
     tcs.gamma
         .iter()
-        .for_each(|(name, value)| println!("{}: {}", name, value));
+        .for_each(|(name, value)| println!("{}: {}", name, with_naturals(naturals, value)));
 }
@@ -1,20 +1,108 @@
+use std::fmt::Display;
+use std::path::Path;
 use std::str;
 
-use minitt::ast::Expression;
-use minitt::parser::{expression_to_expression, parse_str, Tok};
+use minitt::ast::{Expression, Natural, Span};
+use minitt::check::tcm::{TCE, TCW};
+use minitt::parser::{parse_str_recovering_in, parse_str_to_expression_in, unlit, Scope};
+use minitt::pretty::with_naturals;
 use minitt_util::io::read_file;
 
-pub fn parse_file(file_arg: &str, print_lexical_json: bool) -> Option<Expression> {
+/// Read a file as an UTF-8 string.<br/>
+/// Literate files, `.md` or `.lminitt`, are reduced to the code in their `minitt` blocks.
+pub fn read_source(file_arg: &str) -> Option<String> {
     // If cannot read input, return.
     let file_content = read_file(file_arg)?;
-    // Read file
-    let file_content_utf8 = str::from_utf8(file_content.as_slice()).unwrap();
-    // Parse
-    let tok: Tok = parse_str(file_content_utf8)
-        .map_err(|err| eprintln!("{}", err))
+    let source = String::from_utf8(file_content)
+        .map_err(|err| eprintln!("`{}` is not UTF-8 encoded: {}", file_arg, err))
         .ok()?;
+    let extension = Path::new(file_arg).extension().and_then(|e| e.to_str());
+    Some(match extension {
+        Some("md") | Some("lminitt") => unlit(&source),
+        _ => source,
+    })
+}
+
+/// Parse the code in the scope of the `BUILTIN NATURAL` pragmas `naturals`, like the ones of the
+/// imported modules, printing all the syntax errors if it fails.<br/>
+/// The error is the code parsed with the erroneous parts skipped, which can
+/// still tell the modules it imports.
+pub fn parse_source(
+    file_arg: &str,
+    source: &str,
+    naturals: &[Natural],
+    print_lexical_json: bool,
+) -> Result<Expression, Expression> {
+    let scope = Scope {
+        naturals: naturals.to_vec(),
+        ..Default::default()
+    };
+    let (tok, expression) = parse_str_to_expression_in(source, &scope).map_err(|_| {
+        let (partial, errors) = parse_str_recovering_in(source, &scope);
+        for err in errors.iter() {
+            print_located(file_arg, source, err.span, err);
+        }
+        eprintln!("Parse failed with {} error(s).", errors.len());
+        partial
+    })?;
     if print_lexical_json {
         println!("{}", tok.to_json());
     }
-    Some(expression_to_expression(tok))
+    Ok(expression)
+}
+
+/// Print a type-checking error, along with the location and an excerpt of the
+/// code that failed if the error is located.<br/>
+/// The natural numbers of the `BUILTIN NATURAL` pragmas of the file are printed as decimals.
+pub fn print_error(file_arg: &str, source: &str, naturals: &[Natural], err: &TCE) {
+    let message = with_naturals(naturals, err);
+    match err.span() {
+        Some(span) => print_located(file_arg, source, span, &message),
+        None => eprintln!("{}", message),
+    }
+}
+
+/// Print the type-checking warnings reported after the first `since` ones.
+pub fn print_warnings(
+    file_arg: &str,
+    source: &str,
+    naturals: &[Natural],
+    warnings: &[TCW],
+    since: usize,
+) {
+    for warning in warnings.iter().skip(since) {
+        let message = format!("Warning: {}", with_naturals(naturals, warning));
+        match warning.span() {
+            Some(span) => print_located(file_arg, source, span, &message),
+            None => eprintln!("{}", message),
+        }
+    }
+}
+
+pub fn print_located(file_arg: &str, source: &str, span: Span, err: &impl Display) {
+    eprintln!("{}:{}:{}:", file_arg, span.line, span.column);
+    eprintln!("{}", err);
+    eprint!("{}", excerpt(source, span));
+}
+
+/// The line where `span` starts, with carets under the code in `span`.
+fn excerpt(source: &str, span: Span) -> String {
+    let line = match source.lines().nth(span.line - 1) {
+        Some(line) => line,
+        None => return String::new(),
+    };
+    let line_number = span.line.to_string();
+    let padding = " ".repeat(line_number.len());
+    let code = &source[span.start..span.end.max(span.start)];
+    let code_on_line = code.lines().next().unwrap_or("");
+    let indent: String = line
+        .chars()
+        .take(span.column - 1)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let carets = "^".repeat(code_on_line.chars().count().max(1));
+    format!(
+        "{} |\n{} | {}\n{} | {}{}\n",
+        padding, line_number, line, padding, indent, carets
+    )
 }
@@ -1,6 +1,7 @@
 use std::cmp::max;
 
 use crate::ast::*;
+use crate::case::DEFAULT_CLAUSE;
 
 impl Pattern {
     /// `inPat` in Mini-TT.
@@ -9,6 +10,21 @@ impl Pattern {
             Pattern::Var(pattern_name) => pattern_name == name,
             Pattern::Pair(first, second) => first.contains(name) || second.contains(name),
             Pattern::Unit => false,
+            Pattern::Located(_, pattern) => pattern.contains(name),
+        }
+    }
+
+    /// The names bound by the pattern, from left to right.
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Pattern::Var(name) => vec![name.as_str()],
+            Pattern::Pair(first, second) => {
+                let mut names = first.names();
+                names.extend(second.names());
+                names
+            }
+            Pattern::Unit => Vec::new(),
+            Pattern::Located(_, pattern) => pattern.names(),
         }
     }
 
@@ -44,11 +60,32 @@ impl Pattern {
                 }
             }
             Pattern::Unit => Err("Cannot project unit pattern".to_string()),
+            Pattern::Located(_, pattern) => pattern.project(name, val),
         }
     }
 }
 
 impl GenericTelescope<Value> {
+    /// The names bound in the context, the innermost first, shadowed ones included.
+    pub fn names(&self) -> Vec<&str> {
+        use crate::ast::GenericTelescope::*;
+        let mut names = Vec::new();
+        let mut context = self;
+        loop {
+            context = match context {
+                Nil => return names,
+                UpDec(context, declaration) => {
+                    names.extend(declaration.pattern.names());
+                    context
+                }
+                UpVar(context, pattern, _) => {
+                    names.extend(pattern.names());
+                    context
+                }
+            }
+        }
+    }
+
     /// $$
     /// \textnormal{If} \ x \ \textnormal{is\ in}\ p, \\\\
     /// \begin{alignedat}{2}
@@ -73,7 +110,7 @@ impl GenericTelescope<Value> {
                 if pattern.contains(name) {
                     pattern.project(
                         name,
-                        declaration.body.clone().eval(if declaration.is_recursive {
+                        declaration.lifted_body().eval(if declaration.is_recursive {
                             up_dec_rc(context.clone(), declaration.clone())
                         } else {
                             context.clone()
@@ -122,12 +159,12 @@ impl Value {
     pub fn level_safe(&self) -> Option<Level> {
         use crate::ast::Value::*;
         match self {
-            One => Some(0),
+            One | StringType | CharType => Some(0),
             Type(level) => Some(1 + level),
             Sum(branches) => Some(
                 branches
-                    .iter()
-                    .map(|(_, case)| case.clone().reduce_to_value().level_safe().unwrap_or(0))
+                    .values()
+                    .map(|case| case.clone().reduce_to_value().level_safe().unwrap_or(0))
                     .max()
                     .map(|l| if l > 0 { l - 1 } else { 0 })
                     .unwrap_or(0),
@@ -135,14 +172,14 @@ impl Value {
             Pi(first, second) | Value::Sigma(first, second) => Some(max(
                 first
                     .level_safe()
-                    .map_or(0, |level| if level > 1 { level - 1 } else { 0 }),
+                    .map_or(0, |level| level.saturating_sub(1)),
                 // todo: same problem as line 100, and
                 // will `.instantiate(Value::Unit)` work well when calculate level?
                 second
                     .clone()
                     .instantiate(Value::Unit)
                     .level_safe()
-                    .map_or(0, |level| if level > 1 { level - 1 } else { 0 }),
+                    .map_or(0, |level| level.saturating_sub(1)),
             )),
             _ => None,
         }
@@ -214,7 +251,9 @@ impl Value {
     ///  & \textsf{app} [k] \ v &&= [k \ v]
     /// \end{alignedat}
     /// $$
-    /// `app` in Mini-TT.
+    /// `app` in Mini-TT.<br/>
+    /// Constructors not in the case tree are applied to its `DEFAULT_CLAUSE`, if any.<br/>
+    /// Built-in functions compute once they have all their arguments.
     pub fn apply(self, argument: Self) -> Self {
         use crate::ast::GenericNeutral as Neutral;
         match self {
@@ -222,6 +261,7 @@ impl Value {
             Value::Split(case_tree) => match argument {
                 Value::Constructor(name, body) => case_tree
                     .get(&name)
+                    .or_else(|| case_tree.get(DEFAULT_CLAUSE))
                     .unwrap_or_else(|| panic!("Cannot find constructor `{}`.", name))
                     .clone()
                     .reduce_to_value()
@@ -234,11 +274,46 @@ impl Value {
             Value::Neutral(neutral) => {
                 Value::Neutral(Neutral::Application(Box::new(neutral), Box::new(argument)))
             }
+            Value::Primitive(primitive, mut arguments) => {
+                arguments.push(argument);
+                if arguments.len() < primitive.arity() {
+                    Value::Primitive(primitive, arguments)
+                } else {
+                    primitive.compute(arguments)
+                }
+            }
             e => panic!("Cannot apply on: `{}`.", e),
         }
     }
 }
 
+impl Primitive {
+    /// This is not present in Mini-TT.<br/>
+    /// Run a built-in function on all of its arguments, stuck if any of them is not a literal.
+    pub fn compute(self, arguments: Vec<Value>) -> Value {
+        use crate::ast::Literal::*;
+        let boolean = |b: bool| {
+            let name = if b { "True" } else { "False" };
+            Value::Constructor(name.to_string(), Box::new(Value::Unit))
+        };
+        match (&self, arguments.as_slice()) {
+            (Primitive::StringAppend, [Value::Literal(String(a)), Value::Literal(String(b))]) => {
+                Value::Literal(String(format!("{}{}", a, b)))
+            }
+            (Primitive::StringLength(natural), [Value::Literal(String(s))]) => {
+                natural.literal(s.chars().count()).eval(nil_rc())
+            }
+            (Primitive::StringEquality, [Value::Literal(String(a)), Value::Literal(String(b))]) => {
+                boolean(a == b)
+            }
+            (Primitive::CharEquality, [Value::Literal(Char(a)), Value::Literal(Char(b))]) => {
+                boolean(a == b)
+            }
+            _ => Value::Neutral(GenericNeutral::Primitive(self, arguments)),
+        }
+    }
+}
+
 impl Expression {
     /// This is not present in Mini-TT.<br/>
     /// Return `true` if `self` is a `Sum` or `Merge`.
@@ -273,7 +348,9 @@ impl Expression {
     /// $$
     /// `eval` in Mini-TT.<br/>
     /// Evaluate an `Expression` to a `Value` under a `Telescope`,
-    /// panic if not well-typed.
+    /// panic if not well-typed.<br/>
+    /// Record literals, field projections and constructor calls with several arguments are
+    /// elaborated by the type-checker, they're not evaluated before type-checking.
     pub fn eval(self, context: Telescope) -> Value {
         use crate::ast::Expression as E;
         use crate::ast::Value as V;
@@ -315,7 +392,7 @@ impl Expression {
             }
             E::Lambda(pattern, parameter_type, body) => V::Lambda(Closure::Abstraction(
                 pattern,
-                parameter_type.map(|t| t.internal),
+                parameter_type.map(|t| Box::new(t.eval(context.clone()))),
                 *body,
                 Box::new(context),
             )),
@@ -330,6 +407,19 @@ impl Expression {
             ),
             E::Constructor(name, body) => V::Constructor(name, Box::new(body.eval(context))),
             E::Declaration(declaration, rest) => rest.eval(up_dec_rc(context, *declaration)),
+            E::Fixity(fixity, rest) => fixity.into_constant(*rest).eval(context),
+            E::Natural(_, rest) => rest.eval(context),
+            // The imported definitions are already in the context
+            E::Import(_, rest) | E::Export(_, rest) => rest.eval(context),
+            E::Module(module, rest) => rest.eval(module.bind(context)),
+            E::Open(open, rest) => rest.eval(open.bind(context)),
+            E::StringType => V::StringType,
+            E::CharType => V::CharType,
+            E::Literal(literal) => V::Literal(literal),
+            E::Primitive(primitive) => V::Primitive(primitive, Vec::new()),
+            E::Located(_, expression)
+            | E::Clauses(_, expression)
+            | E::Ascription(expression, _) => expression.eval(context),
             E::Constant(pattern, expression, rest) => rest.eval(up_var_rc(
                 context.clone(),
                 pattern,
@@ -339,3 +429,61 @@ impl Expression {
         }
     }
 }
+
+impl Expression {
+    /// The context after the declarations at the beginning of the expression, like the body of a
+    /// `module` block.
+    pub fn eval_declarations(self, context: Telescope) -> Telescope {
+        use crate::ast::Expression as E;
+        match self {
+            E::Declaration(declaration, rest) => {
+                rest.eval_declarations(up_dec_rc(context, *declaration))
+            }
+            E::Constant(pattern, expression, rest) => {
+                let value = expression.eval(context.clone());
+                rest.eval_declarations(up_var_rc(context, pattern, value))
+            }
+            E::Fixity(fixity, rest) => fixity.into_constant(*rest).eval_declarations(context),
+            E::Natural(_, rest) | E::Import(_, rest) | E::Export(_, rest) => {
+                rest.eval_declarations(context)
+            }
+            E::Module(module, rest) => rest.eval_declarations(module.bind(context)),
+            E::Open(open, rest) => rest.eval_declarations(open.bind(context)),
+            E::Located(_, expression) => expression.eval_declarations(context),
+            _ => context,
+        }
+    }
+}
+
+impl Module {
+    /// Bind the names defined in the block qualified by the module name.<br/>
+    /// The names are defined in the block, so they're always resolved.
+    pub fn bind(&self, context: Telescope) -> Telescope {
+        let inner = self.body.clone().eval_declarations(context.clone());
+        self.names().iter().fold(context, |context, name| {
+            let qualified = self.qualify(name);
+            let value = (inner.resolve(name))
+                .unwrap_or_else(|err| panic!("Cannot bind `{}`: {}", qualified, err));
+            up_var_rc(context, Pattern::Var(qualified), value)
+        })
+    }
+}
+
+impl Open {
+    /// Bind the names in the module without the qualifier.<br/>
+    /// The names are taken from the context, so they're always resolved.
+    pub fn bind(&self, context: Telescope) -> Telescope {
+        let mut names: Vec<_> = (context.names().into_iter())
+            .filter(|name| self.unqualify(name).is_some())
+            .map(str::to_owned)
+            .collect();
+        names.sort();
+        names.dedup();
+        names.iter().fold(context.clone(), |opened, name| {
+            let value = (context.resolve(name))
+                .unwrap_or_else(|err| panic!("Cannot open `{}`: {}", name, err));
+            let name = self.unqualify(name).unwrap().to_owned();
+            up_var_rc(opened, Pattern::Var(name), value)
+        })
+    }
+}
@@ -39,9 +39,10 @@ Mini-TT does not, but minitt does support:
 several extensions are introduced in later versions.
 
 For those who want to have a try on minitt:
-Please do notice that function application in minitt is right-associative, which is very-very
-(very-very-very-very) anti-intuitive. This is because the parser is implemented primarily for
-debugging the type-checker, it's not for general-purpose programming.
+Function application in minitt is left-associative and binds tighter than `++`, `*`, `->` and
+`,` (in this order), while `.1` and `.2` bind tighter than application.
+The parser is implemented primarily for debugging the type-checker, it's not for
+general-purpose programming.
 If you want to write some real code, I recommend [Voile],
 which has nicer syntax, meta variables and implicit parameter syntax,
 and a non-dependent version of row-polymorphism.
@@ -130,7 +131,8 @@ $$
 
 Try to infer the type of a given expression.
 
-Mini-TT/minitt cannot infer types of lambdas due to it's undecidable in general.
+Mini-TT/minitt cannot infer types of lambdas due to it's undecidable in general,
+unless the parameter type is annotated, like `\lambda (x : A). e`.
 
 ### `checkT`
 
@@ -207,8 +209,18 @@ pub mod eval;
 /// Depends on module `syntax`.
 pub mod check;
 
+/// Pattern-matching compiler: from nested constructor patterns to nested case trees.
+///
+/// Depends on module `syntax`.
+pub mod case;
+
 /// Pretty print utilities.
 pub mod pretty;
+/// Serialization of checked modules, saved and loaded instead of checking them again.
+///
+/// Depends on modules `syntax` and `check`.
+#[cfg(feature = "serial")]
+pub mod serial;
 
 /// Parser, from text to AST and a bunch of related tools.
 #[cfg(feature = "parser")]
@@ -1,8 +1,16 @@
+use std::fmt::{Display, Error as FmtError, Formatter};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::CharIndices;
+
+use pest::error::{Error, ErrorVariant, InputLocation};
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use pest_derive::Parser;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::ast::*;
+use crate::case::{compile_match, compile_split, CasePattern, Clause, SourceClauses};
 
 #[derive(Parser)]
 #[grammar = "grammar.pest"]
@@ -13,27 +21,602 @@ struct MiniParser;
 pub type Tok<'a> = Pair<'a, Rule>;
 pub type Tik<'a> = Pairs<'a, Rule>;
 
+/// The declarations in scope when converting the syntax tree into an expression,
+/// they decide how the rest of it is converted. The innermost ones are the last.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    /// Fixity declarations
+    pub fixities: Vec<Fixity>,
+    /// `BUILTIN NATURAL` pragmas
+    pub naturals: Vec<Natural>,
+}
+
 /// Parse a string into an optional expression based on `file` rule:
 /// ```ignore
-/// file = { WHITESPACE* ~ expression }
+/// file = { SOI ~ (export_declaration | import_declaration | expression) ~ EOI }
 /// ```
-pub fn parse_str(input: &str) -> Result<Tok, String> {
-    let the_rule: Tok = MiniParser::parse(Rule::file, input)
-        .map_err(|err| format!("Parse failed at:{}", err))?
-        .next()
-        .unwrap()
-        .into_inner()
-        .next()
-        .unwrap();
-    let end_pos = the_rule.as_span().end_pos().pos();
-    if end_pos < input.len() {
-        let rest = &input[end_pos..];
-        Err(format!("Does not consume the following code:\n{}", rest))
-    } else {
-        Ok(the_rule)
+pub fn parse_str(input: &str) -> Result<Tok<'_>, String> {
+    parse_str_in(input, &Default::default())
+}
+
+/// `parse_str` in `scope`, like the `BUILTIN NATURAL` pragmas of the imported modules.
+pub fn parse_str_in<'a>(input: &'a str, scope: &Scope) -> Result<Tok<'a>, String> {
+    parse_file(input, scope).map_err(|err| format!("Parse failed at:{}", err))
+}
+
+/// `parse_str_in`, with the token converted into an expression in the same scope.<br/>
+/// The conversion relies on the checks of the parser in that scope, like the decimal literals
+/// being in the scope of a `BUILTIN NATURAL` pragma, so it's only done here.
+pub fn parse_str_to_expression_in<'a>(
+    input: &'a str,
+    scope: &Scope,
+) -> Result<(Tok<'a>, Expression), String> {
+    let tok = parse_str_in(input, scope)?;
+    Ok((
+        tok.clone(),
+        expression_to_expression(&mut scope.clone(), tok),
+    ))
+}
+
+/// Reduce a literate source file, like a Markdown document, to the code in its
+/// ```` ```minitt ```` or `~~~minitt` blocks. The other lines are blanked, so the line numbers
+/// of the code are kept.
+pub fn unlit(input: &str) -> String {
+    // The opening fence of the current block, and whether it is a `minitt` block
+    let mut block: Option<(&str, bool)> = None;
+    let lines = input.lines().map(|line| {
+        let line_start = line.trim_start();
+        match block {
+            Some((fence, _)) if is_closing_fence(line_start, fence) => block = None,
+            Some((_, true)) => return line,
+            Some(_) => {}
+            None => block = fence(line_start).map(|(fence, info)| (fence, info.trim() == "minitt")),
+        }
+        ""
+    });
+    let mut code = lines.collect::<Vec<_>>().join("\n");
+    code.push('\n');
+    code
+}
+
+/// The fence of a Markdown code block opened by `line`, and the info string after it.
+fn fence(line: &str) -> Option<(&str, &str)> {
+    let fence_char = line.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let length = line.len() - line.trim_start_matches(fence_char).len();
+    if length < 3 {
+        return None;
+    }
+    Some(line.split_at(length))
+}
+
+/// A block is closed by a fence of the same character, at least as long as the opening one.
+fn is_closing_fence(line: &str, fence: &str) -> bool {
+    let line = line.trim_end();
+    line.starts_with(fence) && line.chars().all(|c| fence.starts_with(c))
+}
+
+fn parse_file<'a>(input: &'a str, scope: &Scope) -> Result<Tok<'a>, Error<Rule>> {
+    let file = MiniParser::parse(Rule::file, input)?.next().unwrap();
+    check_absurd_matches(file.clone())?;
+    check_literals(file.clone(), !scope.naturals.is_empty())?;
+    check_record_fields(file.clone())?;
+    check_fixities(file.clone(), &mut scope.clone())?;
+    Ok(file.into_inner().next().unwrap())
+}
+
+/// A `split` clause without a body must have an absurd pattern, which is not expressible in the
+/// grammar.
+fn check_absurd_matches(file: Tok) -> Result<(), Error<Rule>> {
+    let is_absurd = |clause: &Tok| {
+        (clause.clone().into_inner().flatten()).any(|token| token.as_rule() == Rule::absurd_pattern)
+    };
+    match file.into_inner().flatten().find(|token| {
+        let rule = token.as_rule();
+        (rule == Rule::absurd_match || rule == Rule::absurd_clause) && !is_absurd(token)
+    }) {
+        Some(clause) => Err(Error::new_from_pos(
+            ErrorVariant::CustomError {
+                message: "`=>`".to_string(),
+            },
+            clause.as_span().end_pos(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Decimal literals and `primStringLength` must be in the scope of a `BUILTIN NATURAL` pragma,
+/// literals must be at most `Natural::MAX_LITERAL`, and unicode escapes must be valid characters,
+/// which are not expressible in the grammar.
+fn check_literals(token: Tok, in_scope: bool) -> Result<(), Error<Rule>> {
+    let error = |message: &str, token: &Tok| {
+        Err(Error::new_from_pos(
+            ErrorVariant::CustomError {
+                message: message.to_string(),
+            },
+            token.as_span().start_pos(),
+        ))
+    };
+    match token.as_rule() {
+        Rule::natural if !in_scope => {
+            error("a `BUILTIN NATURAL` pragma before the literal", &token)
+        }
+        Rule::natural
+            if !(token.as_str().parse()).is_ok_and(|n: usize| n <= Natural::MAX_LITERAL) =>
+        {
+            let message = format!("a literal at most {}", Natural::MAX_LITERAL);
+            error(&message, &token)
+        }
+        Rule::primitive if !in_scope && token.as_str() == "primStringLength" => error(
+            "a `BUILTIN NATURAL` pragma before `primStringLength`",
+            &token,
+        ),
+        Rule::string_literal | Rule::char_literal if unescape(token.as_str()).is_none() => {
+            error("a unicode escape of a valid character", &token)
+        }
+        rule => {
+            let in_scope = in_scope || rule == Rule::natural_pragma;
+            (token.into_inner()).try_for_each(|token| check_literals(token, in_scope))
+        }
     }
 }
 
+/// The fields of a record must have distinct names, which is not expressible in the grammar.
+fn check_record_fields(file: Tok) -> Result<(), Error<Rule>> {
+    for record in file.into_inner().flatten() {
+        if !matches!(record.as_rule(), Rule::record_type | Rule::record_literal) {
+            continue;
+        }
+        let mut names = Vec::new();
+        for field in record.into_inner() {
+            let token = field.into_inner().next().unwrap();
+            let position = token.as_span().start_pos();
+            let name = identifier_to_name(token);
+            if names.contains(&name) {
+                return Err(Error::new_from_pos(
+                    ErrorVariant::CustomError {
+                        message: format!("a field name other than `{}`", name),
+                    },
+                    position,
+                ));
+            }
+            names.push(name);
+        }
+    }
+    Ok(())
+}
+
+/// Operators of the same precedence and different associativities can't be applied without
+/// parentheses between them, like `a + b :: c` with `infixl 5 +` and `infixr 5 ::`, which is
+/// not expressible in the grammar. The fixities in scope are the ones of `scope`.
+fn check_fixities(token: Tok, scope: &mut Scope) -> Result<(), Error<Rule>> {
+    match token.as_rule() {
+        Rule::fixity_declaration => {
+            let mut inner: Tik = token.into_inner();
+            let (associativity, precedence, operator) = next_fixity(&mut inner);
+            let fixity = Fixity::new(operator, associativity, precedence, String::new());
+            scope.fixities.push(fixity);
+            let checked = inner.try_for_each(|token| check_fixities(token, scope));
+            scope.fixities.pop();
+            checked
+        }
+        Rule::operator_application => {
+            let operators: Vec<_> = (token.clone().into_inner())
+                .filter(|token| token.as_rule() == Rule::operator)
+                .map(|token| (fixity_of(scope, token.as_str()), token))
+                .collect();
+            for (index, (fixity, token)) in operators.iter().enumerate() {
+                // The operators before it, up to the first one of a lower precedence
+                let mut before = (operators[..index].iter().rev())
+                    .map(|(fixity, _)| fixity)
+                    .take_while(|before| before.precedence >= fixity.precedence);
+                let ambiguous = before.find(|before| {
+                    before.precedence == fixity.precedence
+                        && before.associativity != fixity.associativity
+                });
+                if let Some(before) = ambiguous {
+                    let message = format!(
+                        "parentheses, `{}` is `{} {}` but `{}` is `{} {}`",
+                        before.operator,
+                        before.associativity,
+                        before.precedence,
+                        fixity.operator,
+                        fixity.associativity,
+                        fixity.precedence
+                    );
+                    return Err(Error::new_from_pos(
+                        ErrorVariant::CustomError { message },
+                        token.as_span().start_pos(),
+                    ));
+                }
+            }
+            (token.into_inner()).try_for_each(|token| check_fixities(token, scope))
+        }
+        _ => (token.into_inner()).try_for_each(|token| check_fixities(token, scope)),
+    }
+}
+
+/// Helper, extracted.
+/// The content of a string or character literal with the escapes resolved, `None` if a unicode
+/// escape is not a valid character.
+fn unescape(literal: &str) -> Option<String> {
+    let mut result = String::new();
+    let mut chars = literal[1..literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        result.push(match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'u' => {
+                let hex: String = chars.by_ref().skip(1).take_while(|&c| c != '}').collect();
+                char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            // `\\`, `\'` and `\"`
+            c => c,
+        });
+    }
+    Some(result)
+}
+
+/// A syntax error, found at `span` where one of the `expected` rules should
+/// have been.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub expected: Vec<String>,
+}
+
+impl Display for SyntaxError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self.expected.as_slice() {
+            [] => f.write_str("Syntax error."),
+            [expected] => write!(f, "Syntax error, expected {}.", expected),
+            expected => write!(f, "Syntax error, expected one of: {}.", expected.join(", ")),
+        }
+    }
+}
+
+/// Parse a string, recovering from syntax errors at the `;` after declarations
+/// and at the `|` between the branches of `Sum` and `split`.<br/>
+/// Returns the expression parsed from the code with the erroneous parts
+/// skipped, and all the syntax errors found.
+pub fn parse_str_recovering(input: &str) -> (Expression, Vec<SyntaxError>) {
+    parse_str_recovering_in(input, &Default::default())
+}
+
+/// `parse_str_recovering` in `scope`, see `parse_str_in`.
+pub fn parse_str_recovering_in(input: &str, scope: &Scope) -> (Expression, Vec<SyntaxError>) {
+    // The erroneous parts are replaced by whitespaces, so the code after them
+    // keeps its location
+    let mut code = input.to_string();
+    let mut errors = Vec::new();
+    loop {
+        // Blank input is a syntax error itself, like in `parse_str_in`
+        if !errors.is_empty() && code.trim().is_empty() {
+            return (Expression::Void, errors);
+        }
+        let position = match parse_file(&code, scope) {
+            Ok(tok) => return (expression_to_expression(&mut scope.clone(), tok), errors),
+            Err(err) => {
+                let position = match err.location {
+                    InputLocation::Pos(start) => start,
+                    InputLocation::Span((start, _)) => start,
+                };
+                errors.push(syntax_error(input, position, err.variant));
+                position
+            }
+        };
+        if !skip_erroneous(&mut code, position) {
+            return (Expression::Void, errors);
+        }
+    }
+}
+
+/// The syntax error found by pest at `position`.<br/>
+/// After a complete expression, the error is located right after it, which is
+/// where a missing `;` should have been.
+fn syntax_error(input: &str, position: usize, variant: ErrorVariant<Rule>) -> SyntaxError {
+    let token = &input[position..];
+    let end = position + token.find(char::is_whitespace).unwrap_or(token.len());
+    let positives = match variant {
+        ErrorVariant::ParsingError { positives, .. } => positives,
+        ErrorVariant::CustomError { message } => {
+            return SyntaxError {
+                span: span_at(input, position, end),
+                expected: vec![message],
+            }
+        }
+    };
+    // Only an `operator` can continue a complete expression,
+    // an `operator` alone is expected where it is, like in a fixity declaration
+    if positives.contains(&Rule::operator) && positives != [Rule::operator] {
+        let after = code_end(input, position);
+        let expected = if starts_declaration(token) {
+            vec!["`;`".to_string()]
+        } else {
+            rule_names(positives)
+        };
+        return SyntaxError {
+            span: span_at(input, after, after),
+            expected,
+        };
+    }
+    SyntaxError {
+        span: span_at(input, position, end),
+        expected: rule_names(positives),
+    }
+}
+
+/// The end of the code before `position`, without the whitespaces and comments.
+fn code_end(input: &str, position: usize) -> usize {
+    let mut end = position;
+    loop {
+        let code = input[..end].trim_end();
+        let line_start = code.rfind('\n').map_or(0, |i| i + 1);
+        match comment_start(&code[line_start..]) {
+            Some(comment) => end = line_start + comment,
+            None => return code.len(),
+        }
+    }
+}
+
+/// The offset of the `--` comment in `line`, string literals skipped.
+fn comment_start(line: &str) -> Option<usize> {
+    let mut chars = line.char_indices().peekable();
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            '"' => skip_quoted(&mut chars, c),
+            '-' if line[offset..].starts_with("--") => return Some(offset),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The span from `start` to `end` in `input`, with the line and column of `start`.
+fn span_at(input: &str, start: usize, end: usize) -> Span {
+    let line_start = input[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line = input[..start].matches('\n').count() + 1;
+    let column = input[line_start..start].chars().count() + 1;
+    Span::new(start, end, line, column)
+}
+
+/// The names of the expected `rules` shown to the users, without duplicates.
+fn rule_names(rules: Vec<Rule>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for rule in rules {
+        let name = rule_name(rule);
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// The name of the token or the syntactic category of `rule` in the syntax errors.
+fn rule_name(rule: Rule) -> &'static str {
+    use Rule::*;
+    match rule {
+        identifier | identifier_simple | identifier_raw | variable => "identifier",
+        constructor_name | constructor | data_constructor => "constructor",
+        qualified_name | export_name => "name",
+        natural | precedence => "number",
+        string_literal => "string",
+        char_literal => "character",
+        operator | operator_reference => "operator",
+        first | second | field | projection => "projection",
+        where_clause | local_declaration => "`where`",
+        let_or_rec => "`let` or `rec`",
+        associativity => "`infixl` or `infixr`",
+        private => "`private`",
+        module_name | module_component | qualified_module => "module name",
+        pattern | atom_pattern | pair_pattern | meta_var | typed_binder | telescope => "pattern",
+        case_pattern | atom_case_pattern | constructor_pattern | absurd_pattern => "pattern",
+        field_pattern | field_patterns | prefix_parameter | prefix_parameters => "pattern",
+        pattern_match | literal_match | catch_all | absurd_match => "clause",
+        match_clause | absurd_clause => "clause",
+        record_field | field_assignment => "field",
+        data_signature => "`:`",
+        import_declaration => "`import`",
+        void | EOI => "end of file",
+        _ => "expression",
+    }
+}
+
+/// Tokens that the error recovery synchronizes on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Delimiter {
+    Open(char),
+    Close,
+    Bar,
+    Semicolon,
+    /// One of the `DECLARATION_KEYWORDS`, starting a declaration.
+    Keyword,
+}
+
+/// Keywords starting a declaration, `private` is not one, so it's skipped with the declaration
+/// after it.
+const DECLARATION_KEYWORDS: [&str; 10] = [
+    "let", "rec", "const", "data", "mutual", "infixl", "infixr", "import", "module", "open",
+];
+
+fn is_identifier_character(c: char) -> bool {
+    c.is_alphanumeric() || "_'\\".contains(c)
+}
+
+/// Whether `code` starts with one of the `DECLARATION_KEYWORDS`.
+fn starts_declaration(code: &str) -> bool {
+    DECLARATION_KEYWORDS.iter().any(|keyword| {
+        code.starts_with(keyword) && !code[keyword.len()..].starts_with(is_identifier_character)
+    })
+}
+
+/// Helper for error recovery.
+/// Delimiters in `code` with their byte offsets and bracket nesting depth,
+/// comments and literals skipped. Brackets have the depth of the code outside them.
+fn delimiters(code: &str) -> Vec<(usize, Delimiter, usize)> {
+    let mut delimiters = Vec::new();
+    let mut depth = 0usize;
+    let mut chars = code.char_indices().peekable();
+    let mut previous = ' ';
+    while let Some((offset, c)) = chars.next() {
+        let delimiter = match c {
+            '-' if code[offset..].starts_with("--") => {
+                while chars.peek().is_some_and(|&(_, c)| c != '\n') {
+                    chars.next();
+                }
+                None
+            }
+            '"' => {
+                skip_quoted(&mut chars, c);
+                None
+            }
+            '\'' if !is_identifier_character(previous) => {
+                skip_quoted(&mut chars, c);
+                None
+            }
+            '(' | '{' => {
+                depth += 1;
+                Some((Delimiter::Open(c), depth - 1))
+            }
+            ')' | '}' => {
+                depth = depth.saturating_sub(1);
+                Some((Delimiter::Close, depth))
+            }
+            '|' => Some((Delimiter::Bar, depth)),
+            ';' => Some((Delimiter::Semicolon, depth)),
+            _ if !is_identifier_character(previous) => {
+                if starts_declaration(&code[offset..]) {
+                    Some((Delimiter::Keyword, depth))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let Some((delimiter, depth)) = delimiter {
+            delimiters.push((offset, delimiter, depth));
+        }
+        previous = c;
+    }
+    delimiters
+}
+
+/// Helper for error recovery.
+/// Skip a string or character literal until the closing `quote` or the end of the line.
+fn skip_quoted(chars: &mut Peekable<CharIndices>, quote: char) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\n' => break,
+            c if c == quote => break,
+            _ => {}
+        }
+    }
+}
+
+/// Helper for error recovery.
+/// Replace the branch or the declaration containing the syntax error at
+/// `position` with whitespaces. Returns `false` if there is nothing to skip.
+fn skip_erroneous(code: &mut String, position: usize) -> bool {
+    let delimiters = delimiters(code);
+    let is_blank = |range: &Range<usize>| code[range.clone()].trim().is_empty();
+    let range = match erroneous_branch(&delimiters, position).filter(|r| !is_blank(r)) {
+        Some(range) => range,
+        None => match erroneous_declaration(&delimiters, position, code.len()) {
+            range if !is_blank(&range) => range,
+            _ => return false,
+        },
+    };
+    let blank: String = code[range.clone()]
+        .chars()
+        .map(|c| match c {
+            '\n' | '\r' => c.to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect();
+    code.replace_range(range, &blank);
+    true
+}
+
+/// The branch between `{`, `|` and `}` containing `position`, with one of the
+/// `|`s around it, if `position` is directly inside a pair of braces.
+fn erroneous_branch(
+    delimiters: &[(usize, Delimiter, usize)],
+    position: usize,
+) -> Option<Range<usize>> {
+    let mut opened = Vec::new();
+    for &(offset, delimiter, depth) in delimiters.iter() {
+        match delimiter {
+            _ if offset >= position => break,
+            Delimiter::Open(c) => opened.push((offset, c, depth)),
+            Delimiter::Close => {
+                opened.pop();
+            }
+            _ => {}
+        }
+    }
+    let (brace, depth) = match opened.pop() {
+        Some((offset, '{', depth)) => (offset, depth),
+        _ => return None,
+    };
+    let start = delimiters
+        .iter()
+        .rev()
+        .find(|&&(offset, delimiter, d)| {
+            offset > brace && offset < position && delimiter == Delimiter::Bar && d == depth + 1
+        })
+        .map(|&(offset, _, _)| offset);
+    let (end, delimiter) = delimiters
+        .iter()
+        .filter(|&&(offset, _, _)| offset >= position)
+        .find(|&&(_, delimiter, d)| match delimiter {
+            Delimiter::Bar => d == depth + 1,
+            Delimiter::Close => d == depth,
+            _ => false,
+        })
+        .map(|&(offset, delimiter, _)| (offset, delimiter))?;
+    Some(match (start, delimiter) {
+        (Some(bar), _) => bar..end,
+        (None, Delimiter::Bar) => brace + 1..end + 1,
+        (None, _) => brace + 1..end,
+    })
+}
+
+/// The top-level declaration containing `position`, from the keyword (or the
+/// `;` before it) to the `;` after it (or the keyword of the next one).
+fn erroneous_declaration(
+    delimiters: &[(usize, Delimiter, usize)],
+    position: usize,
+    len: usize,
+) -> Range<usize> {
+    let top_level = || delimiters.iter().filter(|&&(_, _, depth)| depth == 0);
+    let start = top_level()
+        .rev()
+        .filter(|&&(offset, _, _)| offset < position)
+        .find_map(|&(offset, delimiter, _)| match delimiter {
+            Delimiter::Semicolon => Some(offset + 1),
+            Delimiter::Keyword => Some(offset),
+            _ => None,
+        })
+        .unwrap_or(0);
+    let end = top_level()
+        .filter(|&&(offset, _, _)| offset >= position && offset > start)
+        .find_map(|&(offset, delimiter, _)| match delimiter {
+            Delimiter::Semicolon => Some(offset + 1),
+            Delimiter::Keyword => Some(offset),
+            _ => None,
+        })
+        .unwrap_or(len);
+    start..end
+}
+
 /// Parse a string into the json-format lexical information.
 pub fn parse_str_to_json(input: &str) -> Result<String, String> {
     parse_str(input).map(|tok| tok.to_json())
@@ -41,9 +624,16 @@ pub fn parse_str_to_json(input: &str) -> Result<String, String> {
 
 /// Parse a string into an optional expression and print error to stderr.
 #[inline]
+#[allow(clippy::result_unit_err)]
 pub fn parse_str_err_printed(code: &str) -> Result<Expression, ()> {
-    parse_str(code)
-        .map(expression_to_expression)
+    parse_str_err_printed_in(code, &Default::default())
+}
+
+/// `parse_str_err_printed` in `scope`, see `parse_str_in`.
+#[allow(clippy::result_unit_err)]
+pub fn parse_str_err_printed_in(code: &str, scope: &Scope) -> Result<Expression, ()> {
+    parse_str_to_expression_in(code, scope)
+        .map(|(_, expression)| expression)
         .map_err(|err| eprintln!("{}", err))
 }
 
@@ -53,16 +643,21 @@ macro_rules! next_rule {
         debug_assert_eq!(token.as_rule(), Rule::$rule_name);
         $function(token)
     }};
+    ($scope:expr, $inner:expr, $rule_name:ident, $function:ident) => {{
+        let token = $inner.next().unwrap();
+        debug_assert_eq!(token.as_rule(), Rule::$rule_name);
+        $function($scope, token)
+    }};
 }
 
 #[inline]
-fn next_expression(inner: &mut Tik) -> Expression {
-    next_rule!(inner, expression, expression_to_expression)
+fn next_expression(scope: &mut Scope, inner: &mut Tik) -> Expression {
+    next_rule!(scope, inner, expression, expression_to_expression)
 }
 
 #[inline]
-fn next_atom(inner: &mut Tik) -> Expression {
-    next_rule!(inner, atom, atom_to_expression)
+fn next_atom(scope: &mut Scope, inner: &mut Tik) -> Expression {
+    next_rule!(scope, inner, atom, atom_to_expression)
 }
 
 #[inline]
@@ -80,115 +675,263 @@ fn end_of_rule(inner: &mut Tik) {
     debug_assert_eq!(inner.next(), None)
 }
 
+/// Source code location of a token, trailing whitespaces excluded.
+pub fn span_of(the_rule: &Tok) -> Span {
+    let span = the_rule.as_span();
+    let (line, column) = span.start_pos().line_col();
+    let end = span.start() + span.as_str().trim_end().len();
+    Span::new(span.start(), end, line, column)
+}
+
+#[inline]
+fn located(span: Span, expression: Expression) -> Expression {
+    Expression::Located(span, Box::new(expression))
+}
+
 /// ```ignore
 /// expression =
 ///  { declaration
 ///  | const_declaration
-///  | merge_sum
-///  | application
-///  | function_type
-///  | pair_type
-///  | first
-///  | second
+///  | data_declaration
+///  | mutual_declaration
+///  | fixity_declaration
+///  | natural_pragma
+///  | module_declaration
+///  | open_declaration
 ///  | pair
-///  | atom
 ///  }
 /// ```
-pub fn expression_to_expression(rules: Tok) -> Expression {
+/// The export list and the imports at the beginning of a file are accepted as well.<br/>
+/// `scope` has the declarations the expression is converted in, it's restored afterwards.
+fn expression_to_expression(scope: &mut Scope, rules: Tok) -> Expression {
+    match rules.as_rule() {
+        Rule::export_declaration => return export_declaration_to_expression(scope, rules),
+        Rule::import_declaration => return import_declaration_to_expression(scope, rules),
+        _ => {}
+    }
     let the_rule: Tok = rules.into_inner().next().unwrap();
     match the_rule.as_rule() {
-        Rule::declaration => declaration_to_expression(the_rule),
-        Rule::const_declaration => const_declaration_to_expression(the_rule),
-        Rule::merge_sum => merge_sum_to_expression(the_rule),
-        Rule::application => application_to_expression(the_rule),
-        Rule::function_type => function_type_to_expression(the_rule),
-        Rule::pair_type => pair_type_to_expression(the_rule),
-        Rule::first => first_to_expression(the_rule),
-        Rule::second => second_to_expression(the_rule),
-        Rule::pair => pair_to_expression(the_rule),
-        Rule::atom => atom_to_expression(the_rule),
+        Rule::declaration => declaration_to_expression(scope, the_rule),
+        Rule::const_declaration => const_declaration_to_expression(scope, the_rule),
+        Rule::data_declaration => data_declaration_to_expression(scope, the_rule),
+        Rule::mutual_declaration => mutual_declaration_to_expression(scope, the_rule),
+        Rule::fixity_declaration => fixity_declaration_to_expression(scope, the_rule),
+        Rule::natural_pragma => natural_pragma_to_expression(scope, the_rule),
+        Rule::module_declaration => module_declaration_to_expression(scope, the_rule),
+        Rule::open_declaration => open_declaration_to_expression(scope, the_rule),
+        Rule::pair => pair_to_expression(scope, the_rule),
         _ => unreachable!(),
     }
 }
 
+/// Helper, extracted.
+/// Fold operands separated by a right-associative binary operator.
+fn right_associative(
+    scope: &mut Scope,
+    the_rule: Tok,
+    operand: impl Fn(&mut Scope, Tok) -> Expression,
+    operator: impl Fn(Expression, Expression) -> Expression,
+) -> Expression {
+    let mut operands: Vec<(Span, Expression)> = the_rule
+        .into_inner()
+        .map(|token| (span_of(&token), operand(scope, token)))
+        .collect();
+    let (mut span, mut result) = operands.pop().unwrap();
+    while let Some((lhs_span, lhs)) = operands.pop() {
+        span = lhs_span.merge(span);
+        result = located(span, operator(lhs, result));
+    }
+    result
+}
+
 /// ```ignore
-/// first = { atom ~ ".1" }
+/// pair = { function_type ~ ("," ~ function_type)* }
 /// ```
-fn first_to_expression(the_rule: Tok) -> Expression {
-    let mut inner: Tik = the_rule.into_inner();
-    let pair = next_atom(&mut inner);
-    end_of_rule(&mut inner);
-    Expression::First(Box::new(pair))
+fn pair_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    right_associative(
+        scope,
+        the_rule,
+        function_type_to_expression,
+        |first, second| Expression::Pair(Box::new(first), Box::new(second)),
+    )
 }
 
 /// ```ignore
-/// function_type = { atom ~ "->" ~ expression }
+/// function_type = { pair_type ~ (single_arrow ~ pair_type)* }
 /// ```
-fn function_type_to_expression(the_rule: Tok) -> Expression {
-    let (input, output) = atom_and_expression_to_tuple(the_rule);
-    Expression::Pi(Typed::new(Pattern::Unit, input), Box::new(output))
+fn function_type_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    right_associative(scope, the_rule, pair_type_to_expression, |input, output| {
+        Expression::Pi(Typed::new(Pattern::Unit, input), Box::new(output))
+    })
 }
 
 /// ```ignore
 /// multiplication = _{ "*" | "\\times" | "×" }
-/// pair_type = { atom ~ multiplication ~ expression }
+/// pair_type = { merge_sum ~ (multiplication ~ merge_sum)* }
 /// ```
-fn pair_type_to_expression(the_rule: Tok) -> Expression {
-    let (first, second) = atom_and_expression_to_tuple(the_rule);
-    Expression::Sigma(Typed::new(Pattern::Unit, first), Box::new(second))
+fn pair_type_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    right_associative(scope, the_rule, merge_sum_to_expression, |first, second| {
+        Expression::Sigma(Typed::new(Pattern::Unit, first), Box::new(second))
+    })
 }
 
-/// Helper, extracted.
-fn atom_and_expression_to_tuple(the_rule: Tok) -> (Expression, Expression) {
-    let mut inner: Tik = the_rule.into_inner();
-    let input = next_atom(&mut inner);
-    let output = next_expression(&mut inner);
-    end_of_rule(&mut inner);
-    (input, output)
+/// ```ignore
+/// merge_sum = { operator_application ~ ("++" ~ operator_application)* }
+/// ```
+fn merge_sum_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    right_associative(
+        scope,
+        the_rule,
+        operator_application_to_expression,
+        |lhs, rhs| Expression::Merge(Box::new(lhs), Box::new(rhs)),
+    )
 }
 
 /// ```ignore
-/// second = { atom ~ ".2" }
+/// operator_application = { application ~ (operator ~ application)* }
 /// ```
-fn second_to_expression(the_rule: Tok) -> Expression {
+/// Operators are grouped by precedence climbing, `a + b` is `(+) a b`.
+/// Operators without fixity declarations in scope are `infixl 9`.
+fn operator_application_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
     let mut inner: Tik = the_rule.into_inner();
-    let pair = next_atom(&mut inner);
-    end_of_rule(&mut inner);
-    Expression::Second(Box::new(pair))
+    let head = inner.next().unwrap();
+    let lhs = (span_of(&head), application_to_expression(scope, head));
+    let mut operations = Vec::new();
+    while let Some(operator) = inner.next() {
+        let fixity = fixity_of(scope, operator.as_str());
+        let operand = inner.next().unwrap();
+        let rhs = (span_of(&operand), application_to_expression(scope, operand));
+        operations.push((fixity, rhs));
+    }
+    climb_operators(lhs, &mut operations.into_iter().peekable(), 0).1
+}
+
+/// Helper, extracted.
+/// The innermost fixity declaration of `operator` in scope.
+fn fixity_of(scope: &Scope, operator: &str) -> Fixity {
+    let declared = scope.fixities.iter().rev().find(|f| f.operator == operator);
+    declared.cloned().unwrap_or_else(|| {
+        Fixity::new(
+            operator.to_owned(),
+            Associativity::Left,
+            9,
+            operator.to_owned(),
+        )
+    })
+}
+
+/// Helper, extracted.
+/// Apply the operators of precedence at least `min` in `operations` to `lhs`.
+fn climb_operators(
+    mut lhs: (Span, Expression),
+    operations: &mut Peekable<impl Iterator<Item = (Fixity, (Span, Expression))>>,
+    min: u8,
+) -> (Span, Expression) {
+    while operations.peek().is_some_and(|(f, _)| f.precedence >= min) {
+        let (fixity, mut rhs) = operations.next().unwrap();
+        while let Some((next, _)) = operations.peek() {
+            let tighter = next.precedence > fixity.precedence
+                || next.precedence == fixity.precedence
+                    && next.associativity == Associativity::Right;
+            if !tighter {
+                break;
+            }
+            let precedence = next.precedence;
+            rhs = climb_operators(rhs, operations, precedence);
+        }
+        let span = lhs.0.merge(rhs.0);
+        let operator = Expression::Var(fixity.operator);
+        let partial = Expression::Application(Box::new(operator), Box::new(lhs.1));
+        let application = Expression::Application(Box::new(partial), Box::new(rhs.1));
+        lhs = (span, located(span, application));
+    }
+    lhs
 }
 
 /// ```ignore
-/// pair = { atom ~ "," ~ expression }
+/// application = { projection+ }
 /// ```
-fn pair_to_expression(the_rule: Tok) -> Expression {
+/// Application is left-associative, `f a b` is `(f a) b`.<br/>
+/// When the function is a constructor name, the first argument becomes the
+/// constructor's argument, so `Suc n` is a constructor call and `Cons x xs` is
+/// `(Cons x) xs`. The rest arguments are the other fields of the constructor,
+/// they're collected by the type-checker, where the constructor is known.
+fn application_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
     let mut inner: Tik = the_rule.into_inner();
-    let first = next_atom(&mut inner);
-    let second = next_expression(&mut inner);
-    end_of_rule(&mut inner);
-    Expression::Pair(Box::new(first), Box::new(second))
+    let head = inner.next().unwrap();
+    let mut span = span_of(&head);
+    let mut function = match constructor_head(&head) {
+        Some(constructor) if inner.peek().is_some() => {
+            let argument = inner.next().unwrap();
+            span = span.merge(span_of(&argument));
+            let argument = projection_to_expression(scope, argument);
+            located(
+                span,
+                Expression::Constructor(constructor, Box::new(argument)),
+            )
+        }
+        _ => projection_to_expression(scope, head),
+    };
+    for argument in inner {
+        span = span.merge(span_of(&argument));
+        let argument = projection_to_expression(scope, argument);
+        let application = Expression::Application(Box::new(function), Box::new(argument));
+        function = located(span, application);
+    }
+    function
+}
+
+/// Helper, extracted.
+/// Returns the constructor name if `projection` is a bare constructor name.
+fn constructor_head(projection: &Tok) -> Option<String> {
+    let mut inner: Tik = projection.clone().into_inner();
+    let atom = inner.next().unwrap();
+    if inner.next().is_some() {
+        return None;
+    }
+    let the_rule = atom.into_inner().next().unwrap();
+    match the_rule.as_rule() {
+        Rule::constructor_name => Some(identifier_to_name(the_rule)),
+        _ => None,
+    }
 }
 
 /// ```ignore
-/// application = { atom ~ expression }
+/// first = { ".1" }
+/// second = { ".2" }
+/// field = @{ "." ~ identifier }
+/// projection = { atom ~ (first | second | field)* }
 /// ```
-fn application_to_expression(the_rule: Tok) -> Expression {
+fn projection_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
     let mut inner: Tik = the_rule.into_inner();
-    let function = next_atom(&mut inner);
-    let argument = next_expression(&mut inner);
-    end_of_rule(&mut inner);
-    Expression::Application(Box::new(function), Box::new(argument))
+    let mut span = span_of(inner.peek().as_ref().unwrap());
+    let mut pair = next_atom(scope, &mut inner);
+    for projection in inner {
+        span = span.merge(span_of(&projection));
+        pair = match projection.as_rule() {
+            Rule::first => located(span, Expression::First(Box::new(pair))),
+            Rule::second => located(span, Expression::Second(Box::new(pair))),
+            Rule::field => {
+                let name = projection.as_str()[1..].nfc().collect();
+                let field = Expression::Field(Box::new(pair), name);
+                located(span, field)
+            }
+            _ => unreachable!(),
+        };
+    }
+    pair
 }
 
 /// ```ignore
 /// prefix_parameter = { "(" ~ typed_pattern ~ ")" }
 /// prefix_parameters = { prefix_parameter* }
 /// ```
-fn prefix_parameters_to_vec(the_rule: Tok) -> Vec<Typed> {
+fn prefix_parameters_to_vec(scope: &mut Scope, the_rule: Tok) -> Vec<Typed> {
     let mut map: Vec<Typed> = Default::default();
     for prefix_parameter in the_rule.into_inner() {
         let mut inner: Tik = prefix_parameter.into_inner();
         let pattern = next_pattern(&mut inner);
-        let parameter_type = next_expression(&mut inner);
+        let parameter_type = next_expression(scope, &mut inner);
         map.push(Typed::new(pattern, parameter_type));
     }
     map
@@ -196,111 +939,528 @@ fn prefix_parameters_to_vec(the_rule: Tok) -> Vec<Typed> {
 
 /// ```ignore
 /// declaration =
-///  { let_or_rec?
+///  { let_or_rec
 ///  ~ pattern
 ///  ~ prefix_parameters
 ///  ~ ":" ~ expression
 ///  ~ "=" ~ expression
+///  ~ where_clause?
 ///  ~ ";" ~ expression?
 ///  }
 /// ```
-fn declaration_to_expression(the_rule: Tok) -> Expression {
+fn declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let span = span_of(&the_rule);
     let mut inner: Tik = the_rule.into_inner();
+    let declaration = next_declaration(scope, span, &mut inner);
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    end_of_rule(&mut inner);
+    Expression::Declaration(Box::new(declaration), Box::new(rest))
+}
+
+/// Helper, extracted.
+/// The declaration from `let_or_rec` to the `where` clause, whose helpers are put before the
+/// body, so they're only visible to the body and the prefix parameters.
+fn next_declaration(scope: &mut Scope, span: Span, inner: &mut Tik) -> Declaration {
+    let is_private = inner
+        .peek()
+        .filter(|t| t.as_rule() == Rule::private)
+        .is_some();
+    if is_private {
+        inner.next();
+    }
     let let_or_rec_rule = inner.next().unwrap();
     let rec = match let_or_rec_rule.as_str() {
         "let" => false,
         "rec" => true,
         _ => unreachable!(),
     };
+    let name = next_pattern(inner);
+    let prefix_parameters = next_rule!(scope, inner, prefix_parameters, prefix_parameters_to_vec);
+    let signature = next_expression(scope, inner);
+    let span = span.merge(span_of(inner.peek().as_ref().unwrap()));
+    let mut body = next_expression(scope, inner);
+    if let Some(where_clause) = inner.peek().filter(|t| t.as_rule() == Rule::where_clause) {
+        inner.next();
+        body = where_clause_to_expression(scope, where_clause, body);
+    }
+    let mut declaration = Declaration::new(name, prefix_parameters, signature, body, rec);
+    declaration.is_private = is_private;
+    declaration.span = Some(span);
+    declaration
+}
+
+/// ```ignore
+/// local_declaration =
+///  { let_or_rec
+///  ~ pattern
+///  ~ prefix_parameters
+///  ~ ":" ~ expression
+///  ~ "=" ~ expression
+///  ~ where_clause?
+///  ~ ";"
+///  }
+/// where_clause = { "where" ~ "{" ~ local_declaration* ~ "}" }
+/// ```
+/// `body where { let a : A = x; }` is `let a : A = x; body`.
+fn where_clause_to_expression(scope: &mut Scope, the_rule: Tok, body: Expression) -> Expression {
+    let helpers: Vec<Declaration> = (the_rule.into_inner())
+        .map(|helper| {
+            let span = span_of(&helper);
+            let mut inner: Tik = helper.into_inner();
+            let declaration = next_declaration(scope, span, &mut inner);
+            end_of_rule(&mut inner);
+            declaration
+        })
+        .collect();
+    (helpers.into_iter().rev()).fold(body, |body, helper| {
+        Expression::Declaration(Box::new(helper), Box::new(body))
+    })
+}
+
+/// ```ignore
+/// const_declaration =
+///  { "const"
+///  ~ pattern
+///  ~ "=" ~ expression
+///  ~ ";" ~ expression?
+///  }
+/// ```
+fn const_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let mut inner: Tik = the_rule.into_inner();
     let name = next_pattern(&mut inner);
-    let prefix_parameters = next_rule!(inner, prefix_parameters, prefix_parameters_to_vec);
-    let signature = next_expression(&mut inner);
-    let body = next_expression(&mut inner);
+    let body = next_expression(scope, &mut inner);
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    end_of_rule(&mut inner);
+    Expression::Constant(name, Box::new(body), Box::new(rest))
+}
+
+/// ```ignore
+/// data_constructor = { constructor_name ~ projection* }
+/// data_signature = { ":" ~ expression }
+/// data_declaration =
+///  { "data"
+///  ~ identifier
+///  ~ prefix_parameters
+///  ~ data_signature?
+///  ~ "=" ~ (data_constructor ~ ("|" ~ data_constructor)*)?
+///  ~ ";" ~ expression?
+///  }
+/// ```
+/// `data list (a : Type) = Nil | Cons a (list a);` is desugared as
+/// `rec list : \Pi a : Type. Type = \lambda a. Sum { Nil | Cons a * list a };`,
+/// the signature is `Type` if not specified.
+fn data_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let mut inner: Tik = the_rule.into_inner();
+    let mut span = start.merge(span_of(inner.peek().as_ref().unwrap()));
+    let name = next_rule!(inner, identifier, identifier_to_name);
+    let parameters = next_rule!(scope, inner, prefix_parameters, prefix_parameters_to_vec);
+    let mut signature = Expression::Type(0);
+    if inner.peek().map(|token| token.as_rule()) == Some(Rule::data_signature) {
+        let mut signature_inner: Tik = inner.next().unwrap().into_inner();
+        signature = next_expression(scope, &mut signature_inner);
+    }
+    let mut constructors: Branch = Default::default();
+    while inner.peek().map(|token| token.as_rule()) == Some(Rule::data_constructor) {
+        let data_constructor = inner.next().unwrap();
+        span = start.merge(span_of(&data_constructor));
+        let mut constructor_inner: Tik = data_constructor.into_inner();
+        let constructor_name = next_constructor_name(&mut constructor_inner);
+        let fields = constructor_inner
+            .map(|rule| projection_to_expression(scope, rule))
+            .collect();
+        constructors.insert(constructor_name, Box::new(fields_to_sigma(fields)));
+    }
     let rest = inner
         .next()
-        .map(expression_to_expression)
+        .map(|rule| expression_to_expression(scope, rule))
         .unwrap_or(Expression::Void);
     end_of_rule(&mut inner);
-    let declaration = Declaration::new(name, prefix_parameters, signature, body, rec);
+    let signature = parameters
+        .iter()
+        .rev()
+        .fold(signature, |signature, parameter| {
+            Expression::Pi(parameter.clone(), Box::new(signature))
+        });
+    let body = parameters
+        .into_iter()
+        .rev()
+        .fold(Expression::Sum(constructors), |body, parameter| {
+            Expression::Lambda(parameter.pattern, None, Box::new(body))
+        });
+    let mut declaration = Declaration::new(Pattern::Var(name), vec![], signature, body, true);
+    declaration.span = Some(span);
     Expression::Declaration(Box::new(declaration), Box::new(rest))
 }
 
 /// ```ignore
-/// const_declaration =
-///  { "const"
-///  ~ pattern
+/// mutual_definition =
+///  { "rec"
+///  ~ identifier
+///  ~ prefix_parameters
+///  ~ ":" ~ expression
 ///  ~ "=" ~ expression
+///  ~ ";"
+///  }
+/// mutual_declaration =
+///  { "mutual"
+///  ~ "{" ~ mutual_definition+ ~ "}"
+///  ~ expression?
+///  }
+/// ```
+/// `mutual { rec a : A = M; rec b : B = N; }` is desugared as
+/// `rec (a, b) : A * B = (M, N);`, prefix parameters of each definition are lifted
+/// into its signature and body.
+fn mutual_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let inner: Tik = the_rule.into_inner();
+    let span = start.merge(span_of(inner.peek().as_ref().unwrap()));
+    let mut patterns = Vec::new();
+    let mut signatures = Vec::new();
+    let mut bodies = Vec::new();
+    let mut rest = Expression::Void;
+    for the_rule in inner {
+        if the_rule.as_rule() == Rule::expression {
+            rest = expression_to_expression(scope, the_rule);
+            break;
+        }
+        let span = span_of(&the_rule);
+        let mut inner: Tik = the_rule.into_inner();
+        let name = next_rule!(inner, identifier, identifier_to_name);
+        let parameters = next_rule!(scope, inner, prefix_parameters, prefix_parameters_to_vec);
+        let signature = next_expression(scope, &mut inner);
+        let span = span.merge(span_of(inner.peek().as_ref().unwrap()));
+        let body = next_expression(scope, &mut inner);
+        end_of_rule(&mut inner);
+        signatures.push(
+            parameters
+                .iter()
+                .rev()
+                .fold(signature, |signature, parameter| {
+                    Expression::Pi(parameter.clone(), Box::new(signature))
+                }),
+        );
+        let body = parameters.into_iter().rev().fold(body, |body, parameter| {
+            Expression::Lambda(parameter.pattern, None, Box::new(body))
+        });
+        bodies.push(located(span, body));
+        patterns.push(Pattern::Var(name));
+    }
+    let pattern = patterns.pop().unwrap();
+    let pattern = patterns.into_iter().rev().fold(pattern, |second, first| {
+        Pattern::Pair(Box::new(first), Box::new(second))
+    });
+    let signature = fields_to_sigma(signatures);
+    let body = fields_to_pair(bodies);
+    let mut declaration = Declaration::new(pattern, vec![], signature, body, true);
+    declaration.span = Some(span);
+    Expression::Declaration(Box::new(declaration), Box::new(rest))
+}
+
+/// ```ignore
+/// associativity = { "infixl" | "infixr" }
+/// precedence = @{ ASCII_DIGIT }
+/// fixity_declaration =
+///  { associativity
+///  ~ precedence
+///  ~ operator
+///  ~ "=" ~ identifier
 ///  ~ ";" ~ expression?
 ///  }
 /// ```
-fn const_declaration_to_expression(the_rule: Tok) -> Expression {
+/// The fixity is in scope in the rest of the expression.
+fn fixity_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
     let mut inner: Tik = the_rule.into_inner();
-    let name = next_pattern(&mut inner);
-    let body = next_expression(&mut inner);
+    let (associativity, precedence, operator) = next_fixity(&mut inner);
+    let span = start.merge(span_of(inner.peek().as_ref().unwrap()));
+    let name = next_rule!(inner, identifier, identifier_to_name);
+    let mut fixity = Fixity::new(operator, associativity, precedence, name);
+    fixity.span = Some(span);
+    scope.fixities.push(fixity.clone());
     let rest = inner
         .next()
-        .map(expression_to_expression)
+        .map(|rule| expression_to_expression(scope, rule))
         .unwrap_or(Expression::Void);
+    scope.fixities.pop();
     end_of_rule(&mut inner);
-    Expression::Constant(name, Box::new(body), Box::new(rest))
+    Expression::Fixity(Box::new(fixity), Box::new(rest))
+}
+
+/// Helper, extracted.
+/// The associativity, the precedence and the operator of a fixity declaration.
+fn next_fixity(inner: &mut Tik) -> (Associativity, u8, String) {
+    let associativity = match inner.next().unwrap().as_str() {
+        "infixl" => Associativity::Left,
+        "infixr" => Associativity::Right,
+        _ => unreachable!(),
+    };
+    let precedence = inner.next().unwrap().as_str().parse().unwrap();
+    let operator = inner.next().unwrap().as_str().to_owned();
+    (associativity, precedence, operator)
+}
+
+/// ```ignore
+/// natural_pragma =
+///  { "{-#" ~ "BUILTIN" ~ "NATURAL"
+///  ~ identifier
+///  ~ constructor_name
+///  ~ constructor_name
+///  ~ "#-}" ~ expression?
+///  }
+/// ```
+/// Decimal literals in the rest of the expression are constructor chains of the natural number
+/// type.
+fn natural_pragma_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let mut inner: Tik = the_rule.into_inner();
+    let name = next_rule!(inner, identifier, identifier_to_name);
+    let zero = next_constructor_name(&mut inner);
+    let span = start.merge(span_of(inner.peek().as_ref().unwrap()));
+    let suc = next_constructor_name(&mut inner);
+    let mut natural = Natural::new(name, zero, suc);
+    natural.span = Some(span);
+    scope.naturals.push(natural.clone());
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    scope.naturals.pop();
+    end_of_rule(&mut inner);
+    Expression::Natural(Box::new(natural), Box::new(rest))
+}
+
+/// ```ignore
+/// export_name = ${ (constructor_name ~ ".")* ~ (identifier | constructor_name) }
+/// export_declaration =
+///  { "export" ~ "(" ~ (export_name ~ ("," ~ export_name)*)? ~ ")"
+///  ~ (import_declaration | expression)?
+///  }
+/// ```
+fn export_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let mut names = Vec::new();
+    let mut span = start;
+    let mut rest = Expression::Void;
+    for token in the_rule.into_inner() {
+        match token.as_rule() {
+            Rule::export_name => {
+                span = start.merge(span_of(&token));
+                names.push(qualified_to_name(token));
+            }
+            _ => rest = expression_to_expression(scope, token),
+        }
+    }
+    let mut export = Export::new(names);
+    export.span = Some(span);
+    Expression::Export(Box::new(export), Box::new(rest))
+}
+
+/// ```ignore
+/// module_body = { "{" ~ expression? ~ "}" }
+/// module_declaration = { "module" ~ constructor_name ~ module_body ~ expression? }
+/// ```
+fn module_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let mut inner: Tik = the_rule.into_inner();
+    let span = start.merge(span_of(inner.peek().as_ref().unwrap()));
+    let name = next_constructor_name(&mut inner);
+    let body = inner.next().unwrap().into_inner().next();
+    let body = body
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    let mut module = Module::new(name, body);
+    module.span = Some(span);
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    end_of_rule(&mut inner);
+    Expression::Module(Box::new(module), Box::new(rest))
 }
 
 /// ```ignore
-/// merge_sum = { atom ~ "++" ~ expression }
+/// qualified_module = ${ constructor_name ~ ("." ~ constructor_name)* }
+/// open_declaration = { "open" ~ qualified_module ~ ";" ~ expression? }
 /// ```
-fn merge_sum_to_expression(the_rule: Tok) -> Expression {
+fn open_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
     let mut inner: Tik = the_rule.into_inner();
-    let lhs = next_atom(&mut inner);
-    let rhs = next_expression(&mut inner);
+    let qualified_module = inner.next().unwrap();
+    let span = start.merge(span_of(&qualified_module));
+    let mut open = Open::new(qualified_to_name(qualified_module));
+    open.span = Some(span);
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
     end_of_rule(&mut inner);
-    Expression::Merge(Box::new(lhs), Box::new(rhs))
+    Expression::Open(Box::new(open), Box::new(rest))
+}
+
+/// ```ignore
+/// module_name = ${ module_component ~ ("." ~ module_component)* }
+/// import_declaration = { "import" ~ module_name ~ ";" ~ (import_declaration | expression)? }
+/// ```
+fn import_declaration_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let start = span_of(&the_rule);
+    let mut inner: Tik = the_rule.into_inner();
+    let module_name = inner.next().unwrap();
+    let span = start.merge(span_of(&module_name));
+    let path = (module_name.into_inner()).map(identifier_to_name).collect();
+    let mut import = Import::new(path);
+    import.span = Some(span);
+    let rest = inner
+        .next()
+        .map(|rule| expression_to_expression(scope, rule))
+        .unwrap_or(Expression::Void);
+    end_of_rule(&mut inner);
+    Expression::Import(Box::new(import), Box::new(rest))
+}
+
+/// Helper, extracted.
+/// The number of a decimal literal and the innermost `BUILTIN NATURAL` pragma,
+/// which is checked to be present after parsing.
+fn natural_literal(scope: &mut Scope, the_rule: Tok) -> (usize, Natural) {
+    let number = the_rule.as_str().parse().unwrap();
+    let natural = scope.naturals.last().cloned();
+    (number, natural.unwrap())
+}
+
+/// Helper, extracted.
+/// The pattern of a decimal literal, `Suc (Suc Zero)` for `2`.
+fn natural_to_case_pattern(scope: &mut Scope, the_rule: Tok) -> CasePattern {
+    let (number, natural) = natural_literal(scope, the_rule);
+    let zero = CasePattern::constructor(natural.zero.clone(), CasePattern::Pattern(Pattern::Unit));
+    (0..number).fold(zero, |pattern, _| {
+        CasePattern::constructor(natural.suc.clone(), pattern)
+    })
+}
+
+/// Helper, extracted.
+/// The payload of a constructor call with the given fields, `(a, (b, c))` for `C a b c`.
+fn fields_to_pair(mut fields: Vec<Expression>) -> Expression {
+    let last = fields.pop().unwrap();
+    fields.into_iter().rev().fold(last, |second, first| {
+        Expression::Pair(Box::new(first), Box::new(second))
+    })
+}
+
+/// Helper, extracted.
+/// The payload type of a constructor with the given fields, `A * B * C` for `C A B C`.
+fn fields_to_sigma(mut fields: Vec<Expression>) -> Expression {
+    match fields.pop() {
+        Some(last) => fields.into_iter().rev().fold(last, |second, first| {
+            Expression::Sigma(Typed::new(Pattern::Unit, first), Box::new(second))
+        }),
+        None => Expression::One,
+    }
+}
+
+/// Helper, extracted.
+/// The built-in function, `primStringLength` is checked to be in the scope of a
+/// `BUILTIN NATURAL` pragma after parsing.
+fn primitive_to_primitive(scope: &mut Scope, the_rule: Tok) -> Primitive {
+    match the_rule.as_str() {
+        "primStringAppend" => Primitive::StringAppend,
+        "primStringLength" => {
+            let natural = scope.naturals.last().cloned();
+            Primitive::StringLength(Box::new(natural.unwrap()))
+        }
+        "primStringEquality" => Primitive::StringEquality,
+        "primCharEquality" => Primitive::CharEquality,
+        _ => unreachable!(),
+    }
 }
 
 /// ```ignore
 /// atom =
 ///   { universe
-///   | constructor
+///   | sum
+///   | string_type
+///   | char_type
+///   | constructor_name
+///   | string_literal
+///   | char_literal
+///   | primitive
 ///   | variable
+///   | natural
 ///   | split
-///   | sum
 ///   | one
 ///   | unit
 ///   | pi_type
 ///   | sigma_type
+///   | record_type
+///   | record_literal
 ///   | lambda_expression
+///   | operator_reference
+///   | ascription
 ///   | "(" ~ expression ~ ")"
 ///   }
 /// ```
-fn atom_to_expression(rules: Tok) -> Expression {
+fn atom_to_expression(scope: &mut Scope, rules: Tok) -> Expression {
+    let span = span_of(&rules);
     let the_rule: Tok = rules.into_inner().next().unwrap();
-    match the_rule.as_rule() {
+    let expression = match the_rule.as_rule() {
         Rule::universe => universe_to_expression(the_rule),
-        Rule::constructor => constructor_to_expression(the_rule),
+        Rule::string_type => Expression::StringType,
+        Rule::char_type => Expression::CharType,
+        Rule::string_literal => {
+            Expression::Literal(Literal::String(unescape(the_rule.as_str()).unwrap()))
+        }
+        Rule::char_literal => {
+            let content = unescape(the_rule.as_str()).unwrap();
+            Expression::Literal(Literal::Char(content.chars().next().unwrap()))
+        }
+        Rule::primitive => Expression::Primitive(primitive_to_primitive(scope, the_rule)),
+        Rule::constructor_name => {
+            Expression::Constructor(identifier_to_name(the_rule), Box::new(Expression::Unit))
+        }
         Rule::variable => variable_to_expression(the_rule),
-        Rule::split => Expression::Split(choices_to_tree_map(the_rule)),
-        Rule::sum => Expression::Sum(branches_to_tree_map(the_rule)),
+        Rule::qualified_name => Expression::Var(qualified_to_name(the_rule)),
+        Rule::natural => {
+            let (number, natural) = natural_literal(scope, the_rule);
+            natural.literal(number)
+        }
+        Rule::split => choices_to_split(scope, the_rule),
+        Rule::match_expression => match_expression_to_expression(scope, the_rule),
+        Rule::sum => Expression::Sum(branches_to_tree_map(scope, the_rule)),
         Rule::one => Expression::One,
         Rule::unit => Expression::Unit,
-        Rule::pi_type => pi_type_to_expression(the_rule),
-        Rule::sigma_type => sigma_type_to_expression(the_rule),
-        Rule::lambda_expression => lambda_expression_to_expression(the_rule),
-        Rule::expression => expression_to_expression(the_rule),
+        Rule::pi_type => pi_type_to_expression(scope, the_rule),
+        Rule::sigma_type => sigma_type_to_expression(scope, the_rule),
+        Rule::record_type => record_type_to_expression(scope, the_rule),
+        Rule::record_literal => record_literal_to_expression(scope, the_rule),
+        Rule::lambda_expression => lambda_expression_to_expression(scope, the_rule),
+        Rule::operator_reference => {
+            Expression::Var(the_rule.into_inner().next().unwrap().as_str().to_owned())
+        }
+        Rule::ascription => ascription_to_expression(scope, the_rule),
+        // Already located
+        Rule::expression => return expression_to_expression(scope, the_rule),
         _ => unreachable!(),
-    }
+    };
+    located(span, expression)
 }
 
 /// ```ignore
 /// branches = _{ "{" ~ (constructor ~ ("|" ~ constructor)*)? ~ "}" }
 /// constructor = { constructor_name ~ expression }
 /// ```
-fn branches_to_tree_map(the_rule: Tok) -> Branch {
+fn branches_to_tree_map(scope: &mut Scope, the_rule: Tok) -> Branch {
     let mut map: Branch = Default::default();
     for constructor in the_rule.into_inner() {
         let mut inner: Tik = constructor.into_inner();
         let constructor_name = next_constructor_name(&mut inner);
         let expression = inner
             .next()
-            .map(expression_to_expression)
+            .map(|rule| expression_to_expression(scope, rule))
             .unwrap_or(Expression::One);
         map.insert(constructor_name, Box::new(expression));
         end_of_rule(&mut inner);
@@ -309,52 +1469,187 @@ fn branches_to_tree_map(the_rule: Tok) -> Branch {
 }
 
 /// ```ignore
-/// choices = _{ "{" ~ (pattern_match ~ ("|" ~ pattern_match)*)? ~ "}" }
-/// pattern_match = { constructor_name ~ maybe_pattern ~ "=>" ~ expression }
+/// clause = _{ pattern_match | literal_match | catch_all | absurd_match }
+/// choices = _{ "{" ~ (clause ~ ("|" ~ clause)*)? ~ "}" }
+/// pattern_match = { constructor_name ~ field_patterns ~ "=>" ~ expression }
+/// literal_match = { natural ~ "=>" ~ expression }
+/// catch_all = { meta_var ~ "=>" ~ expression }
+/// absurd_match = { constructor_name ~ field_patterns }
 /// ```
-fn choices_to_tree_map(the_rule: Tok) -> Branch {
-    let mut map: Branch = Default::default();
-    for pattern_match in the_rule.into_inner() {
-        let mut inner: Tik = pattern_match.into_inner();
-        let constructor_name = next_constructor_name(&mut inner);
-        let pattern = next_rule!(inner, maybe_pattern, maybe_pattern_to_pattern);
-        let expression = next_expression(&mut inner);
-        map.insert(
-            constructor_name,
-            Box::new(Expression::Lambda(pattern, None, Box::new(expression))),
-        );
+/// Nested patterns are compiled into nested case trees, see `compile_split`.
+fn choices_to_split(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let mut clauses: Vec<Clause> = Default::default();
+    let mut spans: Vec<Option<Span>> = Default::default();
+    for clause in the_rule.into_inner() {
+        spans.push(Some(span_of(&clause)));
+        let rule = clause.as_rule();
+        let mut inner: Tik = clause.into_inner();
+        let pattern = match rule {
+            Rule::pattern_match | Rule::absurd_match => {
+                let mut span = span_of(inner.peek().as_ref().unwrap());
+                let constructor_name = next_constructor_name(&mut inner);
+                let fields = inner.peek().unwrap();
+                if !fields.as_str().trim().is_empty() {
+                    span = span.merge(span_of(&fields));
+                }
+                let fields = next_rule!(scope, inner, field_patterns, field_patterns_to_pattern);
+                CasePattern::constructor(constructor_name, fields).at(span)
+            }
+            Rule::literal_match => {
+                let natural = inner.next().unwrap();
+                let span = span_of(&natural);
+                natural_to_case_pattern(scope, natural).at(span)
+            }
+            Rule::catch_all => {
+                inner.next();
+                CasePattern::Pattern(Pattern::Unit)
+            }
+            _ => unreachable!(),
+        };
+        let expression = inner
+            .next()
+            .map(|rule| expression_to_expression(scope, rule));
+        end_of_rule(&mut inner);
+        clauses.push((pattern, expression));
     }
-    map
+    let split = Expression::Split(compile_split(clauses.clone()));
+    let clauses = SourceClauses {
+        scrutinee: None,
+        clauses: clauses.into_iter().zip(spans).collect(),
+    };
+    Expression::Clauses(Box::new(clauses), Box::new(split))
 }
 
 /// ```ignore
-/// pi = ${ ("\\Pi" | "\u{03A0}") ~ level }
-/// pi_type = { pi ~ level ~ typed_abstraction }
+/// match_clause = { case_pattern ~ "=>" ~ expression }
+/// absurd_clause = { case_pattern }
+/// match_expression = { "match" ~ pair ~ match_choices }
 /// ```
-fn pi_type_to_expression(the_rule: Tok) -> Expression {
-    let (first_name, first_type, second) = typed_abstraction_to_tuple(the_rule);
-    Expression::Pi(Typed::new(first_name, first_type), Box::new(second))
+/// `match x, y { p, q => e }` matches the tuple `(x, y)` against `(p, q)`, see `compile_match`.
+fn match_expression_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let mut inner: Tik = the_rule.into_inner();
+    let scrutinee = next_rule!(scope, inner, pair, pair_to_expression);
+    let clauses: Vec<(Clause, Option<Span>)> = inner
+        .map(|clause| {
+            let span = span_of(&clause);
+            let mut inner: Tik = clause.into_inner();
+            let pattern = next_rule!(scope, inner, case_pattern, case_pattern_to_pattern);
+            let expression = inner
+                .next()
+                .map(|rule| expression_to_expression(scope, rule));
+            end_of_rule(&mut inner);
+            ((pattern, expression), Some(span))
+        })
+        .collect();
+    let compiled = (clauses.iter()).map(|(clause, _)| clause.clone()).collect();
+    let compiled = compile_match(scrutinee.clone(), compiled);
+    let clauses = SourceClauses {
+        scrutinee: Some(scrutinee),
+        clauses,
+    };
+    Expression::Clauses(Box::new(clauses), Box::new(compiled))
 }
 
 /// ```ignore
-/// sigma = ${ ("\\Sigma" | "\u{03A3}") ~ level }
-/// pi_type = { pi ~ typed_abstraction }
+/// pi_type = { pi ~ telescope_abstraction }
 /// ```
-fn sigma_type_to_expression(the_rule: Tok) -> Expression {
-    let (input_name, input_type, output) = typed_abstraction_to_tuple(the_rule);
-    Expression::Sigma(Typed::new(input_name, input_type), Box::new(output))
+fn pi_type_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    telescope_abstraction_to_expression(scope, the_rule, Expression::Pi)
+}
+
+/// ```ignore
+/// sigma_type = { sigma ~ telescope_abstraction }
+/// ```
+fn sigma_type_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    telescope_abstraction_to_expression(scope, the_rule, Expression::Sigma)
+}
+
+/// ```ignore
+/// record_field = { identifier ~ ":" ~ expression }
+/// record_type = { "record" ~ "{" ~ (record_field ~ (";" ~ record_field)* ~ ";"?)? ~ "}" }
+/// ```
+/// `record { x : A; y : B x }` is `\Sigma x : A. \Sigma y : B x. 1`.
+fn record_type_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let fields: Vec<Typed> = (the_rule.into_inner())
+        .map(|field| {
+            let mut inner: Tik = field.into_inner();
+            let name = next_rule!(inner, identifier, identifier_to_name);
+            Typed::new(Pattern::Var(name), next_expression(scope, &mut inner))
+        })
+        .collect();
+    (fields.into_iter().rev()).fold(Expression::One, |rest, field| {
+        Expression::Sigma(field, Box::new(rest))
+    })
+}
+
+/// ```ignore
+/// field_assignment = { identifier ~ "=" ~ expression }
+/// record_literal = { "{" ~ field_assignment ~ (";" ~ field_assignment)* ~ ";"? ~ "}" }
+/// ```
+/// The fields can be in any order, the type-checker puts them in the order of the record type.
+fn record_literal_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let fields = (the_rule.into_inner())
+        .map(|field| {
+            let mut inner: Tik = field.into_inner();
+            let name = next_rule!(inner, identifier, identifier_to_name);
+            (name, next_expression(scope, &mut inner))
+        })
+        .collect();
+    Expression::Record(fields)
 }
 
 /// ```ignore
 /// typed_abstraction = _{ pattern ~ ":" ~ expression ~ "." ~ expression }
+/// telescope_abstraction = _{ (telescope ~ "." ~ expression) | typed_abstraction }
+/// ```
+/// Each binder in the telescope becomes a nested abstraction,
+/// so `\Pi (a b : A) (c : B). T` is `\Pi a : A. \Pi b : A. \Pi c : B. T`.
+fn telescope_abstraction_to_expression(
+    scope: &mut Scope,
+    the_rule: Tok,
+    abstraction: impl Fn(Typed, Box<Expression>) -> Expression,
+) -> Expression {
+    let mut inner: Tik = the_rule.into_inner();
+    let binders = match inner.peek().unwrap().as_rule() {
+        Rule::telescope => next_rule!(scope, inner, telescope, telescope_to_vec),
+        _ => {
+            let input_name = next_pattern(&mut inner);
+            let input_type = next_expression(scope, &mut inner);
+            vec![Typed::new(input_name, input_type)]
+        }
+    };
+    let output = next_expression(scope, &mut inner);
+    end_of_rule(&mut inner);
+    binders.into_iter().rev().fold(output, |output, binder| {
+        abstraction(binder, Box::new(output))
+    })
+}
+
+/// ```ignore
+/// telescope = { typed_binder+ }
 /// ```
-fn typed_abstraction_to_tuple(the_rule: Tok) -> (Pattern, Expression, Expression) {
+fn telescope_to_vec(scope: &mut Scope, the_rule: Tok) -> Vec<Typed> {
+    the_rule
+        .into_inner()
+        .flat_map(|rule| typed_binder_to_vec(scope, rule))
+        .collect()
+}
+
+/// ```ignore
+/// typed_binder = { "(" ~ atom_pattern+ ~ ":" ~ expression ~ ")" }
+/// ```
+fn typed_binder_to_vec(scope: &mut Scope, the_rule: Tok) -> Vec<Typed> {
+    let mut patterns: Vec<Pattern> = Default::default();
     let mut inner: Tik = the_rule.into_inner();
-    let input_name = next_pattern(&mut inner);
-    let input_type = next_expression(&mut inner);
-    let output = next_expression(&mut inner);
+    while inner.peek().unwrap().as_rule() == Rule::atom_pattern {
+        patterns.push(next_rule!(inner, atom_pattern, atom_pattern_to_pattern));
+    }
+    let binder_type = next_expression(scope, &mut inner);
     end_of_rule(&mut inner);
-    (input_name, input_type, output)
+    patterns
+        .into_iter()
+        .map(|pattern| Typed::new(pattern, binder_type.clone()))
+        .collect()
 }
 
 /// ```ignore
@@ -364,7 +1659,10 @@ fn typed_abstraction_to_tuple(the_rule: Tok) -> (Pattern, Expression, Expression
 fn atom_pattern_to_pattern(the_rule: Tok) -> Pattern {
     let rule: Tok = the_rule.into_inner().next().unwrap();
     match rule.as_rule() {
-        Rule::identifier => Pattern::Var(identifier_to_name(rule)),
+        Rule::identifier => {
+            let span = span_of(&rule);
+            Pattern::located(span, Pattern::Var(identifier_to_name(rule)))
+        }
         Rule::meta_var => Pattern::Unit,
         Rule::pattern => pattern_to_pattern(rule),
         _ => unreachable!(),
@@ -379,11 +1677,13 @@ fn pattern_to_pattern(the_rule: Tok) -> Pattern {
     let rule: Tok = the_rule.into_inner().next().unwrap();
     match rule.as_rule() {
         Rule::pair_pattern => {
+            let span = span_of(&rule);
             let mut inner: Tik = rule.into_inner();
             let first = next_rule!(inner, atom_pattern, atom_pattern_to_pattern);
             let second = next_pattern(&mut inner);
             end_of_rule(&mut inner);
-            Pattern::Pair(Box::new(first), Box::new(second))
+            let pair = Pattern::Pair(Box::new(first), Box::new(second));
+            Pattern::located(span, pair)
         }
         Rule::atom_pattern => atom_pattern_to_pattern(rule),
         _ => unreachable!(),
@@ -392,20 +1692,44 @@ fn pattern_to_pattern(the_rule: Tok) -> Pattern {
 
 /// ```ignore
 /// lambda = _{ lambda unicode | "\\lambda" }
-/// lambda_expression = { lambda ~ pattern ~ "." ~ expression }
+/// lambda_expression = { lambda ~ (typed_binder | pattern)+ ~ "." ~ expression }
 /// ```
-fn lambda_expression_to_expression(the_rule: Tok) -> Expression {
+/// `\lambda a (b : B). e` is `\lambda a. \lambda (b : B). e`.
+fn lambda_expression_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let mut parameters: Vec<(Pattern, Option<Box<Expression>>)> = Default::default();
     let mut inner: Tik = the_rule.into_inner();
-    let parameter = next_pattern(&mut inner);
-    let body = next_expression(&mut inner);
+    loop {
+        match inner.peek().unwrap().as_rule() {
+            Rule::pattern => parameters.push((next_pattern(&mut inner), None)),
+            Rule::typed_binder => {
+                let binders = next_rule!(scope, inner, typed_binder, typed_binder_to_vec);
+                parameters.extend(binders.into_iter().map(|binder| {
+                    let (pattern, parameter_type) = binder.destruct();
+                    (pattern, Some(Box::new(parameter_type)))
+                }));
+            }
+            _ => break,
+        }
+    }
+    let body = next_expression(scope, &mut inner);
     end_of_rule(&mut inner);
-    Expression::Lambda(parameter, None, Box::new(body))
+    parameters
+        .into_iter()
+        .rev()
+        .fold(body, |body, (parameter, parameter_type)| {
+            Expression::Lambda(parameter, parameter_type, Box::new(body))
+        })
 }
 
-/// Constructor as an expression
-fn constructor_to_expression(the_rule: Tok) -> Expression {
-    let (constructor, argument) = constructor_to_tuple(the_rule);
-    Expression::Constructor(constructor, Box::new(argument))
+/// ```ignore
+/// ascription = { "(" ~ expression ~ ":" ~ expression ~ ")" }
+/// ```
+fn ascription_to_expression(scope: &mut Scope, the_rule: Tok) -> Expression {
+    let mut inner: Tik = the_rule.into_inner();
+    let expression = next_expression(scope, &mut inner);
+    let expression_type = next_expression(scope, &mut inner);
+    end_of_rule(&mut inner);
+    Expression::Ascription(Box::new(expression), Box::new(expression_type))
 }
 
 /// ```ignore
@@ -419,31 +1743,86 @@ fn universe_to_expression(the_rule: Tok) -> Expression {
 }
 
 /// ```ignore
-/// constructor_name = @{ ASCII_ALPHA_UPPER ~ identifier? }
-/// constructor = { constructor_name ~ expression }
+/// field_pattern = { atom_case_pattern ~ ("," ~ case_pattern)? }
+/// field_patterns = { field_pattern* }
 /// ```
-fn constructor_to_tuple(the_rule: Tok) -> (String, Expression) {
-    let mut inner: Tik = the_rule.into_inner();
-    let constructor = next_constructor_name(&mut inner);
-    let argument = inner
-        .next()
-        .map(expression_to_expression)
-        .unwrap_or(Expression::Unit);
-    end_of_rule(&mut inner);
-    (constructor, argument)
+/// Fields are matched positionally, `C a b c` matches `C (a, (b, c))`.
+fn field_patterns_to_pattern(scope: &mut Scope, the_rule: Tok) -> CasePattern {
+    fields_to_case_pattern(
+        the_rule
+            .into_inner()
+            .map(|rule| case_pattern_to_pattern(scope, rule))
+            .collect(),
+    )
+}
+
+/// Helper, extracted.
+/// The pattern of a constructor's fields, `(a, (b, c))` for `a b c`.
+fn fields_to_case_pattern(mut patterns: Vec<CasePattern>) -> CasePattern {
+    match patterns.pop() {
+        Some(last) => patterns
+            .into_iter()
+            .rev()
+            .fold(last, |second, first| CasePattern::pair(first, second)),
+        None => CasePattern::Pattern(Pattern::Unit),
+    }
+}
+
+/// ```ignore
+/// absurd_pattern = { "!" | "(" ~ ")" }
+/// atom_case_pattern =
+///   { absurd_pattern
+///   | constructor_name
+///   | natural
+///   | identifier
+///   | meta_var
+///   | "(" ~ case_pattern ~ ")"
+///   }
+/// ```
+/// A constructor without fields like `Zero` is a constructor pattern, not a variable.
+fn atom_case_pattern_to_pattern(scope: &mut Scope, the_rule: Tok) -> CasePattern {
+    let rule: Tok = the_rule.into_inner().next().unwrap();
+    let span = span_of(&rule);
+    let pattern = match rule.as_rule() {
+        Rule::absurd_pattern => CasePattern::Absurd(None),
+        Rule::constructor_name => CasePattern::constructor(
+            identifier_to_name(rule),
+            CasePattern::Pattern(Pattern::Unit),
+        ),
+        Rule::natural => natural_to_case_pattern(scope, rule),
+        Rule::identifier => CasePattern::Pattern(Pattern::Var(identifier_to_name(rule))),
+        Rule::meta_var => CasePattern::Pattern(Pattern::Unit),
+        Rule::case_pattern => return case_pattern_to_pattern(scope, rule),
+        _ => unreachable!(),
+    };
+    pattern.at(span)
 }
 
 /// ```ignore
-/// maybe_pattern = { pattern? }
+/// constructor_pattern = { constructor_name ~ atom_case_pattern* }
+/// case_pattern = { (constructor_pattern | atom_case_pattern) ~ ("," ~ case_pattern)? }
+/// field_pattern = { atom_case_pattern ~ ("," ~ case_pattern)? }
 /// ```
-fn maybe_pattern_to_pattern(the_rule: Tok) -> Pattern {
+fn case_pattern_to_pattern(scope: &mut Scope, the_rule: Tok) -> CasePattern {
     let mut inner: Tik = the_rule.into_inner();
-    let pattern = inner
-        .next()
-        .map(pattern_to_pattern)
-        .unwrap_or(Pattern::Unit);
-    end_of_rule(&mut inner);
-    pattern
+    let first = inner.next().unwrap();
+    let first = match first.as_rule() {
+        Rule::constructor_pattern => {
+            let span = span_of(&first);
+            let mut inner: Tik = first.into_inner();
+            let name = next_constructor_name(&mut inner);
+            let fields = inner
+                .map(|rule| atom_case_pattern_to_pattern(scope, rule))
+                .collect();
+            CasePattern::constructor(name, fields_to_case_pattern(fields)).at(span)
+        }
+        Rule::atom_case_pattern => atom_case_pattern_to_pattern(scope, first),
+        _ => unreachable!(),
+    };
+    match inner.next() {
+        Some(second) => CasePattern::pair(first, case_pattern_to_pattern(scope, second)),
+        None => first,
+    }
 }
 
 /// ```ignore
@@ -456,16 +1835,33 @@ fn variable_to_expression(the_rule: Tok) -> Expression {
     Expression::Var(name)
 }
 
+/// ```ignore
+/// qualified_name = ${ (constructor_name ~ ".")+ ~ identifier }
+/// ```
+/// The components are joined by dots, `Nat.add`.
+fn qualified_to_name(the_rule: Tok) -> String {
+    let names: Vec<_> = the_rule.into_inner().map(identifier_to_name).collect();
+    names.join(".")
+}
+
 /// ```ignore
 /// identifier = @{ !"let" ~ !"rec" ~ !"0" ~ !"1" ~ character+ }
 /// ```
 fn identifier_to_name(rule: Tok) -> String {
-    rule.as_span().as_str().to_string()
+    rule.as_span().as_str().nfc().collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::parse_str_err_printed;
+    use crate::ast::{Associativity, Expression, Fixity, Literal};
+    use crate::check::read_back::ReadBack;
+    use crate::check::tcm::TCS;
+    use crate::check::{check_elaborate_contextual, check_infer_named_contextual, check_main};
+    use crate::parser::{
+        parse_str, parse_str_err_printed, parse_str_recovering, parse_str_to_expression_in, unlit,
+        Scope,
+    };
+    use crate::pretty::with_naturals;
 
     fn successful_test_case(code: &str) {
         println!("========= source ===========");
@@ -479,6 +1875,22 @@ mod tests {
         println!("========= finish ===========\n");
     }
 
+    /// The case tree compiled from the clauses of the first declaration, under its lambdas.
+    fn case_tree(code: &str) -> String {
+        let mut expr = match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => declaration.body,
+            _ => unreachable!(),
+        };
+        loop {
+            expr = match expr {
+                Expression::Lambda(_, _, body) => *body,
+                Expression::Located(_, expr) => *expr,
+                Expression::Clauses(_, compiled) => return format!("{}", compiled),
+                _ => unreachable!(),
+            }
+        }
+    }
+
     #[test]
     fn simple_parse() {
         successful_test_case("let unit_one : 1 = 0;\nlet type_one : Type0 = unit_one;");
@@ -489,6 +1901,632 @@ mod tests {
         successful_test_case("let pi_lambda : \\Pi a : b . c = \\lambda a . expr;");
         successful_test_case("let pat, pat2 : \\Pi _ : b . c = \\lambda _ . expr;");
         successful_test_case("let function : Sum {C e} = split {C _ => e};");
+        successful_test_case("let function : Sum {C e | D e} = split {C _ => e | _ => e};");
+    }
+
+    #[test]
+    fn left_associative_application() {
+        let parse = |code: &str| match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => format!("{}", declaration.body),
+            _ => unreachable!(),
+        };
+        assert_eq!(parse("let x : t = f a b c;"), "(f a b c)");
+        assert_eq!(parse("let x : t = ((f a) b) c;"), "(f a b c)");
+        assert_eq!(parse("let x : t = f (a b) c;"), "(f (a b) c)");
+        assert_eq!(parse("let x : t = f a.1, g b;"), "((f a.1), (g b))");
+        assert_eq!(parse("let x : t = Cons n Zero;"), "Cons (n, Zero)");
+        assert_eq!(parse("let x : t = Suc (n Zero);"), "Suc (n Zero)");
+        successful_test_case("let arrow : f a -> g b * c = h a b ++ k;");
+    }
+
+    #[test]
+    fn natural_literals() {
+        let code = "{-# BUILTIN NATURAL nat Z S #-}\n\
+                    let x : t = f 0 1 2 x2;\n\
+                    let y : t = split { 2 => 3 | _ => 10 };";
+        let (natural, rest) = match parse_str_err_printed(code).unwrap() {
+            Expression::Natural(natural, rest) => {
+                let constructor = |name: &str, argument| {
+                    Expression::Constructor(name.to_string(), Box::new(argument))
+                };
+                let two = constructor("S", constructor("S", constructor("Z", Expression::Unit)));
+                assert_eq!(natural.literal(2), two);
+                (*natural, *rest)
+            }
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            format!("{}", with_naturals(std::slice::from_ref(&natural), &rest)),
+            "let x: t = (f 0 1 2 x2);\n\
+             let y: t = split {2 => 3 | _ => 10};\n\n\n"
+        );
+        // Out of the scope of the pragma, they're constructor chains again
+        assert!(format!("{}", rest).starts_with("let x: t = (f 0 1 (S (S Z)) x2);"));
+        successful_test_case("{-# BUILTIN NATURAL nat Z S #-}\nlet x : t = f 0 1 2 x2;");
+        assert!(parse_str("let x : t = 2;").is_err());
+        // The pragmas in scope are given by the caller, like the ones of the imported modules
+        let scope = Scope {
+            naturals: vec![natural],
+            ..Default::default()
+        };
+        let (_, expression) = parse_str_to_expression_in("let x : t = 2;", &scope).unwrap();
+        assert!(format!("{}", expression).starts_with("let x: t = S (S Z);"));
+    }
+
+    #[test]
+    fn natural_values() {
+        let code = "data nat = Zero | Suc nat;\n\
+                    {-# BUILTIN NATURAL nat Zero Suc #-}\n\
+                    let two : nat = Suc (Suc Zero);";
+        let expression = parse_str_err_printed(code).unwrap();
+        let naturals = expression.naturals();
+        let tcs = check_main(expression).unwrap();
+        let print = |code: &str| {
+            let context = tcs.context();
+            let expression = parse_str_err_printed(code).unwrap();
+            let expression = check_elaborate_contextual(crate::tcs_borrow!(tcs), expression);
+            let value = expression.unwrap().eval(context);
+            let normal = value.clone().read_back_please();
+            (
+                format!("{}", with_naturals(&naturals, &value)),
+                format!("{}", with_naturals(&naturals, &normal)),
+                format!("{}", value),
+            )
+        };
+        let three = "Suc (Suc (Suc Zero))".to_owned();
+        assert_eq!(print("Suc two"), ("3".to_owned(), "3".to_owned(), three));
+        let one = "Suc Zero".to_owned();
+        assert_eq!(print("Suc Zero"), (one.clone(), one.clone(), one));
+    }
+
+    #[test]
+    fn named_inferred_types() {
+        let code = "data nat = Zero | Suc nat;\n\
+                    let pair : Type = \\Sigma (_ : nat). nat;";
+        let tcs = check_main(parse_str_err_printed(code).unwrap()).unwrap();
+        let infer = |code: &str| {
+            let expression = parse_str_err_printed(code).unwrap();
+            let inferred = check_infer_named_contextual(crate::tcs_borrow!(tcs), expression);
+            format!("{}", inferred.unwrap())
+        };
+        assert_eq!(infer("\\lambda (x : nat). Suc x"), "nat -> Sum {Suc nat}");
+        assert_eq!(infer("\\lambda (p : pair). p.1"), "pair -> nat");
+        assert_eq!(
+            infer("\\lambda (t : Type) (x : t). x"),
+            "\u{03A0} <0>: Type0. <0> -> <0>"
+        );
+    }
+
+    #[test]
+    fn literate_files() {
+        let document = "# Title\n```minitt\nlet x : A = y;\n```\n```haskell\nx :: A\n```\n\
+                        text\n  ```minitt\nlet z : A = x;\n";
+        assert_eq!(
+            unlit(document),
+            "\n\nlet x : A = y;\n\n\n\n\n\n\nlet z : A = x;\n"
+        );
+        successful_test_case(&unlit(document));
+        let document = "~~~minitt\nlet x : A = y;\n~~~\n````haskell\n~~~minitt\n````\n";
+        assert_eq!(unlit(document), "\nlet x : A = y;\n\n\n\n\n");
+    }
+
+    #[test]
+    fn unicode_identifiers() {
+        let body = |code: &str| match parse_str_err_printed(&format!("let x : t = {};", code)) {
+            Ok(Expression::Declaration(declaration, _)) => declaration.body.into_unlocated(),
+            _ => unreachable!(),
+        };
+        let constructor =
+            |name: &str| Expression::Constructor(name.to_string(), Box::new(Expression::Unit));
+        assert_eq!(body("ℕ"), Expression::Var("ℕ".to_string()));
+        assert_eq!(body("α'"), Expression::Var("α'".to_string()));
+        assert_eq!(body("Ärger"), constructor("Ärger"));
+        assert_eq!(body("Ωmega"), constructor("Ωmega"));
+        // The rest of a constructor name may contain keywords and `λ`
+        assert_eq!(body("Ελλάς"), constructor("Ελλάς"));
+        assert_eq!(body("Bopen"), constructor("Bopen"));
+        assert_eq!(body("Cmodule"), constructor("Cmodule"));
+        // NFD is normalized to NFC
+        assert_eq!(
+            body("cafe\u{301}"),
+            Expression::Var("caf\u{e9}".to_string())
+        );
+        successful_test_case("let f : Πα: ℕ. ℕ = λα. Ŝucc α;");
+        assert!(parse_str("let → : t = x;").is_err());
+    }
+
+    #[test]
+    fn where_clauses() {
+        let code =
+            "let f (x : A) : B = g x where { let g : A -> B = h where { let h : A -> B = k; }; };";
+        match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, rest) => {
+                assert_eq!(*rest, Expression::Void);
+                assert_eq!(declaration.prefix_parameters.len(), 1);
+                assert_eq!(
+                    format!("{}", declaration.body),
+                    "let g: A -> B = let h: A -> B = k;\nh\n;\n(g x)\n"
+                );
+            }
+            _ => unreachable!(),
+        }
+        successful_test_case(
+            "let f : A = x where { let y : A = x; rec z : A = y; };\nlet g : A = f;",
+        );
+        successful_test_case("let f : A = x where {};");
+        assert!(parse_str("let f : A = x where { y };").is_err());
+        assert!(parse_str("let where : A = x;").is_err());
+    }
+
+    #[test]
+    fn records() {
+        let body = |code: &str| match parse_str_err_printed(&format!("let x : t = {};", code)) {
+            Ok(Expression::Declaration(declaration, _)) => format!("{}", declaration.body),
+            _ => unreachable!(),
+        };
+        assert_eq!(body("record { a : A; b : B a }"), "record {a: A; b: B a}");
+        assert_eq!(body("record {}"), "1");
+        assert_eq!(body("{ b = y; a = x; }"), "{b = y; a = x}");
+        assert_eq!(body("f r.a.2 r.b"), "(f r.a.2 r.b)");
+        assert_eq!(body("\\Pi a : A. a"), "\u{03A0} a: A. a");
+        successful_test_case("let x : record { a : A } = { a = recordA };");
+        assert!(parse_str("let x : t = { a = x; a = y };").is_err());
+        assert!(parse_str("let record : t = x;").is_err());
+    }
+
+    #[test]
+    fn evaluate_records() {
+        let code = "data ab = A | B;\n\
+                    let t : Type = record { a : ab; b : ab };\n\
+                    let r : t = { b = B; a = A };";
+        let tcs = check_main(parse_str_err_printed(code).unwrap()).unwrap();
+        let normalize = |code: &str| {
+            let context = tcs.context();
+            let expression = parse_str_err_printed(code).unwrap();
+            let expression =
+                check_elaborate_contextual(crate::tcs_borrow!(tcs), expression).unwrap();
+            format!("{}", expression.eval(context).read_back_please())
+        };
+        assert_eq!(normalize("r.b"), "B");
+        assert_eq!(normalize("r"), "(A, (B, 0))");
+    }
+
+    #[test]
+    fn modules() {
+        let code = "module Nat { let add : t = x; module Even { let two : t = add; } }\n\
+                    open Nat;\nlet y : t = Nat.Even.two Even.two add;";
+        match parse_str_err_printed(code).unwrap() {
+            Expression::Module(module, rest) => {
+                assert_eq!(module.name, "Nat");
+                assert_eq!(module.names(), vec!["add", "Even.two"]);
+                match *rest {
+                    Expression::Open(open, _) => assert_eq!(open.name, "Nat"),
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+        successful_test_case("module Empty {}\nopen Empty;\nlet opened : A = Cons.x;");
+        assert!(parse_str("open nat;").is_err());
+        assert!(parse_str("let x : t = Nat . add;").is_err());
+        assert!(parse_str("let module : t = x;").is_err());
+    }
+
+    #[test]
+    fn visibility() {
+        let code =
+            "export (x, Nat.Even, Nat.two)\nimport nat;\nprivate rec x : t = y;\nlet y : t = x;";
+        match parse_str_err_printed(code).unwrap() {
+            Expression::Export(export, rest) => {
+                assert_eq!(export.names, vec!["x", "Nat.Even", "Nat.two"]);
+                assert!(export.contains("Nat.Even.four"));
+                assert!(!export.contains("Nat.Evening"));
+                assert_eq!(
+                    rest.definitions(),
+                    vec![("x".to_string(), true), ("y".to_string(), false)]
+                );
+            }
+            _ => unreachable!(),
+        }
+        successful_test_case("export ()\nlet privately : t = x;");
+        assert!(parse_str("let x : t = y;\nexport (x)").is_err());
+        assert!(parse_str("private const x = y;").is_err());
+    }
+
+    #[test]
+    fn imports() {
+        match parse_str_err_printed("import nat;\nimport data.list;\nlet x : t = important;") {
+            Ok(Expression::Import(import, rest)) => {
+                assert_eq!(import.path, vec!["nat".to_string()]);
+                match *rest {
+                    Expression::Import(import, _) => assert_eq!(import.name(), "data.list"),
+                    _ => unreachable!(),
+                }
+            }
+            _ => unreachable!(),
+        }
+        successful_test_case("import nat;");
+        // Imports are only allowed at the beginning of a file
+        assert!(parse_str("let x : t = y;\nimport nat;").is_err());
+        assert!(parse_str("import data . list;").is_err());
+        assert!(parse_str("let import : t = x;").is_err());
+    }
+
+    #[test]
+    fn string_literals() {
+        let body = |code: &str| match parse_str_err_printed(&format!("let x : t = {};", code)) {
+            Ok(Expression::Declaration(declaration, _)) => declaration.body.into_unlocated(),
+            _ => unreachable!(),
+        };
+        let string = |s: &str| Expression::Literal(Literal::String(s.to_string()));
+        assert_eq!(body(r#""a\"\n\u{3bb}""#), string("a\"\nλ"));
+        assert_eq!(body(r"'\''"), Expression::Literal(Literal::Char('\'')));
+        assert_eq!(body("String"), Expression::StringType);
+        assert_eq!(body("Char"), Expression::CharType);
+        let strings = Expression::Constructor("Strings".to_string(), Box::new(Expression::Unit));
+        assert_eq!(body("Strings"), strings);
+        successful_test_case("let x : String = primStringAppend \"a'\\\"\" a';");
+        successful_test_case("let x : Char = f '\\\\' '\\'' Char';");
+        assert!(parse_str("let x : t = '\\u{d800}';").is_err());
+        assert!(parse_str("let x : t = primStringLength;").is_err());
+        assert!(parse_str("data t = String;").is_err());
+    }
+
+    #[test]
+    fn infix_operators() {
+        let parse = |code: &str| {
+            let code = format!(
+                "infixl 6 + = plus;\ninfixr 5 :: = cons;\nlet x : t = {};",
+                code
+            );
+            match parse_str_err_printed(&code).unwrap() {
+                Expression::Fixity(_, rest) => match *rest {
+                    Expression::Fixity(_, rest) => match *rest {
+                        Expression::Declaration(declaration, _) => format!("{}", declaration.body),
+                        _ => unreachable!(),
+                    },
+                    _ => unreachable!(),
+                },
+                _ => unreachable!(),
+            }
+        };
+        assert_eq!(parse("a + b + c"), "((a + b) + c)");
+        assert_eq!(parse("a :: b :: c"), "(a :: (b :: c))");
+        assert_eq!(parse("a + b :: c + d"), "((a + b) :: (c + d))");
+        assert_eq!(parse("f a + g b"), "((f a) + (g b))");
+        // Operators without fixity declarations are `infixl 9`
+        assert_eq!(parse("a + b <> c"), "(a + (b <> c))");
+        assert_eq!(parse("(+) a"), "((+) a)");
+        assert_eq!(parse("(a + b) c"), "((a + b) c)");
+        assert_eq!(parse("a ++ b + c -> d"), "(a ++ (b + c)) -> d");
+        successful_test_case("infixl 6 + = plus;\nlet x : t = a + b + (+) c d;");
+        // The fixities in scope are given by the caller, they don't outlive the conversion
+        let mut scope = Scope::default();
+        let cons = Fixity::new("::".to_owned(), Associativity::Right, 5, "cons".to_owned());
+        scope.fixities.push(cons);
+        let code = "let x : t = a :: b :: c;";
+        let (_, scoped) = parse_str_to_expression_in(code, &scope).unwrap();
+        assert!(format!("{}", scoped).starts_with("let x: t = (a :: (b :: c));"));
+        let unscoped = parse_str_err_printed(code).unwrap();
+        assert!(format!("{}", unscoped).starts_with("let x: t = ((a :: b) :: c);"));
+    }
+
+    #[test]
+    fn ambiguous_operators() {
+        let parse = |code: &str| {
+            let code = format!(
+                "infixl 5 + = plus;\ninfixr 5 :: = cons;\ninfixl 4 <> = append;\nlet x : t = {};",
+                code
+            );
+            parse_str(&code).map(|_| ())
+        };
+        let ambiguous = parse("a + b :: c").unwrap_err();
+        assert!(ambiguous.contains("parentheses, `+` is `infixl 5` but `::` is `infixr 5`"));
+        assert!(parse("a :: b + c").is_err());
+        // Operators without fixity declarations are `infixl 9`, they bind tighter
+        assert!(parse("a + b ^ c :: d").is_err());
+        assert!(parse("a + b <> c :: d").is_ok());
+        assert!(parse("a + (b :: c)").is_ok());
+        // The innermost fixity declaration is in scope
+        assert!(parse("(infixr 5 + = plus; a + b :: c)").is_ok());
+    }
+
+    #[test]
+    fn spans() {
+        let code = "let x : t =\n  f (a b) c;";
+        let body = match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => {
+                let span = declaration.span.unwrap();
+                assert_eq!(&code[span.start..span.end], "let x : t =\n  f (a b) c");
+                declaration.body
+            }
+            _ => unreachable!(),
+        };
+        let (span, function) = match body {
+            Expression::Located(span, body) => match *body {
+                Expression::Application(function, _) => (span, *function),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+        assert_eq!((span.line, span.column), (2, 3));
+        assert_eq!(&code[span.start..span.end], "f (a b) c");
+        match function {
+            Expression::Located(span, _) => assert_eq!(&code[span.start..span.end], "f (a b)"),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn pattern_spans() {
+        let code = "let f : t = \\lambda (x, y) z. e;";
+        let body = match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => declaration.body,
+            _ => unreachable!(),
+        };
+        let mut patterns = Vec::new();
+        let mut body = body.into_unlocated();
+        while let Expression::Lambda(pattern, _, rest) = body {
+            let span = pattern.span().unwrap();
+            patterns.push(&code[span.start..span.end]);
+            body = rest.into_unlocated();
+        }
+        assert_eq!(patterns, vec!["x, y", "z"]);
+    }
+
+    #[test]
+    fn error_spans() {
+        fn located(code: &str) -> ((usize, usize), &str) {
+            let err = check_main(parse_str_err_printed(code).unwrap()).unwrap_err();
+            let span = err.span().unwrap();
+            ((span.line, span.column), &code[span.start..span.end])
+        }
+        let nat = "rec nat : Type = Sum { Zero | Suc nat };\n";
+        assert_eq!(located("let x : 1 = Type;"), ((1, 13), "Type"));
+        assert_eq!(
+            located(&format!("{}let f : nat -> nat = \\lambda (a, b). a;", nat)),
+            ((2, 31), "a, b")
+        );
+        assert_eq!(
+            located(&format!(
+                "{}let f : nat -> nat = split {{ Zero => Zero | Suc a b => a }};",
+                nat
+            )),
+            ((2, 45), "Suc a b")
+        );
+        assert_eq!(
+            located(&format!(
+                "{}let f : nat -> nat = split {{ Zero => Zero | Suc ! }};",
+                nat
+            )),
+            ((2, 49), "!")
+        );
+    }
+
+    #[test]
+    fn telescopes() {
+        let parse = |code: &str| match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => format!("{}", declaration),
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            parse("let f : \\Pi (a b : A) (c : B). T = \\lambda a b c. e;"),
+            "let f: \u{03A0} (a b: A) (c: B). T = \u{03BB} a b c. e"
+        );
+        assert_eq!(
+            parse("let f : \\Pi a : A. \\Pi b : A. T = \\lambda a. \\lambda b. e;"),
+            "let f: \u{03A0} (a b: A). T = \u{03BB} a b. e"
+        );
+        assert_eq!(
+            parse("let p : \\Sigma (x : A) (y : B x). C = x, y;"),
+            "let p: \u{03A3} (x: A) (y: B x). C = (x, y)"
+        );
+        assert_eq!(
+            parse("let p : \\Sigma x : A. C = \\lambda (x, y). x;"),
+            "let p: \u{03A3} x: A. C = \u{03BB} (x, y). x"
+        );
+        successful_test_case("let f : \\Pi (a b : A) (_ : B). T = \\lambda (a, b) _. e;");
+        assert_eq!(
+            parse("let f : T = \\lambda (a b : A) c (d : B). e;"),
+            "let f: T = \u{03BB} (a b: A) c (d: B). e"
+        );
+        successful_test_case("let f : T = \\lambda (a : A) (b, c) (_ : B). e;");
+        successful_test_case("let f : T = (\\lambda a. e : A -> B) (a : A), (b);");
+    }
+
+    #[test]
+    fn data_declaration() {
+        let code = "data list (a : Type) = Nil | Cons a (list a);\n\
+                    let f : T = split { Nil => Nil | Cons x xs => Cons x (Cons x xs) };";
+        let expr = parse_str_err_printed(code).unwrap();
+        let (data, rest) = match expr {
+            Expression::Declaration(data, rest) => (data, rest),
+            _ => unreachable!(),
+        };
+        assert!(data.is_recursive);
+        assert!(data.prefix_parameters.is_empty());
+        assert_eq!(
+            format!("{}", data),
+            "rec list: \u{03A0} a: Type0. Type0 = \
+             \u{03BB} a. Sum {Cons \u{03A3} _: a. (list a) | Nil 1}"
+        );
+        assert_eq!(
+            format!("{}", rest),
+            "let f: T = split {Nil => Nil | Cons x xs => Cons (x, Cons (x, xs))};\n\n"
+        );
+        successful_test_case("data empty = ;\ndata unit : Type0 = Unit;");
+        // The rest arguments of constructors are their other fields
+        let code = "data list = Nil | Cons nat list;\n\
+                    let x : list = Cons n Nil;\n\
+                    let y : nat = Suc n;";
+        assert_eq!(
+            format!("{}", parse_str_err_printed(code).unwrap()),
+            "rec list: Type0 = Sum {Cons \u{03A3} _: nat. list | Nil 1};\n\
+             let x: list = Cons (n, Nil);\n\
+             let y: nat = Suc n;\n\n\n\n"
+        );
+    }
+
+    #[test]
+    fn mutual_declaration() {
+        let code = "mutual { rec a : A = b; rec b (x : X) : B = a x; rec c : C = c; }";
+        let declaration = match parse_str_err_printed(code).unwrap() {
+            Expression::Declaration(declaration, _) => declaration,
+            _ => unreachable!(),
+        };
+        assert!(declaration.is_recursive);
+        assert_eq!(
+            format!("{}", declaration),
+            "rec (a, (b, c)): \u{03A3} (_: A) (_: \u{03A0} x: X. B). C = \
+             (b, (\u{03BB} x. (a x), c))"
+        );
+        successful_test_case("mutual { rec a : A = b; }\nlet x : A = a;");
+    }
+
+    #[test]
+    fn absurd_patterns() {
+        let code = "let f : T = split { Left n => n | Right ! | Both (_, ()) };";
+        assert_eq!(
+            case_tree(code),
+            "split {Both #1 => const (#2, #3) = #1;\n(split {} #3) \
+             | Left n => n | Right #0 => (split {} #0)}"
+        );
+        successful_test_case(code);
+        let errors = parse_str_recovering("let f : T = split { Left n => n | Right x };").1;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(format!("{}", errors[0]), "Syntax error, expected `=>`.");
+    }
+
+    #[test]
+    fn match_expression() {
+        let code = "let f : T = \\lambda x y. match x, y { True, b => b | _ => False };";
+        assert_eq!(
+            case_tree(code),
+            "const #0 = (x, y);\nconst (#1, #2) = #0;\n\
+             (split {True _ => const b = #2;\nb | _ => False} #1)"
+        );
+        // Printed as it's written, so that it can be parsed again
+        assert_eq!(
+            format!("{}", parse_str_err_printed(code).unwrap()),
+            "let f: T = \u{03BB} x y. match (x, y) {(True, b) => b | _ => False};\n\n"
+        );
+        successful_test_case(code);
+        let code = "let g : T = \\lambda x. match x { Just ! | Nothing => a };";
+        assert_eq!(
+            case_tree(code),
+            "(split {Just #0 => (split {} #0) | Nothing _ => a} x)"
+        );
+        successful_test_case(code);
+        let code = "let h : T = match x, y {};";
+        assert_eq!(case_tree(code), "const #0 = (x, y);\n(split {} #0)");
+        successful_test_case(code);
+    }
+
+    #[test]
+    fn nested_patterns() {
+        let code = "let f : T = split { Suc (Suc n) => n | Suc m => m | Zero => Zero };";
+        assert_eq!(
+            case_tree(code),
+            "split {Suc #0 => (split {Suc n => n | _ => const m = #0;\nm} #0) | Zero _ => Zero}"
+        );
+        // Printed as it's written, so that it can be parsed again
+        assert_eq!(
+            format!("{}", parse_str_err_printed(code).unwrap()),
+            "let f: T = split {Suc (Suc n) => n | Suc m => m | Zero => Zero};\n\n"
+        );
+        successful_test_case(code);
+        let code = "let g : T = split { Cons (Just x, _) => x | Cons Nothing Nil => a };";
+        assert_eq!(
+            case_tree(code),
+            "split {Cons #0 => const (#1, #2) = #0;\n\
+             (split {Just x => x | Nothing _ => (split {Nil _ => a} #2)} #1)}"
+        );
+        successful_test_case(code);
+    }
+
+    #[test]
+    fn missing_nested_case() {
+        let missing = |clauses: &str| {
+            let code = format!(
+                "data nat = Zero | Suc nat;\n\
+                 data list = Nil | Cons nat list;\n\
+                 let f : list -> nat = split {{ {} }};",
+                clauses
+            );
+            let err = check_main(parse_str_err_printed(&code).unwrap()).unwrap_err();
+            format!("{}", err.unlocated())
+        };
+        assert_eq!(missing("Zero => Zero"), "Missing case-split: `Cons _ _`.");
+        assert_eq!(
+            missing("Nil => Zero | Cons Zero Nil => Zero | Cons (Suc n) _ => n"),
+            "Missing case-split: `Cons Zero (Cons _ _)`."
+        );
+    }
+
+    #[test]
+    fn unreachable_clauses() {
+        let unreachable = |clauses: &str| {
+            let code = format!(
+                "data ab = A | B;\n\
+                 data nat = Zero | Suc nat;\n\
+                 let f : ab -> nat = split {{ {} }};",
+                clauses
+            );
+            let tcs = check_main(parse_str_err_printed(&code).unwrap()).unwrap();
+            let warnings = tcs.warnings.borrow();
+            (warnings.iter())
+                .map(|warning| {
+                    let span = warning.span().unwrap();
+                    code[span.start..span.end].to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+        // The catch-all in the middle matches `B`, the clause after it is unreachable
+        assert_eq!(
+            unreachable("A => Zero | _ => Zero | B => Suc Zero"),
+            vec!["B => Suc Zero"]
+        );
+        assert_eq!(
+            unreachable("A => Zero | B => Zero | _ => Zero"),
+            vec!["_ => Zero"]
+        );
+        assert_eq!(unreachable("A => Zero | _ => Zero"), Vec::<String>::new());
+        assert_eq!(unreachable("_ => Zero | A => Zero"), vec!["A => Zero"]);
+    }
+
+    #[test]
+    fn recovering() {
+        let code = "let x : = a;\n\
+                    let y : Sum { A | B => | C } = b\n\
+                    let z : t = split { A => a | B x => };\n\
+                    let w : t = c;";
+        let (expr, errors) = parse_str_recovering(code);
+        let positions: Vec<_> = errors
+            .iter()
+            .map(|err| (err.span.line, err.span.column))
+            .collect();
+        assert_eq!(positions, vec![(1, 9), (2, 21), (2, 33), (3, 37)]);
+        assert_eq!(errors[0].expected, vec!["expression".to_string()]);
+        // The missing `;` is located after the code before it
+        assert_eq!(errors[2].expected, vec!["`;`".to_string()]);
+        assert_eq!(errors[2].span.start, errors[2].span.end);
+        assert_eq!(
+            format!("{}", expr),
+            "let z: t = split {A => a};\nlet w: t = c;\n\n\n"
+        );
+        let (_, errors) = parse_str_recovering("let x : t = a;");
+        assert!(errors.is_empty());
+        // Blank input is rejected like by `parse_str`
+        for code in ["", "  \n\n"] {
+            assert!(parse_str(code).is_err());
+            let (_, errors) = parse_str_recovering(code);
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].expected.contains(&"expression".to_string()));
+        }
+        let (_, errors) = parse_str_recovering("let x : t = a -- comment\n: b;");
+        assert_eq!((errors[0].span.line, errors[0].span.column), (1, 14));
+        let expected = ["operator", "expression", "projection", "`where`"];
+        assert_eq!(errors[0].expected, expected.map(String::from));
     }
 
     #[test]
@@ -1,78 +1,343 @@
 use core::fmt::Write;
 use std::fmt::{Display, Error as FmtError, Formatter};
 
+use either::Either;
+
 use crate::ast::*;
+use crate::case::{CasePattern, SourceClauses, DEFAULT_CLAUSE};
 use crate::check::read_back::*;
 
+/// Printing with the constructor chains `suc (suc .. zero)` of the natural number types in scope,
+/// declared by `BUILTIN NATURAL` pragmas, as decimals. The innermost one is the last of
+/// `naturals`.<br/>
+/// `Display` prints without any, see `with_naturals`.
+pub trait Pretty {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError>;
+}
+
+impl<T: Pretty + ?Sized> Pretty for Box<T> {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        (**self).pretty(naturals, f)
+    }
+}
+
+impl<L: Pretty, R: Pretty> Pretty for Either<L, R> {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Either::Left(left) => left.pretty(naturals, f),
+            Either::Right(right) => right.pretty(naturals, f),
+        }
+    }
+}
+
+/// Something printed with the constructor chains of some natural number types as decimals,
+/// see `with_naturals`.
+pub struct WithNaturals<'a, T: ?Sized> {
+    naturals: &'a [Natural],
+    inner: &'a T,
+}
+
+/// Print `inner` with the constructor chains `suc (suc .. zero)` of the natural number types of
+/// `naturals`, declared by `BUILTIN NATURAL` pragmas, as decimals.<br/>
+/// Since `0` and `1` are the unit value and the unit type, only numbers from `2` are printed.
+pub fn with_naturals<'a, T: Pretty + ?Sized>(
+    naturals: &'a [Natural],
+    inner: &'a T,
+) -> WithNaturals<'a, T> {
+    WithNaturals { naturals, inner }
+}
+
+impl<T: Pretty + ?Sized> Display for WithNaturals<'_, T> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.inner.pretty(self.naturals, f)
+    }
+}
+
+/// The number represented by the constructor call `name argument`, if it's a chain of one of
+/// `naturals` ending with the zero constructor applied to `0`, and the number can be printed
+/// as a decimal.<br/>
+/// `constructor` returns the name and the argument of a constructor call.
+fn natural_literal<'a, T>(
+    naturals: &[Natural],
+    name: &'a str,
+    argument: &'a T,
+    constructor: impl Fn(&'a T) -> Option<(&'a str, &'a T)>,
+    is_unit: impl Fn(&T) -> bool,
+) -> Option<usize> {
+    naturals.iter().rev().find_map(|natural| {
+        let (zero, suc) = (&natural.zero, &natural.suc);
+        let (mut name, mut argument, mut number) = (name, argument, 0);
+        while name == suc {
+            let (inner_name, inner_argument) = constructor(argument)?;
+            name = inner_name;
+            argument = inner_argument;
+            number += 1;
+        }
+        Some(number).filter(|&number| name == zero && is_unit(argument) && number >= 2)
+    })
+}
+
+fn expression_literal(naturals: &[Natural], name: &str, argument: &Expression) -> Option<usize> {
+    natural_literal(
+        naturals,
+        name,
+        argument,
+        |argument| match argument.unlocated() {
+            Expression::Constructor(name, argument) => Some((name, &**argument)),
+            _ => None,
+        },
+        |argument| *argument.unlocated() == Expression::Unit,
+    )
+}
+
+fn pattern_literal(naturals: &[Natural], pattern: &CasePattern) -> Option<usize> {
+    match pattern {
+        CasePattern::Constructor(name, fields, _) => natural_literal(
+            naturals,
+            name,
+            &**fields,
+            |fields| match fields {
+                CasePattern::Constructor(name, fields, _) => Some((name, &**fields)),
+                _ => None,
+            },
+            |fields| *fields == CasePattern::Pattern(Pattern::Unit),
+        ),
+        _ => None,
+    }
+}
+
+fn value_literal(naturals: &[Natural], name: &str, argument: &Value) -> Option<usize> {
+    natural_literal(
+        naturals,
+        name,
+        argument,
+        |argument| match argument {
+            Value::Constructor(name, argument) => Some((name, &**argument)),
+            _ => None,
+        },
+        |argument| matches!(argument, Value::Unit),
+    )
+}
+
+fn normal_literal(naturals: &[Natural], name: &str, argument: &NormalExpression) -> Option<usize> {
+    natural_literal(
+        naturals,
+        name,
+        argument,
+        |argument| match argument {
+            NormalExpression::Constructor(name, argument) => Some((name, &**argument)),
+            _ => None,
+        },
+        |argument| *argument == NormalExpression::Unit,
+    )
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for Value {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         match self {
             Value::Lambda(closure) => {
                 f.write_str("\u{03BB} ")?;
-                closure.fmt_with_type(f, None)
+                closure.fmt_with_type(naturals, f, None)
             }
-            Value::Pair(first, second) => write!(f, "({}, {})", first, second),
+            Value::Pair(first, second) => write!(
+                f,
+                "({}, {})",
+                with_naturals(naturals, first),
+                with_naturals(naturals, second)
+            ),
             Value::Unit => f.write_str("0"),
             Value::One => f.write_str("1"),
             Value::Pi(input, output) => {
+                if let Closure::Abstraction(pattern, _, body, _) = output {
+                    if *pattern.unlocated() == Pattern::Unit {
+                        fmt_value_atom(input, naturals, f)?;
+                        return write!(f, " -> {}", with_naturals(naturals, body));
+                    }
+                }
                 f.write_str("\u{03A0}")?;
                 f.write_str(" ")?;
-                output.fmt_with_type(f, Some(&**input))
+                output.fmt_with_type(naturals, f, Some(&**input))
             }
             Value::Type(level) => write!(f, "Type{}", level),
             Value::Sigma(first, second) => {
+                if let Closure::Abstraction(pattern, _, body, _) = second {
+                    if let Pattern::Var(name) = pattern.unlocated() {
+                        let rest = match body.unlocated() {
+                            Expression::One => Some(Vec::new()),
+                            Expression::Sigma(first, second) => record_fields(first, second),
+                            _ => None,
+                        };
+                        if let Some(mut fields) = rest {
+                            fields.insert(0, (name, &**first));
+                            return fmt_record(fields, naturals, f);
+                        }
+                    }
+                }
                 f.write_str("\u{03A3}")?;
                 f.write_str(" ")?;
-                second.fmt_with_type(f, Some(&**first))
+                second.fmt_with_type(naturals, f, Some(&**first))
+            }
+            Value::Constructor(name, argument) => {
+                if let Some(number) = value_literal(naturals, name, argument) {
+                    return number.fmt(f);
+                }
+                name.fmt(f)?;
+                if !matches!(**argument, Value::Unit) {
+                    f.write_char(' ')?;
+                    fmt_value_atom(argument, naturals, f)?;
+                }
+                Ok(())
             }
-            Value::Constructor(name, arguments) => write!(f, "{} {}", name, arguments),
             // Don't print context
             Value::Split(branches) => {
                 f.write_str("split {")?;
-                fmt_branch(branches, f)?;
+                fmt_branch(branches, naturals, f)?;
                 f.write_char('}')
             }
             // Don't print the context
             Value::Sum(constructors) => {
                 f.write_str("Sum {")?;
-                fmt_branch(constructors, f)?;
+                fmt_branch(constructors, naturals, f)?;
                 f.write_char('}')
             }
-            Value::Neutral(neutral) => write!(f, "[{}]", neutral),
+            Value::Neutral(neutral) => write!(f, "[{}]", with_naturals(naturals, neutral)),
+            Value::StringType => f.write_str("String"),
+            Value::CharType => f.write_str("Char"),
+            Value::Literal(literal) => literal.fmt(f),
+            Value::Primitive(primitive, arguments) => {
+                fmt_primitive(primitive, arguments, naturals, f)
+            }
         }
     }
 }
 
 impl Display for Expression {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for Expression {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         match self {
+            Expression::Var(name) if is_operator(name) => write!(f, "({})", name),
             Expression::Var(name) => name.fmt(f),
-            Expression::First(pair) => write!(f, "({}.1)", pair),
-            Expression::Second(pair) => write!(f, "({}.2)", pair),
-            Expression::Application(function, argument) => write!(f, "({} {})", function, argument),
+            Expression::First(pair) => {
+                fmt_atom(pair, naturals, f)?;
+                f.write_str(".1")
+            }
+            Expression::Second(pair) => {
+                fmt_atom(pair, naturals, f)?;
+                f.write_str(".2")
+            }
+            Expression::Field(record, name) => {
+                fmt_atom(record, naturals, f)?;
+                write!(f, ".{}", name)
+            }
+            Expression::Application(function, argument) => {
+                if let Some(call) = self.constructor_call() {
+                    return call.pretty(naturals, f);
+                }
+                f.write_char('(')?;
+                if let Some((operator, lhs)) = infix_operator(function) {
+                    fmt_atom(lhs, naturals, f)?;
+                    write!(f, " {} ", operator)?;
+                    fmt_atom(argument, naturals, f)?;
+                    return f.write_char(')');
+                }
+                fmt_application(function, naturals, f)?;
+                f.write_char(' ')?;
+                fmt_atom(argument, naturals, f)?;
+                f.write_char(')')
+            }
             Expression::Lambda(pattern, parameter_type, body) => {
-                f.write_str("\u{03BB} ")?;
-                pattern.fmt(f)?;
-                if let Some(parameter_type) = parameter_type {
-                    f.write_str(": ")?;
-                    parameter_type.internal.fmt(f)?;
+                f.write_str("\u{03BB}")?;
+                let mut group = vec![pattern];
+                let mut group_type = parameter_type;
+                let mut body = body.unlocated();
+                while let Expression::Lambda(pattern, parameter_type, inner) = body {
+                    if parameter_type != group_type {
+                        fmt_lambda_binders(&group, group_type, naturals, f)?;
+                        group.clear();
+                        group_type = parameter_type;
+                    }
+                    group.push(pattern);
+                    body = inner.unlocated();
                 }
+                fmt_lambda_binders(&group, group_type, naturals, f)?;
                 f.write_str(". ")?;
-                body.fmt(f)
+                body.pretty(naturals, f)
             }
-            Expression::Pair(first, second) => write!(f, "({}, {})", first, second),
+            Expression::Pair(first, second) => write!(
+                f,
+                "({}, {})",
+                with_naturals(naturals, first),
+                with_naturals(naturals, second)
+            ),
+            Expression::Record(fields) => {
+                f.write_char('{')?;
+                for (index, (name, field)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str("; ")?;
+                    }
+                    write!(f, "{} = {}", name, with_naturals(naturals, field))?;
+                }
+                f.write_char('}')
+            }
+            Expression::Ascription(expression, expression_type) => write!(
+                f,
+                "({}: {})",
+                with_naturals(naturals, expression),
+                with_naturals(naturals, expression_type)
+            ),
             Expression::Unit => f.write_str("0"),
             Expression::One => f.write_str("1"),
+            Expression::Pi(input, output) if *input.pattern.unlocated() == Pattern::Unit => {
+                fmt_atom(&input.expression, naturals, f)?;
+                write!(f, " -> {}", with_naturals(naturals, output))
+            }
             Expression::Pi(input, output) => {
-                f.write_str("\u{03A0}")?;
-                write!(f, " {}. {}", input, output)
+                fmt_telescope("\u{03A0}", input, output, naturals, f, |e| {
+                    match e.unlocated() {
+                        // Unnamed binders end the telescope and are printed as arrows
+                        Expression::Pi(input, output)
+                            if *input.pattern.unlocated() != Pattern::Unit =>
+                        {
+                            Some((input, output))
+                        }
+                        _ => None,
+                    }
+                })
             }
             Expression::Type(level) => write!(f, "Type{}", level),
             Expression::Sigma(first, second) => {
-                f.write_str("\u{03A3}")?;
-                write!(f, " {}. {}", first, second)
+                if let Some(fields) = record_fields(first, second) {
+                    return fmt_record(fields, naturals, f);
+                }
+                fmt_telescope("\u{03A3}", first, second, naturals, f, |e| {
+                    match e.unlocated() {
+                        Expression::Sigma(first, second) => Some((first, second)),
+                        _ => None,
+                    }
+                })
+            }
+            Expression::Constructor(name, argument) => {
+                if let Some(number) = expression_literal(naturals, name, argument) {
+                    return number.fmt(f);
+                }
+                name.fmt(f)?;
+                if *argument.unlocated() != Expression::Unit {
+                    f.write_char(' ')?;
+                    fmt_atom(argument, naturals, f)?;
+                }
+                Ok(())
             }
-            Expression::Constructor(name, arguments) => write!(f, "{} {}", name, arguments),
             Expression::Split(clauses) => {
                 f.write_str("split {")?;
                 let mut started = false;
@@ -82,15 +347,21 @@ impl Display for Expression {
                     } else {
                         started = true;
                     }
+                    if name == DEFAULT_CLAUSE {
+                        if let Expression::Lambda(Pattern::Unit, _, body) = clause.unlocated() {
+                            write!(f, "{} => {}", name, with_naturals(naturals, body))?;
+                            continue;
+                        }
+                    }
                     name.fmt(f)?;
                     f.write_char(' ')?;
-                    match &**clause {
+                    match clause.unlocated() {
                         Expression::Lambda(pattern, _, body) => {
                             pattern.fmt(f)?;
                             f.write_str(" => ")?;
-                            body.fmt(f)
+                            body.pretty(naturals, f)
                         }
-                        rest => rest.fmt(f),
+                        rest => rest.pretty(naturals, f),
                     }?;
                 }
                 f.write_char('}')
@@ -99,36 +370,310 @@ impl Display for Expression {
             Expression::Sum(constructors) => {
                 f.write_str("Sum")?;
                 f.write_str(" {")?;
-                fmt_branch(constructors, f)?;
+                fmt_branch(constructors, naturals, f)?;
                 f.write_char('}')
             }
-            Expression::Declaration(declaration, rest) => writeln!(f, "{};\n{}", declaration, rest),
-            Expression::Constant(pattern, body, rest) => {
-                write!(f, "const {} = {};\n{}", pattern, body, rest)
+            Expression::Declaration(declaration, rest) => writeln!(
+                f,
+                "{};\n{}",
+                with_naturals(naturals, declaration),
+                with_naturals(naturals, rest)
+            ),
+            Expression::Constant(pattern, body, rest) => write!(
+                f,
+                "const {} = {};\n{}",
+                pattern,
+                with_naturals(naturals, body),
+                with_naturals(naturals, rest)
+            ),
+            Expression::Fixity(fixity, rest) => {
+                writeln!(f, "{};\n{}", fixity, with_naturals(naturals, rest))
             }
+            Expression::Natural(natural, rest) => {
+                let naturals = [naturals, std::slice::from_ref(&**natural)].concat();
+                writeln!(f, "{}\n{}", natural, with_naturals(&naturals, rest))
+            }
+            Expression::Import(import, rest) => {
+                writeln!(f, "{};\n{}", import, with_naturals(naturals, rest))
+            }
+            Expression::Module(module, rest) => writeln!(
+                f,
+                "{}\n{}",
+                with_naturals(naturals, module),
+                with_naturals(naturals, rest)
+            ),
+            Expression::Open(open, rest) => {
+                writeln!(f, "{};\n{}", open, with_naturals(naturals, rest))
+            }
+            Expression::Export(export, rest) => {
+                writeln!(f, "{}\n{}", export, with_naturals(naturals, rest))
+            }
+            Expression::StringType => f.write_str("String"),
+            Expression::CharType => f.write_str("Char"),
+            Expression::Literal(literal) => literal.fmt(f),
+            Expression::Primitive(primitive) => primitive.fmt(f),
             Expression::Void => Ok(()),
+            Expression::Located(_, expression) => expression.pretty(naturals, f),
+            // The clauses are printed as they're written, the case tree has generated names
+            Expression::Clauses(clauses, _) => clauses.pretty(naturals, f),
             Expression::Merge(lhs, rhs) => {
-                lhs.fmt(f)?;
+                lhs.pretty(naturals, f)?;
                 f.write_str(" ++ ")?;
-                rhs.fmt(f)
+                rhs.pretty(naturals, f)
+            }
+        }
+    }
+}
+
+/// The fields of a record type `Σ x: A. Σ y: B. 1`, if all the patterns are field names.
+fn record_fields<'a>(
+    mut first: &'a Typed,
+    mut second: &'a Expression,
+) -> Option<Vec<(&'a str, &'a dyn Pretty)>> {
+    let mut fields: Vec<(&str, &dyn Pretty)> = Vec::new();
+    loop {
+        match first.pattern.unlocated() {
+            Pattern::Var(name) => fields.push((name, &*first.expression)),
+            _ => return None,
+        }
+        match second.unlocated() {
+            Expression::One => return Some(fields),
+            Expression::Sigma(inner_first, inner_second) => {
+                first = inner_first;
+                second = inner_second;
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Print a record type, `record {x: A; y: B}`.
+fn fmt_record(
+    fields: Vec<(&str, &dyn Pretty)>,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    f.write_str("record {")?;
+    for (index, (name, field_type)) in fields.into_iter().enumerate() {
+        if index > 0 {
+            f.write_str("; ")?;
+        }
+        write!(f, "{}: {}", name, with_naturals(naturals, field_type))?;
+    }
+    f.write_char('}')
+}
+
+/// Print nested `Π`s or `Σ`s in the compact form, `Π (a b: A) (c: B). T`.<br/>
+/// `nested` returns the binder and the body of the inner abstraction.
+fn fmt_telescope<'a>(
+    symbol: &str,
+    input: &'a Typed,
+    output: &'a Expression,
+    naturals: &[Natural],
+    f: &mut Formatter,
+    nested: impl Fn(&'a Expression) -> Option<(&'a Typed, &'a Expression)>,
+) -> Result<(), FmtError> {
+    f.write_str(symbol)?;
+    let (mut binder, mut output) = match nested(output) {
+        Some(inner) => inner,
+        None => {
+            return write!(
+                f,
+                " {}. {}",
+                with_naturals(naturals, input),
+                with_naturals(naturals, output)
+            )
+        }
+    };
+    let mut group = vec![&input.pattern];
+    let mut group_type = &input.expression;
+    loop {
+        if binder.expression != *group_type {
+            fmt_binder_group(&group, group_type, naturals, f)?;
+            group.clear();
+            group_type = &binder.expression;
+        }
+        group.push(&binder.pattern);
+        match nested(output) {
+            Some((inner_binder, inner_output)) => {
+                binder = inner_binder;
+                output = inner_output;
             }
+            None => break,
+        }
+    }
+    fmt_binder_group(&group, group_type, naturals, f)?;
+    write!(f, ". {}", with_naturals(naturals, output))
+}
+
+/// Print binders sharing the same type, ` (a b: A)`.
+fn fmt_binder_group(
+    patterns: &[&Pattern],
+    binder_type: &Expression,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    f.write_str(" (")?;
+    for (index, pattern) in patterns.iter().enumerate() {
+        if index > 0 {
+            f.write_char(' ')?;
+        }
+        pattern.fmt(f)?;
+    }
+    write!(f, ": {})", with_naturals(naturals, binder_type))
+}
+
+/// Print lambda parameters sharing the same type, ` a b` or ` (a b: A)`.
+fn fmt_lambda_binders(
+    patterns: &[&Pattern],
+    parameter_type: &Option<Box<Expression>>,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    match parameter_type {
+        Some(parameter_type) => fmt_binder_group(patterns, parameter_type, naturals, f),
+        None => patterns
+            .iter()
+            .try_for_each(|pattern| write!(f, " {}", pattern)),
+    }
+}
+
+/// Print the function part of an application without parenthesizing the spine,
+/// since application is left-associative.
+fn fmt_application(
+    function: &Expression,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    match function.unlocated() {
+        Expression::Application(function, argument) if infix_operator(function).is_none() => {
+            fmt_application(function, naturals, f)?;
+            f.write_char(' ')?;
+            fmt_atom(argument, naturals, f)
+        }
+        function => fmt_atom(function, naturals, f),
+    }
+}
+
+/// The operator and its left operand, if `function` is an operator applied to one operand,
+/// so the application of `function` is printed in the infix form.
+fn infix_operator(function: &Expression) -> Option<(&str, &Expression)> {
+    match function.unlocated() {
+        Expression::Application(operator, lhs) => match operator.unlocated() {
+            Expression::Var(name) if is_operator(name) => Some((name, lhs)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Print an expression, parenthesized unless it is self-delimited.
+fn fmt_atom(
+    expression: &Expression,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    use crate::ast::Expression::*;
+    if let Some(call) = expression.constructor_call() {
+        return fmt_atom(&call, naturals, f);
+    }
+    match expression {
+        Var(_)
+        | Unit
+        | One
+        | Type(_)
+        | StringType
+        | CharType
+        | Literal(_)
+        | Primitive(_)
+        | Sum(_)
+        | Split(_)
+        | Application(_, _)
+        | Pair(_, _)
+        | Record(_)
+        | Ascription(_, _)
+        | First(_)
+        | Second(_)
+        | Field(_, _) => expression.pretty(naturals, f),
+        Constructor(_, argument) if *argument.unlocated() == Unit => expression.pretty(naturals, f),
+        Constructor(name, argument) if expression_literal(naturals, name, argument).is_some() => {
+            expression.pretty(naturals, f)
+        }
+        Located(_, expression) => fmt_atom(expression, naturals, f),
+        Clauses(clauses, _) if clauses.scrutinee.is_none() => expression.pretty(naturals, f),
+        expression => write!(f, "({})", with_naturals(naturals, expression)),
+    }
+}
+
+/// Print a value, parenthesized unless it is self-delimited.
+fn fmt_value_atom(value: &Value, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+    match value {
+        Value::Constructor(_, argument) if matches!(**argument, Value::Unit) => {
+            value.pretty(naturals, f)
+        }
+        Value::Constructor(name, argument) if value_literal(naturals, name, argument).is_some() => {
+            value.pretty(naturals, f)
+        }
+        Value::Lambda(_) | Value::Pi(_, _) | Value::Sigma(_, _) | Value::Constructor(_, _) => {
+            write!(f, "({})", with_naturals(naturals, value))
+        }
+        value => value.pretty(naturals, f),
+    }
+}
+
+/// Print a normal form, parenthesized unless it is self-delimited.
+fn fmt_normal_atom(
+    normal: &NormalExpression,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    use crate::check::read_back::NormalExpression::*;
+    match normal {
+        Constructor(_, argument) if **argument == Unit => normal.pretty(naturals, f),
+        Constructor(name, argument) if normal_literal(naturals, name, argument).is_some() => {
+            normal.pretty(naturals, f)
+        }
+        Lambda(_, _) | Pi(_, _, _) | Sigma(_, _, _) | Constructor(_, _) => {
+            write!(f, "({})", with_naturals(naturals, normal))
         }
+        normal => normal.pretty(naturals, f),
     }
 }
 
-impl<Expr: Display, Value: Clone + Display> Display for GenericCase<Expr, Value> {
+impl<Expr: Pretty, Value: Clone> Display for GenericCase<Expr, Value> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
-        self.expression.fmt(f)
+        self.pretty(&[], f)
+    }
+}
+
+impl<Expr: Pretty, Value: Clone> Pretty for GenericCase<Expr, Value> {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        self.expression.pretty(naturals, f)
     }
 }
 
 impl Display for Typed {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
-        write!(f, "{}: {}", self.pattern, self.expression)
+        self.pretty(&[], f)
     }
 }
 
-fn fmt_branch<E: Display>(branch: &GenericBranch<E>, f: &mut Formatter) -> Result<(), FmtError> {
+impl Pretty for Typed {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        write!(
+            f,
+            "{}: {}",
+            self.pattern,
+            with_naturals(naturals, &self.expression)
+        )
+    }
+}
+
+fn fmt_branch<E: Pretty>(
+    branch: &GenericBranch<E>,
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
     let mut started = false;
     for (name, clause) in branch.iter() {
         if started {
@@ -138,7 +683,7 @@ fn fmt_branch<E: Display>(branch: &GenericBranch<E>, f: &mut Formatter) -> Resul
         }
         name.fmt(f)?;
         f.write_char(' ')?;
-        clause.fmt(f)?;
+        clause.pretty(naturals, f)?;
     }
     Ok(())
 }
@@ -149,120 +694,387 @@ impl Display for Pattern {
             Pattern::Pair(first, second) => write!(f, "({}, {})", first, second),
             Pattern::Unit => f.write_char('_'),
             Pattern::Var(name) => f.write_str(name.as_str()),
+            Pattern::Located(_, pattern) => pattern.fmt(f),
+        }
+    }
+}
+
+impl Display for CasePattern {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            CasePattern::Pattern(pattern) => pattern.fmt(f),
+            CasePattern::Pair(first, second) => write!(f, "({}, {})", first, second),
+            CasePattern::Constructor(name, fields, _) => {
+                name.fmt(f)?;
+                if **fields != CasePattern::Pattern(Pattern::Unit) {
+                    fmt_fields(fields, f)?;
+                }
+                Ok(())
+            }
+            CasePattern::Absurd(_) => f.write_char('!'),
+        }
+    }
+}
+
+/// The fields of a constructor pattern, separated like the arguments of a constructor call,
+/// ` a (Cons b c)` for `(a, Cons (b, c))`.
+fn fmt_fields(fields: &CasePattern, f: &mut Formatter) -> Result<(), FmtError> {
+    match fields {
+        CasePattern::Pair(first, second) => {
+            fmt_field(first, f)?;
+            fmt_fields(second, f)
+        }
+        CasePattern::Pattern(pattern) => fmt_pattern_fields(pattern, f),
+        field => fmt_field(field, f),
+    }
+}
+
+/// `fmt_fields` of irrefutable fields.
+fn fmt_pattern_fields(fields: &Pattern, f: &mut Formatter) -> Result<(), FmtError> {
+    match fields.unlocated() {
+        Pattern::Pair(first, second) => {
+            write!(f, " {}", first)?;
+            fmt_pattern_fields(second, f)
         }
+        field => write!(f, " {}", field),
     }
 }
 
+fn fmt_field(field: &CasePattern, f: &mut Formatter) -> Result<(), FmtError> {
+    match field {
+        CasePattern::Constructor(_, fields, _)
+            if **fields != CasePattern::Pattern(Pattern::Unit) =>
+        {
+            write!(f, " ({})", field)
+        }
+        field => write!(f, " {}", field),
+    }
+}
+
+impl Display for Associativity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        f.write_str(match self {
+            Associativity::Left => "infixl",
+            Associativity::Right => "infixr",
+        })
+    }
+}
+
+impl Pretty for SourceClauses {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        match &self.scrutinee {
+            Some(scrutinee) => write!(f, "match {} {{", with_naturals(naturals, scrutinee))?,
+            None => f.write_str("split {")?,
+        }
+        let mut started = false;
+        for ((pattern, body), _) in self.clauses.iter() {
+            if started {
+                f.write_str(" | ")?;
+            } else {
+                started = true;
+            }
+            match pattern_literal(naturals, pattern) {
+                Some(number) if self.scrutinee.is_none() => number.fmt(f)?,
+                _ => pattern.fmt(f)?,
+            }
+            if let Some(body) = body {
+                write!(f, " => {}", with_naturals(naturals, body))?;
+            }
+        }
+        f.write_char('}')
+    }
+}
+
+impl Display for Fixity {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(
+            f,
+            "{} {} {} = {}",
+            self.associativity, self.precedence, self.operator, self.name
+        )
+    }
+}
+
+impl Display for Natural {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(
+            f,
+            "{{-# BUILTIN NATURAL {} {} {} #-}}",
+            self.name, self.zero, self.suc
+        )
+    }
+}
+
+impl Display for Import {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "import {}", self.name())
+    }
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for Module {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        write!(
+            f,
+            "module {} {{\n{}}}",
+            self.name,
+            with_naturals(naturals, &self.body)
+        )
+    }
+}
+
+impl Display for Export {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "export ({})", self.names.join(", "))
+    }
+}
+
+impl Display for Open {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "open {}", self.name)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Literal::String(string) => write!(f, "{:?}", string),
+            Literal::Char(character) => write!(f, "{:?}", character),
+        }
+    }
+}
+
+impl Display for Primitive {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        f.write_str(self.name())
+    }
+}
+
+/// Print a built-in function applied to some arguments, `(primStringAppend "a")`.
+fn fmt_primitive<T: Pretty>(
+    primitive: &Primitive,
+    arguments: &[T],
+    naturals: &[Natural],
+    f: &mut Formatter,
+) -> Result<(), FmtError> {
+    if arguments.is_empty() {
+        return primitive.fmt(f);
+    }
+    write!(f, "({}", primitive)?;
+    for argument in arguments {
+        write!(f, " {}", with_naturals(naturals, argument))?;
+    }
+    f.write_char(')')
+}
+
 impl Display for Declaration {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for Declaration {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
+        if self.is_private {
+            f.write_str("private ")?;
+        }
         f.write_str(if self.is_recursive { "rec" } else { "let" })?;
         f.write_char(' ')?;
         self.pattern.fmt(f)?;
         for typed in self.prefix_parameters.iter() {
-            write!(f, "({})", typed)?;
+            write!(f, "({})", with_naturals(naturals, typed))?;
         }
         f.write_str(": ")?;
-        self.signature.fmt(f)?;
+        self.signature.pretty(naturals, f)?;
         f.write_str(" = ")?;
-        self.body.fmt(f)
+        self.body.pretty(naturals, f)
     }
 }
 
 impl Display for Closure {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for Closure {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         match self {
             // Don't print the scope
-            Closure::Abstraction(_, pt, _, _) => self.fmt_with_type(f, pt.as_ref().map(|t| &**t)),
-            e => e.fmt_with_type(f, None),
+            Closure::Abstraction(_, pt, _, _) => {
+                self.fmt_with_type(naturals, f, pt.as_ref().map(|t| &**t))
+            }
+            e => e.fmt_with_type(naturals, f, None),
         }
     }
 }
 
 impl Closure {
-    /// Actual implementation of `fmt` for `Closure`
-    pub fn fmt_with_type(&self, f: &mut Formatter, t: Option<&Value>) -> Result<(), FmtError> {
+    /// Actual implementation of `pretty` for `Closure`
+    pub fn fmt_with_type(
+        &self,
+        naturals: &[Natural],
+        f: &mut Formatter,
+        t: Option<&Value>,
+    ) -> Result<(), FmtError> {
         match self {
             Closure::Abstraction(pattern, _, body, _) => {
                 pattern.fmt(f)?;
                 if let Some(t) = t {
                     f.write_str(": ")?;
-                    t.fmt(f)?;
+                    t.pretty(naturals, f)?;
                 }
                 f.write_str(". ")?;
-                body.fmt(f)
+                body.pretty(naturals, f)
             }
-            Closure::Value(value) => value.fmt(f),
-            Closure::Choice(rest, name) => write!(f, "{}. {}", name, rest),
+            Closure::Value(value) => value.pretty(naturals, f),
+            Closure::Choice(rest, name) => write!(f, "{}. {}", name, with_naturals(naturals, rest)),
         }
     }
 }
 
-impl<Value: Display + Clone> Display for GenericNeutral<Value> {
+impl<Value: Pretty + Clone> Display for GenericNeutral<Value> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl<Value: Pretty + Clone> Pretty for GenericNeutral<Value> {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         match self {
             GenericNeutral::Generated(index) => write!(f, "<{}>", index),
-            GenericNeutral::Application(function, argument) => {
-                write!(f, "({} {})", function, argument)
-            }
-            GenericNeutral::First(pair) => write!(f, "({}.1)", pair),
-            GenericNeutral::Second(pair) => write!(f, "({}.2)", pair),
+            GenericNeutral::Postulate(_, name) => f.write_str(name),
+            GenericNeutral::Application(function, argument) => write!(
+                f,
+                "({} {})",
+                with_naturals(naturals, function),
+                with_naturals(naturals, argument)
+            ),
+            GenericNeutral::First(pair) => write!(f, "({}.1)", with_naturals(naturals, pair)),
+            GenericNeutral::Second(pair) => write!(f, "({}.2)", with_naturals(naturals, pair)),
             GenericNeutral::Split(clauses, argument) => {
-                write!(f, "app {} {{", argument)?;
-                fmt_branch(clauses, f)?;
+                write!(f, "app {} {{", with_naturals(naturals, argument))?;
+                fmt_branch(clauses, naturals, f)?;
                 f.write_char('}')
             }
+            GenericNeutral::Primitive(primitive, arguments) => {
+                fmt_primitive(primitive, arguments, naturals, f)
+            }
         }
     }
 }
 
 impl Display for NormalExpression {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl Pretty for NormalExpression {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         use crate::check::read_back::NormalExpression as Expression;
         match self {
             Expression::Lambda(index, expression) => {
                 f.write_str("\u{03BB} <")?;
-                write!(f, "{}> {}", index, expression)
+                write!(f, "{}> {}", index, with_naturals(naturals, expression))
             }
-            Expression::Pair(first, second) => write!(f, "({}, {})", first, second),
+            Expression::Pair(first, second) => write!(
+                f,
+                "({}, {})",
+                with_naturals(naturals, first),
+                with_naturals(naturals, second)
+            ),
             Expression::Unit => f.write_str("0"),
             Expression::One => f.write_str("1"),
             Expression::Pi(input, index, output) => {
                 f.write_str("\u{03A0}")?;
-                write!(f, " <{}> {}. {}", index, input, output)
+                write!(
+                    f,
+                    " <{}> {}. {}",
+                    index,
+                    with_naturals(naturals, input),
+                    with_naturals(naturals, output)
+                )
             }
             Expression::Type(level) => write!(f, "Type{}", level),
             Expression::Sigma(first, index, second) => {
                 f.write_str("\u{03A3}")?;
-                write!(f, " <{}> {}. {}", index, first, second)
+                write!(
+                    f,
+                    " <{}> {}. {}",
+                    index,
+                    with_naturals(naturals, first),
+                    with_naturals(naturals, second)
+                )
+            }
+            Expression::Constructor(name, argument) => {
+                if let Some(number) = normal_literal(naturals, name, argument) {
+                    return number.fmt(f);
+                }
+                name.fmt(f)?;
+                if **argument != Expression::Unit {
+                    f.write_char(' ')?;
+                    fmt_normal_atom(argument, naturals, f)?;
+                }
+                Ok(())
             }
-            Expression::Constructor(name, arguments) => write!(f, "{} {}", name, arguments),
             Expression::Split(clauses) => {
                 f.write_str("split {")?;
-                fmt_branch(clauses, f)?;
+                fmt_branch(clauses, naturals, f)?;
                 f.write_char('}')
             }
             // Don't print the context
             Expression::Sum(constructors) => {
                 f.write_str("Sum {")?;
-                fmt_branch(constructors, f)?;
+                fmt_branch(constructors, naturals, f)?;
                 f.write_char('}')
             }
-            Expression::Neutral(neutral) => write!(f, "[{}]", neutral),
+            Expression::Neutral(neutral) => write!(f, "[{}]", with_naturals(naturals, neutral)),
+            Expression::StringType => f.write_str("String"),
+            Expression::CharType => f.write_str("Char"),
+            Expression::Literal(literal) => literal.fmt(f),
+            Expression::Primitive(primitive, arguments) => {
+                fmt_primitive(primitive, arguments, naturals, f)
+            }
         }
     }
 }
 
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
 /// Actually it's for NeutralTelescope
-impl<Value: Clone + Display> Display for GenericTelescope<Value> {
+impl<Value: Clone + Pretty> Display for GenericTelescope<Value> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        self.pretty(&[], f)
+    }
+}
+
+impl<Value: Clone + Pretty> Pretty for GenericTelescope<Value> {
+    fn pretty(&self, naturals: &[Natural], f: &mut Formatter) -> Result<(), FmtError> {
         match self {
             GenericTelescope::Nil => Ok(()),
-            GenericTelescope::UpDec(previous, declaration) => {
-                write!(f, "{};\n{}", declaration, previous)
-            }
-            GenericTelescope::UpVar(previous, pattern, value) => {
-                write!(f, "var {}: {};\n{}", pattern, value, previous)
-            }
+            GenericTelescope::UpDec(previous, declaration) => write!(
+                f,
+                "{};\n{}",
+                with_naturals(naturals, declaration),
+                with_naturals(naturals, &**previous)
+            ),
+            GenericTelescope::UpVar(previous, pattern, value) => write!(
+                f,
+                "var {}: {};\n{}",
+                pattern,
+                with_naturals(naturals, value),
+                with_naturals(naturals, &**previous)
+            ),
         }
     }
 }
@@ -0,0 +1,399 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use either::Either;
+use serde::{Deserialize, Serialize};
+
+use crate::ast::*;
+use crate::check::tcm::{GammaRaw, TCS};
+
+/// Position of a context in `SerialModule::contexts`.
+type Index = usize;
+
+/// `Value`, where the contexts are referred to by their positions.
+#[derive(Serialize, Deserialize)]
+enum SerialValue {
+    Lambda(SerialClosure),
+    Unit,
+    One,
+    Type(Level),
+    Pi(Box<Self>, SerialClosure),
+    Sigma(Box<Self>, SerialClosure),
+    Pair(Box<Self>, Box<Self>),
+    Constructor(String, Box<Self>),
+    Split(SerialCaseTree),
+    Sum(SerialCaseTree),
+    Neutral(SerialNeutral),
+    StringType,
+    CharType,
+    Literal(Literal),
+    Primitive(Primitive, Vec<Self>),
+}
+
+/// `Closure`, where the context is referred to by its position.
+#[derive(Serialize, Deserialize)]
+enum SerialClosure {
+    Abstraction(Pattern, Option<Box<SerialValue>>, Expression, Index),
+    Value(Box<SerialValue>),
+    Choice(Box<Self>, String),
+}
+
+/// `Case`, where the context is referred to by its position.
+#[derive(Serialize, Deserialize)]
+struct SerialCase {
+    expression: Either<SerialValue, Expression>,
+    context: Index,
+}
+
+type SerialCaseTree = BTreeMap<String, SerialCase>;
+
+/// `Neutral`, where the contexts are referred to by their positions.
+#[derive(Serialize, Deserialize)]
+enum SerialNeutral {
+    Generated(u32),
+    Postulate(u32, String),
+    Application(Box<Self>, Box<SerialValue>),
+    First(Box<Self>),
+    Second(Box<Self>),
+    Split(SerialCaseTree, Box<Self>),
+    Primitive(Primitive, Vec<SerialValue>),
+}
+
+/// `Telescope`, where the context it extends is referred to by its position.
+#[derive(Serialize, Deserialize)]
+enum SerialTelescope {
+    Nil,
+    UpDec(Index, Declaration),
+    UpVar(Index, Pattern, SerialValue),
+}
+
+/// A checked module: the types of the definitions in Gamma, and their values.<br/>
+/// It can be saved and loaded instead of checking the module again.
+///
+/// The values share the contexts they capture, which are stored once. This is why the values are
+/// not read back: normal forms copy the contexts, which may be exponentially larger.
+#[derive(Serialize, Deserialize)]
+pub struct SerialModule {
+    /// The contexts captured by the values, each one after the one it extends.
+    contexts: Vec<SerialTelescope>,
+    /// The names, the types and the values of the definitions.
+    definitions: Vec<(String, SerialValue, SerialValue)>,
+    /// The names hidden from the module, with the modules defining them.
+    hidden: BTreeMap<String, String>,
+}
+
+impl SerialModule {
+    /// The definitions of a module, from its state after the type-checking.
+    pub fn new(tcs: &TCS) -> Result<Self, String> {
+        let mut encoder = Encoder::default();
+        let definitions = (tcs.gamma.iter())
+            .map(|(name, signature)| {
+                let value = tcs.context.resolve(name)?;
+                Ok((
+                    name.clone(),
+                    encoder.value(signature),
+                    encoder.value(&value),
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            contexts: encoder.contexts,
+            definitions,
+            hidden: (*tcs.hidden).clone(),
+        })
+    }
+
+    /// The state of the checked module, where the definitions are bound in the context.
+    pub fn into_tcs<'a>(self) -> Result<TCS<'a>, String> {
+        let decoder = Decoder::new(self.contexts)?;
+        let mut gamma = GammaRaw::new();
+        let mut context = nil_rc();
+        for (name, signature, value) in self.definitions {
+            gamma.insert(name.clone(), decoder.value(signature)?);
+            context = up_var_rc(context, Pattern::Var(name), decoder.value(value)?);
+        }
+        let hidden = Rc::new(self.hidden);
+        Ok(TCS::with_shared(
+            Cow::Owned(gamma),
+            context,
+            Default::default(),
+            hidden,
+        ))
+    }
+}
+
+#[derive(Default)]
+struct Encoder {
+    contexts: Vec<SerialTelescope>,
+    /// Positions of the encoded contexts, by their addresses.
+    indices: HashMap<*const GenericTelescope<Value>, Index>,
+    /// The encoded contexts, kept alive so their addresses are not reused.
+    encoded: Vec<Telescope>,
+}
+
+impl Encoder {
+    fn context(&mut self, context: &Telescope) -> Index {
+        use crate::ast::GenericTelescope::*;
+        // The contexts extended by this one that are not encoded yet, the innermost first
+        let mut pending = Vec::new();
+        let mut current = context.clone();
+        while !self.indices.contains_key(&Rc::as_ptr(&current)) {
+            pending.push(current.clone());
+            current = match &*current {
+                Nil => break,
+                UpDec(parent, _) | UpVar(parent, _, _) => parent.clone(),
+            };
+        }
+        for context in pending.into_iter().rev() {
+            let serial = match &*context {
+                Nil => SerialTelescope::Nil,
+                UpDec(parent, declaration) => {
+                    SerialTelescope::UpDec(self.indices[&Rc::as_ptr(parent)], declaration.clone())
+                }
+                UpVar(parent, pattern, value) => {
+                    let parent = self.indices[&Rc::as_ptr(parent)];
+                    SerialTelescope::UpVar(parent, pattern.clone(), self.value(value))
+                }
+            };
+            self.indices
+                .insert(Rc::as_ptr(&context), self.contexts.len());
+            self.contexts.push(serial);
+            self.encoded.push(context);
+        }
+        self.indices[&Rc::as_ptr(context)]
+    }
+
+    fn value(&mut self, value: &Value) -> SerialValue {
+        use crate::ast::Value::*;
+        match value {
+            Lambda(closure) => SerialValue::Lambda(self.closure(closure)),
+            Unit => SerialValue::Unit,
+            One => SerialValue::One,
+            Type(level) => SerialValue::Type(*level),
+            Pi(input, output) => SerialValue::Pi(Box::new(self.value(input)), self.closure(output)),
+            Sigma(first, second) => {
+                SerialValue::Sigma(Box::new(self.value(first)), self.closure(second))
+            }
+            Pair(first, second) => {
+                SerialValue::Pair(Box::new(self.value(first)), Box::new(self.value(second)))
+            }
+            Constructor(name, payload) => {
+                SerialValue::Constructor(name.clone(), Box::new(self.value(payload)))
+            }
+            Split(case_tree) => SerialValue::Split(self.case_tree(case_tree)),
+            Sum(case_tree) => SerialValue::Sum(self.case_tree(case_tree)),
+            Neutral(neutral) => SerialValue::Neutral(self.neutral(neutral)),
+            StringType => SerialValue::StringType,
+            CharType => SerialValue::CharType,
+            Literal(literal) => SerialValue::Literal(literal.clone()),
+            Primitive(primitive, arguments) => {
+                SerialValue::Primitive(primitive.clone(), self.values(arguments))
+            }
+        }
+    }
+
+    fn values(&mut self, values: &[Value]) -> Vec<SerialValue> {
+        values.iter().map(|value| self.value(value)).collect()
+    }
+
+    fn closure(&mut self, closure: &Closure) -> SerialClosure {
+        match closure {
+            Closure::Abstraction(pattern, parameter_type, body, context) => {
+                let parameter_type = parameter_type.as_ref().map(|t| Box::new(self.value(t)));
+                let context = self.context(context);
+                SerialClosure::Abstraction(pattern.clone(), parameter_type, body.clone(), context)
+            }
+            Closure::Value(value) => SerialClosure::Value(Box::new(self.value(value))),
+            Closure::Choice(closure, name) => {
+                SerialClosure::Choice(Box::new(self.closure(closure)), name.clone())
+            }
+        }
+    }
+
+    fn case_tree(&mut self, case_tree: &CaseTree) -> SerialCaseTree {
+        (case_tree.iter())
+            .map(|(name, case)| {
+                let expression = match &case.expression {
+                    Either::Left(value) => Either::Left(self.value(value)),
+                    Either::Right(expression) => Either::Right(expression.clone()),
+                };
+                let context = self.context(&case.context);
+                (
+                    name.clone(),
+                    SerialCase {
+                        expression,
+                        context,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn neutral(&mut self, neutral: &Neutral) -> SerialNeutral {
+        use crate::ast::GenericNeutral::*;
+        match neutral {
+            Generated(index) => SerialNeutral::Generated(*index),
+            Postulate(index, name) => SerialNeutral::Postulate(*index, name.clone()),
+            Application(function, argument) => SerialNeutral::Application(
+                Box::new(self.neutral(function)),
+                Box::new(self.value(argument)),
+            ),
+            First(pair) => SerialNeutral::First(Box::new(self.neutral(pair))),
+            Second(pair) => SerialNeutral::Second(Box::new(self.neutral(pair))),
+            Split(case_tree, argument) => {
+                SerialNeutral::Split(self.case_tree(case_tree), Box::new(self.neutral(argument)))
+            }
+            Primitive(primitive, arguments) => {
+                SerialNeutral::Primitive(primitive.clone(), self.values(arguments))
+            }
+        }
+    }
+}
+
+struct Decoder {
+    contexts: Vec<Telescope>,
+}
+
+impl Decoder {
+    fn new(contexts: Vec<SerialTelescope>) -> Result<Self, String> {
+        let mut decoder = Self {
+            contexts: Vec::with_capacity(contexts.len()),
+        };
+        for context in contexts {
+            let context = match context {
+                SerialTelescope::Nil => nil_rc(),
+                SerialTelescope::UpDec(parent, declaration) => {
+                    up_dec_rc(decoder.context(parent)?, declaration)
+                }
+                SerialTelescope::UpVar(parent, pattern, value) => {
+                    up_var_rc(decoder.context(parent)?, pattern, decoder.value(value)?)
+                }
+            };
+            decoder.contexts.push(context);
+        }
+        Ok(decoder)
+    }
+
+    fn context(&self, index: Index) -> Result<Telescope, String> {
+        (self.contexts.get(index).cloned())
+            .ok_or_else(|| format!("Invalid reference to the context {}.", index))
+    }
+
+    fn value(&self, value: SerialValue) -> Result<Value, String> {
+        use crate::ast::Value::*;
+        Ok(match value {
+            SerialValue::Lambda(closure) => Lambda(self.closure(closure)?),
+            SerialValue::Unit => Unit,
+            SerialValue::One => One,
+            SerialValue::Type(level) => Type(level),
+            SerialValue::Pi(input, output) => {
+                Pi(Box::new(self.value(*input)?), self.closure(output)?)
+            }
+            SerialValue::Sigma(first, second) => {
+                Sigma(Box::new(self.value(*first)?), self.closure(second)?)
+            }
+            SerialValue::Pair(first, second) => Pair(
+                Box::new(self.value(*first)?),
+                Box::new(self.value(*second)?),
+            ),
+            SerialValue::Constructor(name, payload) => {
+                Constructor(name, Box::new(self.value(*payload)?))
+            }
+            SerialValue::Split(case_tree) => Split(self.case_tree(case_tree)?),
+            SerialValue::Sum(case_tree) => Sum(self.case_tree(case_tree)?),
+            SerialValue::Neutral(neutral) => Neutral(self.neutral(neutral)?),
+            SerialValue::StringType => StringType,
+            SerialValue::CharType => CharType,
+            SerialValue::Literal(literal) => Literal(literal),
+            SerialValue::Primitive(primitive, arguments) => {
+                Primitive(primitive, self.values(arguments)?)
+            }
+        })
+    }
+
+    fn values(&self, values: Vec<SerialValue>) -> Result<Vec<Value>, String> {
+        values.into_iter().map(|value| self.value(value)).collect()
+    }
+
+    fn closure(&self, closure: SerialClosure) -> Result<Closure, String> {
+        Ok(match closure {
+            SerialClosure::Abstraction(pattern, parameter_type, body, context) => {
+                let parameter_type = match parameter_type {
+                    Some(parameter_type) => Some(Box::new(self.value(*parameter_type)?)),
+                    None => None,
+                };
+                let context = Box::new(self.context(context)?);
+                Closure::Abstraction(pattern, parameter_type, body, context)
+            }
+            SerialClosure::Value(value) => Closure::Value(Box::new(self.value(*value)?)),
+            SerialClosure::Choice(closure, name) => {
+                Closure::Choice(Box::new(self.closure(*closure)?), name)
+            }
+        })
+    }
+
+    fn case_tree(&self, case_tree: SerialCaseTree) -> Result<CaseTree, String> {
+        (case_tree.into_iter())
+            .map(|(name, case)| {
+                let expression = match case.expression {
+                    Either::Left(value) => Either::Left(self.value(value)?),
+                    Either::Right(expression) => Either::Right(expression),
+                };
+                let case = Case::new(expression, self.context(case.context)?);
+                Ok((name, Box::new(case)))
+            })
+            .collect()
+    }
+
+    fn neutral(&self, neutral: SerialNeutral) -> Result<Neutral, String> {
+        use crate::ast::GenericNeutral::*;
+        Ok(match neutral {
+            SerialNeutral::Generated(index) => Generated(index),
+            SerialNeutral::Postulate(index, name) => Postulate(index, name),
+            SerialNeutral::Application(function, argument) => Application(
+                Box::new(self.neutral(*function)?),
+                Box::new(self.value(*argument)?),
+            ),
+            SerialNeutral::First(pair) => First(Box::new(self.neutral(*pair)?)),
+            SerialNeutral::Second(pair) => Second(Box::new(self.neutral(*pair)?)),
+            SerialNeutral::Split(case_tree, argument) => Split(
+                self.case_tree(case_tree)?,
+                Box::new(self.neutral(*argument)?),
+            ),
+            SerialNeutral::Primitive(primitive, arguments) => {
+                Primitive(primitive, self.values(arguments)?)
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::SerialModule;
+    use crate::check::check_main;
+    use crate::check::read_back::ReadBack;
+    use crate::parser::parse_str_err_printed;
+
+    #[test]
+    fn round_trip() {
+        let code = "data nat = Zero | Suc nat;\n\
+                    rec plus: nat -> nat -> nat = \\lambda m. split\n\
+                    { Zero => m | Suc n => Suc (plus m n) };\n\
+                    let two: nat = double (Suc Zero) where {\n\
+                    let double (n: nat): nat = plus n n;\n\
+                    };";
+        let tcs = check_main(parse_str_err_printed(code).unwrap()).unwrap();
+        let module = SerialModule::new(&tcs).unwrap();
+        let loaded = module.into_tcs().unwrap();
+        assert_eq!(tcs.gamma.len(), loaded.gamma.len());
+        for (name, signature) in tcs.gamma.iter() {
+            let loaded_signature = loaded.gamma[name].clone();
+            let signature = signature.clone().read_back_please();
+            assert_eq!(signature, loaded_signature.read_back_please());
+            let value = tcs.context.resolve(name).unwrap().read_back_please();
+            let loaded_value = loaded.context.resolve(name).unwrap().read_back_please();
+            assert_eq!(value, loaded_value);
+        }
+    }
+}